@@ -35,6 +35,7 @@
 //! ```bash
 //! # nft delete table inet example-table
 //! ```
+//! or, from Rust, by sending a batch built with `Batch::delete_table`.
 
 use ipnetwork::{IpNetwork, Ipv4Network};
 use rustables::{
@@ -0,0 +1,54 @@
+///! Adds a bridge table with a chain filtering the packets forwarded between the ports of the
+///! bridges, which drops the IPv4 packets coming from a given address.
+///!
+///! Run the following to print out current active tables, chains and rules in netfilter. Must be
+///! executed as root:
+///! ```bash
+///! # nft list ruleset
+///! ```
+///! After running this example, the output should be the following:
+///! ```ignore
+///! table bridge example-filter-bridge {
+///!         chain chain-for-bridged-packets {
+///!                 type filter hook forward priority 0; policy accept;
+///!                 meta protocol ip ip saddr 10.0.0.1 drop
+///!         }
+///! }
+///! ```
+///!
+///!
+///! Everything created by this example can be removed by running
+///! ```bash
+///! # nft delete table bridge example-filter-bridge
+///! ```
+use std::net::Ipv4Addr;
+
+use rustables::{Batch, Chain, ChainPolicy, HookClass, MsgType, ProtocolFamily, Rule, Table};
+
+const TABLE_NAME: &str = "example-filter-bridge";
+const FORWARD_CHAIN_NAME: &str = "chain-for-bridged-packets";
+
+fn main() {
+    let mut batch = Batch::new();
+    let table = Table::new(ProtocolFamily::Bridge).with_name(TABLE_NAME);
+    batch.add(&table, MsgType::Add);
+
+    // The bridge family has the same hooks as the IP families, but a chain of an arp table could
+    // not register on the forward hook, which `with_base_hook` checks.
+    let forward_chain = Chain::new(&table)
+        .with_name(FORWARD_CHAIN_NAME)
+        .with_base_hook(HookClass::Forward, 0)
+        .unwrap()
+        .with_policy(ChainPolicy::Accept);
+    batch.add(&forward_chain, MsgType::Add);
+
+    // In a bridge table, the address match is guarded by the ethertype of the frame rather than
+    // by the netfilter protocol, which the bridged packets don't have.
+    let rule = Rule::new(&forward_chain)
+        .unwrap()
+        .saddr(Ipv4Addr::new(10, 0, 0, 1).into())
+        .drop();
+    batch.add(&rule, MsgType::Add);
+
+    batch.send().unwrap();
+}
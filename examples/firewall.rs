@@ -6,7 +6,7 @@ use ipnetwork::IpNetwork;
 use rustables::error::{BuilderError, QueryError};
 use rustables::expr::Log;
 use rustables::{
-    Batch, Chain, ChainPolicy, Hook, HookClass, MsgType, Protocol, ProtocolFamily, Rule, Table,
+    Batch, Chain, ChainPolicy, Hook, HookClass, MsgType, Protocol, ProtocolFamily, RuleGroup, Table,
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -70,45 +70,34 @@ impl Firewall {
     }
     /// Allow some common-sense exceptions to inbound drop, and accept outbound and forward.
     pub fn start(mut self) -> Result<(), Error> {
-        // Allow all established connections to get in.
-        Rule::new(&self.inbound)?
-            .established()?
-            .accept()
-            .add_to_batch(&mut self.batch);
-        // Allow all traffic on the loopback interface.
-        Rule::new(&self.inbound)?
-            .iface("lo")?
-            .accept()
-            .add_to_batch(&mut self.batch);
-        // Allow ssh from anywhere, and log to dmesg with a prefix.
-        Rule::new(&self.inbound)?
-            .dport(22, Protocol::TCP)
-            .accept()
-            .with_expr(Log::new(None, Some("allow ssh connection:"))?)
-            .add_to_batch(&mut self.batch);
-
-        // Allow http from all IPs in 192.168.1.255/24 .
-        let local_net = IpNetwork::new([192, 168, 1, 0].into(), 24).unwrap();
-        Rule::new(&self.inbound)?
-            .dport(80, Protocol::TCP)
-            .snetwork(local_net)?
-            .accept()
-            .add_to_batch(&mut self.batch);
-
-        // Allow ICMP traffic, drop IGMP.
-        Rule::new(&self.inbound)?
-            .icmp()
-            .accept()
-            .add_to_batch(&mut self.batch);
-        Rule::new(&self.inbound)?
-            .igmp()
-            .drop()
-            .add_to_batch(&mut self.batch);
-
-        // Log all traffic not accepted to NF_LOG group 1, accessible with ulogd.
-        Rule::new(&self.inbound)?
-            .with_expr(Log::new(Some(1), None::<String>)?)
-            .add_to_batch(&mut self.batch);
+        let inbound = RuleGroup::new(&self.inbound)?;
+        let rules = vec![
+            // Allow all established connections to get in.
+            inbound.rule().established()?.accept(),
+            // Allow all traffic on the loopback interface.
+            inbound.rule().iface("lo")?.accept(),
+            // Allow ssh from anywhere, and log to dmesg with a prefix.
+            inbound
+                .rule()
+                .dport(22, Protocol::TCP)
+                .accept()
+                .with_expr(Log::new(None, Some("allow ssh connection:"))?),
+            // Allow http from all IPs in 192.168.1.255/24 .
+            inbound
+                .rule()
+                .dport(80, Protocol::TCP)
+                .snetwork(IpNetwork::new([192, 168, 1, 0].into(), 24).unwrap())?
+                .accept(),
+            // Allow ICMP traffic, drop IGMP.
+            inbound.rule().icmp().accept(),
+            inbound.rule().igmp().drop(),
+            // Answer the connection attempts to the other TCP ports with a reset, so that SYN
+            // scans see closed ports rather than filtered ones.
+            inbound.rule().syn()?.reject_tcp_reset(),
+            // Log all traffic not accepted to NF_LOG group 1, accessible with ulogd.
+            inbound.rule().with_expr(Log::new(Some(1), None::<String>)?),
+        ];
+        RuleGroup::add_all(&mut self.batch, rules);
 
         self.batch.send()?;
         println!("table {} commited", TABLE_NAME);
@@ -116,8 +105,7 @@ impl Firewall {
     }
     /// If there is any table with name TABLE_NAME, remove it.
     pub fn stop(mut self) -> Result<(), Error> {
-        self.batch.add(&self.table, MsgType::Add);
-        self.batch.add(&self.table, MsgType::Del);
+        self.batch.delete_table(&self.table);
 
         self.batch.send()?;
         println!("table {} destroyed", TABLE_NAME);
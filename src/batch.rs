@@ -1,15 +1,33 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::Deref;
+use std::os::unix::prelude::RawFd;
+
 use libc;
 
 use thiserror::Error;
 
-use crate::error::QueryError;
-use crate::nlmsg::{NfNetlinkObject, NfNetlinkWriter};
-use crate::sys::NFNL_SUBSYS_NFTABLES;
-use crate::{MsgType, ProtocolFamily};
-
-use nix::sys::socket::{
-    self, AddressFamily, MsgFlags, NetlinkAddr, SockAddr, SockFlag, SockProtocol, SockType,
+use crate::error::{BuilderError, DecodeError, QueryError};
+use crate::expr::ConnTrackState;
+use crate::nlmsg::{
+    get_operation_from_nlmsghdr_type, pad_netlink_object_with_variable_size,
+    NfNetlinkDeserializable, NfNetlinkObject, NfNetlinkWriter,
 };
+use crate::parser::get_nlmsghdr;
+use crate::query::SocketOptions;
+use crate::set::{SetElement, SetElementList};
+use crate::sys::{
+    nlmsghdr, NFNL_MSG_BATCH_BEGIN, NFNL_SUBSYS_NFTABLES, NFT_MSG_DELCHAIN, NFT_MSG_DELFLOWTABLE,
+    NFT_MSG_DELOBJ, NFT_MSG_DELRULE, NFT_MSG_DELSET, NFT_MSG_DELSETELEM, NFT_MSG_DELTABLE,
+    NFT_MSG_NEWCHAIN, NFT_MSG_NEWFLOWTABLE, NFT_MSG_NEWOBJ, NFT_MSG_NEWRULE, NFT_MSG_NEWSET,
+    NFT_MSG_NEWSETELEM, NFT_MSG_NEWTABLE, NLM_F_ECHO, NLM_F_EXCL, NLM_F_REPLACE,
+};
+use crate::{Chain, Flowtable, MsgType, Object, Protocol, ProtocolFamily, Rule, Set, Table};
+
+use nix::sys::socket::{self, MsgFlags, NetlinkAddr, SockAddr};
+
+/// Processes a message echoed by the kernel, e.g. to collect the handles of the new objects.
+type EchoCallback<T> = dyn Fn(&[u8], &mut T) -> Result<(), QueryError>;
 
 /// Error while communicating with netlink.
 #[derive(Error, Debug)]
@@ -60,6 +78,108 @@ impl Batch {
         self.seq += 1;
     }
 
+    /// Adds the given message to this batch, asking the kernel to echo back the object it
+    /// creates, and returns the sequence number of the message. After the batch is sent with
+    /// [`Batch::send_and_get_handles`], the handle assigned to the object can be retrieved from
+    /// this sequence number. The echoed rules can also be retrieved whole with
+    /// [`Batch::send_and_get_rules`].
+    ///
+    /// [`Batch::send_and_get_handles`]: struct.Batch.html#method.send_and_get_handles
+    /// [`Batch::send_and_get_rules`]: struct.Batch.html#method.send_and_get_rules
+    pub fn add_with_echo<T: NfNetlinkObject>(&mut self, msg: &T, msg_type: MsgType) -> u32 {
+        let seq = self.seq;
+        let start = self.buf.len();
+        self.add(msg, msg_type);
+        // Safe because `add` just wrote a complete netlink message at `start`
+        let hdr = unsafe { &mut *(self.buf[start..].as_mut_ptr() as *mut nlmsghdr) };
+        hdr.nlmsg_flags |= NLM_F_ECHO as u16;
+        seq
+    }
+
+    /// Adds `elements` to `set`, which may be a set created in this same batch. The elements
+    /// are packed in as few messages as possible, which is much faster than adding them one
+    /// by one when there are many of them. The netlink attribute holding the elements of a
+    /// message being limited to 64 KBytes, large lists are split across several messages.
+    pub fn add_elements(&mut self, set: &Set, elements: &[SetElement]) {
+        for list in SetElementList::split(set, elements) {
+            self.add(&list, MsgType::Add);
+        }
+    }
+
+    /// Removes `elements` from `set`, packing them in as few messages as possible like
+    /// [`Batch::add_elements`].
+    ///
+    /// [`Batch::add_elements`]: struct.Batch.html#method.add_elements
+    pub fn remove_elements(&mut self, set: &Set, elements: &[SetElement]) {
+        for list in SetElementList::split(set, elements) {
+            self.add(&list, MsgType::Del);
+        }
+    }
+
+    /// Replaces the content of `set` with `elements`: all the elements of the set are flushed,
+    /// then `elements` are added like with [`Batch::add_elements`]. As the batch is applied
+    /// atomically, the lookups never see the set empty or partially filled, e.g. when refreshing
+    /// a blocklist.
+    ///
+    /// [`Batch::add_elements`]: struct.Batch.html#method.add_elements
+    pub fn replace_elements(&mut self, set: &Set, elements: &[SetElement]) {
+        // deleting elements without specifying any flushes the set
+        let flush = SetElementList {
+            family: set.family,
+            table: set.table.clone(),
+            set: set.name.clone(),
+            set_id: set.id,
+            elements: None,
+        };
+        self.add(&flush, MsgType::Del);
+        self.add_elements(set, elements);
+    }
+
+    /// Adds to `chain` the two rules starting a stateful firewall: the packets of the
+    /// established connections and of the connections related to them are accepted, and the
+    /// packets conntrack considers invalid are dropped.
+    pub fn add_conntrack_preamble(&mut self, chain: &Chain) -> Result<(), BuilderError> {
+        let established = Rule::new(chain)?
+            .ct_states(ConnTrackState::ESTABLISHED | ConnTrackState::RELATED)?
+            .accept();
+        let invalid = Rule::new(chain)?.ct_states(ConnTrackState::INVALID)?.drop();
+        self.add(&established, MsgType::Add);
+        self.add(&invalid, MsgType::Add);
+        Ok(())
+    }
+
+    /// Adds to `chain` the rules of a default-deny policy: the packets to each of the `services`,
+    /// given as a destination port and a protocol, are accepted, then all the other packets are
+    /// dropped. This is usually preceded by [`Batch::add_conntrack_preamble`], so that the
+    /// answers to the connections opened by the host are accepted too. Returns
+    /// `BuilderError::EmptyServiceList` if `services` is empty, rather than dropping everything,
+    /// which would e.g. lock out the administrators of a remote host.
+    ///
+    /// [`Batch::add_conntrack_preamble`]: struct.Batch.html#method.add_conntrack_preamble
+    pub fn allow_ports_then_drop(
+        &mut self,
+        chain: &Chain,
+        services: &[(u16, Protocol)],
+    ) -> Result<(), BuilderError> {
+        if services.is_empty() {
+            return Err(BuilderError::EmptyServiceList);
+        }
+        for &(port, protocol) in services {
+            self.add(
+                &Rule::new(chain)?.dport(port, protocol).accept(),
+                MsgType::Add,
+            );
+        }
+        self.add(&Rule::new(chain)?.drop(), MsgType::Add);
+        Ok(())
+    }
+
+    /// Atomically replaces the rule with the given `handle` by `rule`, so that there is no
+    /// window during which neither of the two rules is in place.
+    pub fn replace(&mut self, rule: &Rule, handle: u64) {
+        self.add(&rule.clone().with_handle(handle), MsgType::Replace);
+    }
+
     /// Adds all the messages in the given iterator to this batch.
     pub fn add_iter<T: NfNetlinkObject, I: Iterator<Item = T>>(
         &mut self,
@@ -71,13 +191,185 @@ impl Batch {
         }
     }
 
-    /// Adds the final end message to the batch and returns a [`FinalizedBatch`] that can be used
-    /// to send the messages to netfilter.
+    /// Adds to this batch the messages replacing the whole content of `table` by `chains` and
+    /// `rules`: the table is created if need be, all its rules are flushed, then the chains and
+    /// rules are added. As the batch is applied atomically, the packets never see a partially
+    /// updated table, and sending the same batch several times yields the same ruleset.
+    ///
+    /// Note that the chains of the table that are not in `chains` are not removed, only emptied.
     ///
-    /// Return None if there is no object in the batch (this could block forever).
+    /// The NAT expressions of the rules are checked against the chains they belong to with
+    /// [`Rule::check_nat_chain`], so this returns `BuilderError::InvalidChainForNat` instead of
+    /// letting e.g. a masquerading rule in a filter chain silently do nothing.
+    ///
+    /// [`Rule::check_nat_chain`]: struct.Rule.html#method.check_nat_chain
+    pub fn add_owned_table(
+        &mut self,
+        table: &Table,
+        chains: &[Chain],
+        rules: &[Rule],
+    ) -> Result<(), BuilderError> {
+        let table_name = table.get_name().ok_or(BuilderError::MissingTableName)?;
+        let family = table.get_family();
+        let belongs_to_table =
+            |obj_family: ProtocolFamily, obj_table: Option<&String>| -> Result<(), BuilderError> {
+                if obj_family != family || obj_table != Some(table_name) {
+                    return Err(BuilderError::ObjectOutsideOfTable);
+                }
+                Ok(())
+            };
+        for chain in chains {
+            belongs_to_table(chain.get_family(), chain.get_table())?;
+        }
+        for rule in rules {
+            belongs_to_table(rule.get_family(), rule.get_table())?;
+            if let Some(chain) = chains.iter().find(|c| c.get_name() == rule.get_chain()) {
+                rule.check_nat_chain(chain)?;
+            }
+        }
+
+        self.add(table, MsgType::Add);
+        // deleting rules without specifying a chain flushes every chain of the table
+        let mut flush = Rule::default().with_table(table_name.as_str());
+        flush.set_family(family);
+        self.add(&flush, MsgType::Del);
+        for chain in chains {
+            self.add(chain, MsgType::Add);
+        }
+        for rule in rules {
+            self.add(rule, MsgType::Add);
+        }
+        Ok(())
+    }
+
+    /// Adds to this batch the messages deleting `table` together with everything it contains:
+    /// its chains, rules, sets and objects, i.e. everything the ruleset built in it. The table
+    /// is first added, which does nothing if it already exists, so that the batch doesn't fail
+    /// when the table is missing, e.g. to reset the state of integration tests or to tear down
+    /// the ruleset of a service when it shuts down:
+    ///
+    /// ```no_run
+    /// # use rustables::{Batch, ProtocolFamily, Table};
+    /// let table = Table::new(ProtocolFamily::Inet).with_name("example-table");
+    /// let mut batch = Batch::new();
+    /// batch.delete_table(&table);
+    /// batch.send()?;
+    /// # Ok::<(), rustables::error::QueryError>(())
+    /// ```
+    pub fn delete_table(&mut self, table: &Table) {
+        self.add(table, MsgType::Add);
+        self.add(table, MsgType::Del);
+    }
+
+    /// Describes the operations in this batch, in the order they will be performed, without
+    /// sending anything to netfilter.
+    pub fn operations(&self) -> Result<Vec<BatchOperation>, DecodeError> {
+        fn name(name: Option<&String>) -> &str {
+            name.map(String::as_str).unwrap_or("<unnamed>")
+        }
+
+        let mut res = Vec::new();
+        for msg in (BatchMessages { buf: &self.buf }) {
+            let hdr = get_nlmsghdr(msg)?;
+            if hdr.nlmsg_type == NFNL_MSG_BATCH_BEGIN as u16 {
+                continue;
+            }
+
+            let op = get_operation_from_nlmsghdr_type(hdr.nlmsg_type) as u32;
+            let (kind, summary) = match op {
+                NFT_MSG_NEWTABLE | NFT_MSG_DELTABLE => {
+                    let table = Table::deserialize(msg)?.0;
+                    (
+                        BatchObjectKind::Table,
+                        format!("table {}", name(table.get_name())),
+                    )
+                }
+                NFT_MSG_NEWCHAIN | NFT_MSG_DELCHAIN => {
+                    let chain = Chain::deserialize(msg)?.0;
+                    (
+                        BatchObjectKind::Chain,
+                        format!(
+                            "chain {} {}",
+                            name(chain.get_table()),
+                            name(chain.get_name())
+                        ),
+                    )
+                }
+                NFT_MSG_NEWRULE | NFT_MSG_DELRULE => {
+                    let rule = Rule::deserialize(msg)?.0;
+                    let mut summary =
+                        format!("rule {} {}", name(rule.get_table()), name(rule.get_chain()));
+                    if let Some(handle) = rule.get_handle() {
+                        summary += &format!(" handle {}", handle);
+                    }
+                    (BatchObjectKind::Rule, summary)
+                }
+                NFT_MSG_NEWSET | NFT_MSG_DELSET => {
+                    let set = Set::deserialize(msg)?.0;
+                    (
+                        BatchObjectKind::Set,
+                        format!("set {} {}", name(set.get_table()), name(set.get_name())),
+                    )
+                }
+                NFT_MSG_NEWSETELEM | NFT_MSG_DELSETELEM => {
+                    let elements = SetElementList::deserialize(msg)?.0;
+                    // a list without elements flushes the set
+                    let count = match elements.get_elements() {
+                        Some(e) => e.iter().count().to_string(),
+                        None => "all".to_string(),
+                    };
+                    (
+                        BatchObjectKind::SetElements,
+                        format!(
+                            "{} elements of set {} {}",
+                            count,
+                            name(elements.get_table()),
+                            name(elements.get_set())
+                        ),
+                    )
+                }
+                NFT_MSG_NEWOBJ | NFT_MSG_DELOBJ => {
+                    let obj = Object::deserialize(msg)?.0;
+                    (
+                        BatchObjectKind::Object,
+                        format!("object {} {}", name(obj.get_table()), name(obj.get_name())),
+                    )
+                }
+                NFT_MSG_NEWFLOWTABLE | NFT_MSG_DELFLOWTABLE => {
+                    let flowtable = Flowtable::deserialize(msg)?.0;
+                    (
+                        BatchObjectKind::Flowtable,
+                        format!(
+                            "flowtable {} {}",
+                            name(flowtable.get_table()),
+                            name(flowtable.get_name())
+                        ),
+                    )
+                }
+                _ => return Err(DecodeError::UnexpectedType(hdr.nlmsg_type)),
+            };
+
+            let msg_type = match op {
+                NFT_MSG_DELTABLE | NFT_MSG_DELCHAIN | NFT_MSG_DELRULE | NFT_MSG_DELSET
+                | NFT_MSG_DELSETELEM | NFT_MSG_DELOBJ | NFT_MSG_DELFLOWTABLE => MsgType::Del,
+                _ if hdr.nlmsg_flags & NLM_F_REPLACE as u16 != 0 => MsgType::Replace,
+                _ if hdr.nlmsg_flags & NLM_F_EXCL as u16 != 0 => MsgType::Create,
+                _ => MsgType::Add,
+            };
+            res.push(BatchOperation {
+                msg_type,
+                kind,
+                summary,
+            });
+        }
+        Ok(res)
+    }
+
+    /// Adds the final end message to the batch and returns a [`FinalizedBatch`] holding the
+    /// messages, ready to be sent to netfilter.
     ///
     /// [`FinalizedBatch`]: struct.FinalizedBatch.html
-    pub fn finalize(mut self) -> Vec<u8> {
+    pub fn finalize(mut self) -> FinalizedBatch {
         self.writer.write_header(
             libc::NFNL_MSG_BATCH_END as u16,
             ProtocolFamily::Unspec,
@@ -86,37 +378,288 @@ impl Batch {
             Some(NFNL_SUBSYS_NFTABLES as u16),
         );
         self.writer.finalize_writing_object();
-        *self.buf
+        FinalizedBatch { buf: *self.buf }
     }
 
+    /// Sends the batch to netfilter and waits for the acknowledgement of every message.
     pub fn send(self) -> Result<(), QueryError> {
-        use crate::query::{recv_and_process, socket_close_wrapper};
+        self.send_with_options(&SocketOptions::default())
+    }
 
-        let sock = socket::socket(
-            AddressFamily::Netlink,
-            SockType::Raw,
-            SockFlag::empty(),
-            SockProtocol::NetlinkNetFilter,
-        )
-        .map_err(QueryError::NetlinkOpenError)?;
+    /// Sends the batch like [`Batch::send`], over a socket opened with the given `options`,
+    /// e.g. with larger buffers for batches holding many messages.
+    ///
+    /// [`Batch::send`]: struct.Batch.html#method.send
+    pub fn send_with_options(self, options: &SocketOptions) -> Result<(), QueryError> {
+        use crate::query::{open_netfilter_socket, socket_close_wrapper};
 
-        let max_seq = self.seq - 1;
+        let sock = open_netfilter_socket(options)?;
 
         let addr = SockAddr::Netlink(NetlinkAddr::new(0, 0));
         // while this bind() is not strictly necessary, strace have trouble decoding the messages
         // if we don't
         socket::bind(sock, &addr).expect("bind");
 
+        socket_close_wrapper(sock, move |sock| self.send_on(sock))
+    }
+
+    /// Sends the batch to netfilter over `sock`, an already opened netlink socket of the
+    /// `NETLINK_NETFILTER` protocol, and waits for the acknowledgement of every message.
+    /// This is useful in sandboxed environments where the process is not allowed to open sockets
+    /// itself. The socket is left open, closing it remains the responsibility of the caller.
+    pub fn send_on(self, sock: RawFd) -> Result<(), QueryError> {
+        self.send_on_with_echo(sock, &|_: &[u8], _: &mut ()| Ok(()), &mut ())
+    }
+
+    /// Sends the batch to netfilter like [`Batch::send`], and returns the handles the kernel
+    /// assigned to the tables, chains, rules and objects added with [`Batch::add_with_echo`],
+    /// indexed by the sequence numbers that method returned. This avoids listing the chain
+    /// again to find out which handle belongs to which of the rules that were just added.
+    ///
+    /// [`Batch::send`]: struct.Batch.html#method.send
+    /// [`Batch::add_with_echo`]: struct.Batch.html#method.add_with_echo
+    pub fn send_and_get_handles(self) -> Result<HashMap<u32, u64>, QueryError> {
+        let mut handles = HashMap::new();
+        self.send_and_collect_echoes(
+            &|buf: &[u8], handles: &mut HashMap<u32, u64>| {
+                let (seq, handle) = get_echoed_handle(buf)?;
+                if let Some(handle) = handle {
+                    handles.insert(seq, handle);
+                }
+                Ok(())
+            },
+            &mut handles,
+        )?;
+        Ok(handles)
+    }
+
+    /// Sends the batch to netfilter like [`Batch::send`], and returns the rules added with
+    /// [`Batch::add_with_echo`], as the kernel echoed them back, in the order they were added.
+    /// Unlike the rules of the batch, these carry the handles the kernel assigned to them.
+    ///
+    /// Note that the kernel echoes the whole rule, expressions included, so echoing many rules
+    /// significantly increases the volume of the responses to read.
+    ///
+    /// [`Batch::send`]: struct.Batch.html#method.send
+    /// [`Batch::add_with_echo`]: struct.Batch.html#method.add_with_echo
+    pub fn send_and_get_rules(self) -> Result<Vec<Rule>, QueryError> {
+        let mut rules = Vec::new();
+        self.send_and_collect_echoes(
+            &|buf: &[u8], rules: &mut Vec<Rule>| {
+                if let Some(rule) = get_echoed_rule(buf)? {
+                    rules.push(rule);
+                }
+                Ok(())
+            },
+            &mut rules,
+        )?;
+        Ok(rules)
+    }
+
+    fn send_and_collect_echoes<T>(
+        self,
+        cb: &EchoCallback<T>,
+        echoes: &mut T,
+    ) -> Result<(), QueryError> {
+        use crate::query::{open_netfilter_socket, socket_close_wrapper};
+
+        let sock = open_netfilter_socket(&SocketOptions::default())?;
+
+        let addr = SockAddr::Netlink(NetlinkAddr::new(0, 0));
+        socket::bind(sock, &addr).expect("bind");
+
+        socket_close_wrapper(sock, |sock| self.send_on_with_echo(sock, cb, echoes))
+    }
+
+    fn send_on_with_echo<T>(
+        self,
+        sock: RawFd,
+        cb: &EchoCallback<T>,
+        echoes: &mut T,
+    ) -> Result<(), QueryError> {
+        use crate::query::recv_and_process;
+
+        let max_seq = self.seq - 1;
+
         let to_send = self.finalize();
         let mut sent = 0;
-        while sent != to_send.len() {
-            sent += socket::send(sock, &to_send[sent..], MsgFlags::empty())
+        while sent != to_send.as_bytes().len() {
+            sent += socket::send(sock, &to_send.as_bytes()[sent..], MsgFlags::empty())
                 .map_err(QueryError::NetlinkSendError)?;
         }
 
-        Ok(socket_close_wrapper(sock, move |sock| {
-            recv_and_process(sock, Some(max_seq), None, &mut ())
-        })?)
+        recv_and_process(sock, Some(max_seq), true, Some(cb), echoes)
+    }
+}
+
+/// Returns the sequence number of an object echoed by the kernel, along with the handle of the
+/// object, if it has one.
+pub(crate) fn get_echoed_handle(buf: &[u8]) -> Result<(u32, Option<u64>), QueryError> {
+    use crate::query::deserialize_object;
+
+    let hdr = get_nlmsghdr(buf)?;
+    let op = get_operation_from_nlmsghdr_type(hdr.nlmsg_type) as u32;
+    let handle = match op {
+        NFT_MSG_NEWTABLE => deserialize_object::<Table>(hdr.nlmsg_type, buf)?
+            .get_handle()
+            .copied(),
+        NFT_MSG_NEWCHAIN => deserialize_object::<Chain>(hdr.nlmsg_type, buf)?
+            .get_handle()
+            .copied(),
+        NFT_MSG_NEWRULE => deserialize_object::<Rule>(hdr.nlmsg_type, buf)?
+            .get_handle()
+            .copied(),
+        NFT_MSG_NEWOBJ => deserialize_object::<Object>(hdr.nlmsg_type, buf)?
+            .get_handle()
+            .copied(),
+        _ => None,
+    };
+    Ok((hdr.nlmsg_seq, handle))
+}
+
+/// Returns the rule echoed by the kernel in `buf`, or `None` if the message holds another kind
+/// of object.
+pub(crate) fn get_echoed_rule(buf: &[u8]) -> Result<Option<Rule>, QueryError> {
+    use crate::query::deserialize_object;
+
+    let hdr = get_nlmsghdr(buf)?;
+    match get_operation_from_nlmsghdr_type(hdr.nlmsg_type) as u32 {
+        NFT_MSG_NEWRULE => Ok(Some(deserialize_object::<Rule>(hdr.nlmsg_type, buf)?)),
+        _ => Ok(None),
+    }
+}
+
+/// Atomically replaces the content of `table` by `chains` and `rules`, creating the table if it
+/// does not exist yet. See [`Batch::add_owned_table`] for details.
+///
+/// [`Batch::add_owned_table`]: struct.Batch.html#method.add_owned_table
+pub fn apply_owned_table(
+    table: &Table,
+    chains: &[Chain],
+    rules: &[Rule],
+) -> Result<(), QueryError> {
+    let mut batch = Batch::new();
+    batch.add_owned_table(table, chains, rules)?;
+    batch.send()
+}
+
+/// A batch of messages, terminated by the batch end message, as returned by [`Batch::finalize`].
+/// Iterating over a reference to it yields the individual netlink messages.
+///
+/// [`Batch::finalize`]: struct.Batch.html#method.finalize
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinalizedBatch {
+    buf: Vec<u8>,
+}
+
+impl FinalizedBatch {
+    /// Returns the raw netlink messages, as they would be sent to netfilter.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Consumes the batch and returns the raw netlink messages.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    /// Returns an iterator over the netlink messages in the batch.
+    pub fn iter(&self) -> BatchMessages<'_> {
+        BatchMessages { buf: &self.buf }
+    }
+
+    /// Returns the number of netlink messages in the batch, including the batch begin and end
+    /// messages.
+    pub fn message_count(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// Returns the size in bytes of the messages in the batch, padding included, i.e. the number
+    /// of bytes sent to netfilter.
+    pub fn total_len(&self) -> usize {
+        self.buf.len()
+    }
+}
+
+impl AsRef<[u8]> for FinalizedBatch {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl Deref for FinalizedBatch {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl<'a> IntoIterator for &'a FinalizedBatch {
+    type Item = &'a [u8];
+    type IntoIter = BatchMessages<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the netlink messages of a [`FinalizedBatch`], without their padding.
+///
+/// [`FinalizedBatch`]: struct.FinalizedBatch.html
+pub struct BatchMessages<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Iterator for BatchMessages<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let hdr = get_nlmsghdr(self.buf).ok()?;
+        let len = hdr.nlmsg_len as usize;
+        let msg = &self.buf[..len];
+        let next_msg = pad_netlink_object_with_variable_size(len);
+        self.buf = &self.buf[next_msg.min(self.buf.len())..];
+        Some(msg)
+    }
+}
+
+/// The kind of object modified by a [`BatchOperation`].
+///
+/// [`BatchOperation`]: struct.BatchOperation.html
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BatchObjectKind {
+    Table,
+    Chain,
+    Rule,
+    Set,
+    SetElements,
+    Object,
+    Flowtable,
+}
+
+/// A human-readable description of a message in a [`Batch`], as returned by
+/// [`Batch::operations`].
+///
+/// [`Batch`]: struct.Batch.html
+/// [`Batch::operations`]: struct.Batch.html#method.operations
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BatchOperation {
+    pub msg_type: MsgType,
+    pub kind: BatchObjectKind,
+    /// A short description of the object, e.g. `rule filter input`.
+    pub summary: String,
+}
+
+impl Display for BatchOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self.msg_type {
+            MsgType::Add => "add",
+            MsgType::Create => "create",
+            MsgType::Del => "delete",
+            MsgType::Replace => "replace",
+        };
+        write!(f, "{} {}", action, self.summary)
     }
 }
 
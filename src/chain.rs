@@ -1,12 +1,19 @@
 use libc::{NF_ACCEPT, NF_DROP};
 use rustables_macros::nfnetlink_struct;
 
-use crate::error::{DecodeError, QueryError};
-use crate::nlmsg::{NfNetlinkAttribute, NfNetlinkDeserializable, NfNetlinkObject};
+use crate::error::{BuilderError, DecodeError, QueryError};
+use crate::nlmsg::{
+    pad_netlink_object, pad_netlink_object_with_variable_size, AttributeDecoder,
+    NfNetlinkAttribute, NfNetlinkDeserializable, NfNetlinkObject,
+};
+use crate::parser::{read_attributes, write_attribute};
 use crate::sys::{
-    NFTA_CHAIN_FLAGS, NFTA_CHAIN_HOOK, NFTA_CHAIN_NAME, NFTA_CHAIN_POLICY, NFTA_CHAIN_TABLE,
-    NFTA_CHAIN_TYPE, NFTA_HOOK_HOOKNUM, NFTA_HOOK_PRIORITY, NFT_MSG_DELCHAIN, NFT_MSG_NEWCHAIN,
+    nlattr, NFTA_CHAIN_FLAGS, NFTA_CHAIN_HANDLE, NFTA_CHAIN_HOOK, NFTA_CHAIN_NAME,
+    NFTA_CHAIN_POLICY, NFTA_CHAIN_TABLE, NFTA_CHAIN_TYPE, NFTA_CHAIN_USE, NFTA_DEVICE_NAME,
+    NFTA_HOOK_DEV, NFTA_HOOK_DEVS, NFTA_HOOK_HOOKNUM, NFTA_HOOK_PRIORITY, NFT_MSG_DELCHAIN,
+    NFT_MSG_NEWCHAIN, NF_NETDEV_EGRESS, NF_NETDEV_INGRESS,
 };
+use crate::table::validate_name;
 use crate::{Batch, ProtocolFamily, Table};
 use std::fmt::Debug;
 
@@ -28,6 +35,87 @@ pub enum HookClass {
     PostRouting = libc::NF_INET_POST_ROUTING,
 }
 
+impl HookClass {
+    /// Returns the number of this hook in `family`, or `None` if the chains of that family
+    /// cannot register on it. The bridge family numbers its hooks like the IP families, while
+    /// the arp family only has input and output hooks, numbered differently. The chains of the
+    /// netdev family register on a [`NetDevHookClass`] instead.
+    pub fn hook_num(&self, family: ProtocolFamily) -> Option<u32> {
+        match family {
+            ProtocolFamily::Ipv4 | ProtocolFamily::Ipv6 | ProtocolFamily::Inet => {
+                Some(*self as u32)
+            }
+            ProtocolFamily::Bridge => Some(match self {
+                HookClass::PreRouting => libc::NF_BR_PRE_ROUTING,
+                HookClass::In => libc::NF_BR_LOCAL_IN,
+                HookClass::Forward => libc::NF_BR_FORWARD,
+                HookClass::Out => libc::NF_BR_LOCAL_OUT,
+                HookClass::PostRouting => libc::NF_BR_POST_ROUTING,
+            } as u32),
+            ProtocolFamily::Arp => match self {
+                HookClass::In => Some(libc::NF_ARP_IN as u32),
+                HookClass::Out => Some(libc::NF_ARP_OUT as u32),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// The hooks a chain of the netdev family can register for.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Hash)]
+#[repr(u32)]
+pub enum NetDevHookClass {
+    /// Hook into the ingress path of the devices. Corresponds to `NF_NETDEV_INGRESS`.
+    Ingress = NF_NETDEV_INGRESS,
+    /// Hook into the egress path of the devices (Linux 5.16 and later). Corresponds to
+    /// `NF_NETDEV_EGRESS`.
+    Egress = NF_NETDEV_EGRESS,
+}
+
+/// The list of devices a netdev chain is bound to.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct HookDevices(pub Vec<String>);
+
+impl NfNetlinkAttribute for HookDevices {
+    fn is_nested(&self) -> bool {
+        true
+    }
+
+    fn get_size(&self) -> usize {
+        self.0.iter().fold(0, |acc, dev| {
+            acc + pad_netlink_object::<nlattr>() + pad_netlink_object_with_variable_size(dev.len())
+        })
+    }
+
+    fn write_payload(&self, mut addr: &mut [u8]) {
+        for dev in &self.0 {
+            write_attribute(NFTA_DEVICE_NAME, dev, addr);
+            let offset =
+                pad_netlink_object::<nlattr>() + pad_netlink_object_with_variable_size(dev.len());
+            addr = &mut addr[offset..];
+        }
+    }
+}
+
+impl AttributeDecoder for HookDevices {
+    fn decode_attribute(&mut self, attr_type: u16, buf: &[u8]) -> Result<(), DecodeError> {
+        match attr_type {
+            NFTA_DEVICE_NAME => {
+                self.0.push(String::deserialize(buf)?.0);
+                Ok(())
+            }
+            _ => Err(DecodeError::UnsupportedAttributeType(attr_type)),
+        }
+    }
+}
+
+impl NfNetlinkDeserializable for HookDevices {
+    fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
+        Ok((read_attributes(buf)?, &[]))
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
 #[nfnetlink_struct(nested = true)]
 pub struct Hook {
@@ -36,6 +124,13 @@ pub struct Hook {
     class: u32,
     #[field(NFTA_HOOK_PRIORITY)]
     priority: u32,
+    /// The device a netdev chain is bound to.
+    #[field(NFTA_HOOK_DEV)]
+    device: String,
+    /// The devices a netdev chain is bound to, when there is more than one (Linux 5.5 and
+    /// later).
+    #[field(NFTA_HOOK_DEVS)]
+    devices: HookDevices,
 }
 
 impl Hook {
@@ -44,6 +139,41 @@ impl Hook {
             .with_class(class as u32)
             .with_priority(priority as u32)
     }
+
+    /// Creates a hook for a chain of `family`, numbered as that family expects (see
+    /// [`HookClass::hook_num`]). Returns `BuilderError::InvalidHook` if the chains of `family`
+    /// cannot register on `class`.
+    pub fn for_family(
+        class: HookClass,
+        priority: ChainPriority,
+        family: ProtocolFamily,
+    ) -> Result<Self, BuilderError> {
+        let hook_num = class.hook_num(family).ok_or(BuilderError::InvalidHook)?;
+        Ok(Hook::default()
+            .with_class(hook_num)
+            .with_priority(priority as u32))
+    }
+
+    /// Creates a hook for a chain of the netdev family, bound to `devices`.
+    pub fn new_netdev(
+        class: NetDevHookClass,
+        priority: ChainPriority,
+        devices: &[&str],
+    ) -> Result<Self, BuilderError> {
+        if devices.iter().any(|dev| dev.len() >= libc::IFNAMSIZ) {
+            return Err(BuilderError::InterfaceNameTooLong);
+        }
+        let hook = Hook::default()
+            .with_class(class as u32)
+            .with_priority(priority as u32);
+        Ok(match devices {
+            [] => return Err(BuilderError::MissingHookDevice),
+            [device] => hook.with_device(*device),
+            _ => hook.with_devices(HookDevices(
+                devices.iter().map(|dev| dev.to_string()).collect(),
+            )),
+        })
+    }
 }
 
 /// A chain policy. Decides what to do with a packet that was processed by the chain but did not
@@ -73,7 +203,7 @@ impl NfNetlinkDeserializable for ChainPolicy {
         Ok((
             match v {
                 NF_ACCEPT => ChainPolicy::Accept,
-                NF_DROP => ChainPolicy::Accept,
+                NF_DROP => ChainPolicy::Drop,
                 _ => return Err(DecodeError::UnknownChainPolicy),
             },
             remaining_data,
@@ -140,6 +270,9 @@ pub struct Chain {
     family: ProtocolFamily,
     #[field(NFTA_CHAIN_TABLE)]
     table: String,
+    /// Identifier assigned by the kernel, only available on chains retrieved from the kernel.
+    #[field(NFTA_CHAIN_HANDLE)]
+    handle: u64,
     #[field(NFTA_CHAIN_NAME)]
     name: String,
     #[field(NFTA_CHAIN_HOOK)]
@@ -150,6 +283,10 @@ pub struct Chain {
     chain_type: ChainType,
     #[field(NFTA_CHAIN_FLAGS)]
     flags: u32,
+    /// The number of references to the chain (e.g. jumps from other chains), only available on
+    /// chains retrieved from the kernel.
+    #[field(NFTA_CHAIN_USE)]
+    use_count: u32,
     #[field(optional = true, crate::sys::NFTA_CHAIN_USERDATA)]
     userdata: Vec<u8>,
 }
@@ -169,11 +306,164 @@ impl Chain {
         chain
     }
 
+    /// Returns whether this is a base chain, i.e. a chain registered on a netfilter hook, which
+    /// sees the packets directly, as opposed to a regular chain, which is only reached by jumps.
+    pub fn is_base_chain(&self) -> bool {
+        self.get_hook().is_some()
+    }
+
+    /// Returns the priority of the hook of this chain, if it is a base chain. Chains with a lower
+    /// priority see the packets first.
+    pub fn get_priority(&self) -> Option<ChainPriority> {
+        self.get_hook()
+            .and_then(|hook| hook.get_priority())
+            .map(|priority| *priority as ChainPriority)
+    }
+
+    /// Creates a new chain named `name` inside the given [`Table`]. Unlike [`Chain::with_name`],
+    /// this checks that the name fits in the limits of nftables, and returns
+    /// `BuilderError::NameTooLong` otherwise.
+    ///
+    /// [`Table`]: struct.Table.html
+    /// [`Chain::with_name`]: struct.Chain.html#method.with_name
+    pub fn named(table: &Table, name: impl Into<String>) -> Result<Chain, BuilderError> {
+        let name = name.into();
+        validate_name(&name)?;
+        Ok(Chain::new(table).with_name(name))
+    }
+
+    /// Registers this chain on the `class` hook, checking that the family of the chain has such a
+    /// hook. Unlike `Chain::set_hook(Hook::new(class, priority))`, this translates the hook to
+    /// the numbering of the arp family, and returns `BuilderError::InvalidHook` instead of
+    /// letting the kernel reject the chain, e.g. for a forward chain in an arp table.
+    pub fn set_base_hook(
+        &mut self,
+        class: HookClass,
+        priority: ChainPriority,
+    ) -> Result<(), BuilderError> {
+        self.set_hook(Hook::for_family(class, priority, self.family)?);
+        Ok(())
+    }
+
+    /// Like [`Chain::set_base_hook`], but in a builder fashion.
+    pub fn with_base_hook(
+        mut self,
+        class: HookClass,
+        priority: ChainPriority,
+    ) -> Result<Self, BuilderError> {
+        self.set_base_hook(class, priority)?;
+        Ok(self)
+    }
+
+    /// Creates a base chain named `name` inside `table`, registered on the `class` hook with
+    /// the given `priority`, `chain_type` and `policy`, all at once. The combination is checked
+    /// with [`Chain::validate`], in addition to the checks of [`Chain::named`] and
+    /// [`Chain::set_base_hook`].
+    ///
+    /// [`Chain::validate`]: struct.Chain.html#method.validate
+    /// [`Chain::named`]: struct.Chain.html#method.named
+    /// [`Chain::set_base_hook`]: struct.Chain.html#method.set_base_hook
+    pub fn new_base(
+        table: &Table,
+        name: impl Into<String>,
+        class: HookClass,
+        priority: ChainPriority,
+        chain_type: ChainType,
+        policy: ChainPolicy,
+    ) -> Result<Chain, BuilderError> {
+        let chain = Chain::named(table, name)?
+            .with_base_hook(class, priority)?
+            .with_type(chain_type)
+            .with_policy(policy);
+        chain.validate()?;
+        Ok(chain)
+    }
+
+    /// Checks, before the kernel does, that the settings of this chain are consistent: only base
+    /// chains have a policy and a type (`BuilderError::MissingHookForChain` otherwise), and the
+    /// nat and route chains only exist in the ip, ip6 and inet families and on some hooks,
+    /// respectively pre-routing, input, output and post-routing, and output
+    /// (`BuilderError::InvalidHookForChainType` otherwise).
+    pub fn validate(&self) -> Result<(), BuilderError> {
+        let hook = match self.get_hook().and_then(|hook| hook.get_class()) {
+            Some(hook) => *hook,
+            None if self.get_policy().is_some() || self.get_type().is_some() => {
+                return Err(BuilderError::MissingHookForChain)
+            }
+            None => return Ok(()),
+        };
+        let classes: &[HookClass] = match self.get_type() {
+            Some(ChainType::Nat) => &[
+                HookClass::PreRouting,
+                HookClass::In,
+                HookClass::Out,
+                HookClass::PostRouting,
+            ],
+            Some(ChainType::Route) => &[HookClass::Out],
+            Some(ChainType::Filter) | None => return Ok(()),
+        };
+        let ip_family = matches!(
+            self.family,
+            ProtocolFamily::Ipv4 | ProtocolFamily::Ipv6 | ProtocolFamily::Inet
+        );
+        if ip_family
+            && classes
+                .iter()
+                .any(|class| class.hook_num(self.family) == Some(hook))
+        {
+            Ok(())
+        } else {
+            Err(BuilderError::InvalidHookForChainType)
+        }
+    }
+
+    /// Registers this chain, which must belong to a table of the netdev family, on the `class`
+    /// hook of `devices`. The device names are validated against `IFNAMSIZ`.
+    pub fn set_hook_device(
+        &mut self,
+        class: NetDevHookClass,
+        priority: ChainPriority,
+        devices: &[&str],
+    ) -> Result<(), BuilderError> {
+        self.set_hook(Hook::new_netdev(class, priority, devices)?);
+        Ok(())
+    }
+
+    /// Like [`Chain::set_hook_device`], but in a builder fashion.
+    pub fn with_hook_device(
+        mut self,
+        class: NetDevHookClass,
+        priority: ChainPriority,
+        devices: &[&str],
+    ) -> Result<Self, BuilderError> {
+        self.set_hook_device(class, priority, devices)?;
+        Ok(self)
+    }
+
     /// Appends this chain to `batch`
     pub fn add_to_batch(self, batch: &mut Batch) -> Self {
         batch.add(&self, crate::MsgType::Add);
         self
     }
+
+    /// Returns a chain that renames this chain to `new_name` when added to a batch with
+    /// `MsgType::Add`. The chain keeps its rules, and the jumps to it follow the new name.
+    ///
+    /// The kernel identifies the chain to rename by its handle, so this chain must have been
+    /// retrieved from the kernel (see [`list_chains_for_table`]).
+    ///
+    /// [`list_chains_for_table`]: fn.list_chains_for_table.html
+    pub fn rename(&self, new_name: impl Into<String>) -> Result<Chain, BuilderError> {
+        let mut chain = Chain::default()
+            .with_table(
+                self.get_table()
+                    .ok_or(BuilderError::MissingChainInformationError)?,
+            )
+            .with_handle(*self.get_handle().ok_or(BuilderError::MissingChainHandle)?)
+            .with_name(new_name.into());
+        chain.family = self.family;
+        Ok(chain)
+    }
 }
 
 impl NfNetlinkObject for Chain {
@@ -25,6 +25,16 @@ impl DataType for Ipv6Addr {
     }
 }
 
+/// A 32 bits integer in host byte order, as loaded by the meta and conntrack marks.
+impl DataType for u32 {
+    const TYPE: u32 = 4;
+    const LEN: u32 = 4;
+
+    fn data(&self) -> Vec<u8> {
+        self.to_ne_bytes().to_vec()
+    }
+}
+
 impl<const N: usize> DataType for [u8; N] {
     const TYPE: u32 = 5;
     const LEN: u32 = N as u32;
@@ -40,3 +50,57 @@ pub fn ip_to_vec(ip: IpAddr) -> Vec<u8> {
         IpAddr::V6(x) => x.octets().to_vec(),
     }
 }
+
+/// The inverse of [`ip_to_vec`]: returns the IPv4 address stored in 4 bytes or the IPv6 address
+/// stored in 16 bytes, or `None` for data of any other length.
+pub fn vec_to_ip(data: &[u8]) -> Option<IpAddr> {
+    if let Ok(octets) = <[u8; 4]>::try_from(data) {
+        Some(IpAddr::V4(Ipv4Addr::from(octets)))
+    } else if let Ok(octets) = <[u8; 16]>::try_from(data) {
+        Some(IpAddr::V6(Ipv6Addr::from(octets)))
+    } else {
+        None
+    }
+}
+
+/// The number of bits used by each type in the type of a concatenation.
+const CONCAT_TYPE_BITS: u32 = 6;
+
+/// Rounds `len` up to the size of a 32 bits register, as each field of a concatenation starts in
+/// a register of its own.
+const fn pad_to_register(len: u32) -> u32 {
+    (len + 3) & !3
+}
+
+fn concat_field_data(data: &mut Vec<u8>, field: Vec<u8>) {
+    let len = field.len();
+    data.extend(field);
+    data.resize(data.len() + pad_to_register(len as u32) as usize - len, 0);
+}
+
+/// The concatenation of two keys, as in `ip saddr . tcp dport`.
+impl<A: DataType, B: DataType> DataType for (A, B) {
+    const TYPE: u32 = A::TYPE << CONCAT_TYPE_BITS | B::TYPE;
+    const LEN: u32 = pad_to_register(A::LEN) + pad_to_register(B::LEN);
+
+    fn data(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::LEN as usize);
+        concat_field_data(&mut data, self.0.data());
+        concat_field_data(&mut data, self.1.data());
+        data
+    }
+}
+
+/// The concatenation of three keys, as in `ip saddr . ip daddr . tcp dport`.
+impl<A: DataType, B: DataType, C: DataType> DataType for (A, B, C) {
+    const TYPE: u32 = (A::TYPE << CONCAT_TYPE_BITS | B::TYPE) << CONCAT_TYPE_BITS | C::TYPE;
+    const LEN: u32 = pad_to_register(A::LEN) + pad_to_register(B::LEN) + pad_to_register(C::LEN);
+
+    fn data(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::LEN as usize);
+        concat_field_data(&mut data, self.0.data());
+        concat_field_data(&mut data, self.1.data());
+        concat_field_data(&mut data, self.2.data());
+        data
+    }
+}
@@ -7,31 +7,55 @@ use crate::sys::nlmsgerr;
 
 #[derive(Error, Debug)]
 pub enum DecodeError {
-    #[error("The buffer is too small to hold a valid message")]
-    BufTooSmall,
-
-    #[error("The message is too small")]
-    NlMsgTooSmall,
+    #[error(
+        "The buffer holds {available} bytes, too few for a message header of {expected} bytes"
+    )]
+    BufTooSmall { expected: usize, available: usize },
+
+    /// The length in the header of a message is either smaller than the headers it must hold or
+    /// larger than the buffer.
+    #[error("The message claims a length of {len} bytes, but it must be at least {min} bytes long and the buffer holds {available} bytes")]
+    NlMsgTooSmall {
+        len: usize,
+        min: usize,
+        available: usize,
+    },
 
     #[error("The message holds unexpected data")]
     InvalidDataSize,
 
-    #[error("Invalid subsystem, expected NFTABLES")]
+    /// The length of the attribute starting `offset` bytes into the list of attributes being
+    /// parsed is smaller than its header or larger than the `available` bytes left in the list.
+    #[error("The attribute at offset {offset} claims a length of {len} bytes, with {available} bytes left")]
+    InvalidAttributeLength {
+        offset: usize,
+        len: usize,
+        available: usize,
+    },
+
+    /// `len` bytes, too few to hold an attribute, were left at `offset` at the end of a list of
+    /// attributes. `offset` is the number of bytes consumed by the attributes parsed before, and
+    /// `len` the number of bytes remaining after them, so that callers can log the data this
+    /// crate doesn't model.
+    #[error("{len} bytes of trailing data at offset {offset} after the attributes")]
+    TrailingData { offset: usize, len: usize },
+
+    #[error("Invalid subsystem {0}, expected NFTABLES")]
     InvalidSubsystem(u8),
 
-    #[error("Invalid version, expected NFNETLINK_V0")]
+    #[error("Invalid version {0}, expected NFNETLINK_V0")]
     InvalidVersion(u8),
 
-    #[error("Invalid port ID")]
+    #[error("Invalid port ID {0}")]
     InvalidPortId(u32),
 
-    #[error("Invalid sequence number")]
+    #[error("Invalid sequence number {0}")]
     InvalidSeq(u32),
 
     #[error("The generation number was bumped in the kernel while the operation was running, interrupting it")]
     ConcurrentGenerationUpdate,
 
-    #[error("Unsupported message type")]
+    #[error("Unsupported message type {0}")]
     UnsupportedType(u16),
 
     #[error("Invalid attribute type")]
@@ -43,58 +67,70 @@ pub enum DecodeError {
     #[error("Invalid policy for a chain")]
     UnknownChainPolicy,
 
-    #[error("Unknown type for a Meta expression")]
+    #[error("Unknown type for a Meta expression: {0}")]
     UnknownMetaType(u32),
 
-    #[error("Unsupported value for an icmp reject type")]
+    #[error("Unsupported value for an icmp reject type: {0}")]
     UnknownRejectType(u32),
 
-    #[error("Invalid type for a rt expression")]
+    #[error("Invalid type for a rt expression: {0}")]
     UnknownRtKey(u32),
 
-    #[error("Unsupported value for an icmp code in a reject expression")]
+    #[error("Unsupported value for an icmp code in a reject expression: {0}")]
     UnknownIcmpCode(u8),
 
-    #[error("Invalid value for a register")]
+    #[error("Invalid value for a register: {0}")]
     UnknownRegister(u32),
 
-    #[error("Invalid type for a verdict expression")]
+    #[error("Invalid type for a verdict expression: {0}")]
     UnknownVerdictType(i32),
 
-    #[error("Invalid type for a nat expression")]
+    #[error("Invalid type for a nat expression: {0}")]
     UnknownNatType(i32),
 
-    #[error("Invalid type for a payload expression")]
+    #[error("Invalid type for a payload expression: {0}")]
     UnknownPayloadType(u32),
 
-    #[error("Invalid type for a byteorder expression")]
+    #[error("Invalid type for a byteorder expression: {0}")]
     UnknownByteorderOp(u32),
 
-    #[error("Invalid type for a compare expression")]
+    #[error("Invalid type for a compare expression: {0}")]
     UnknownCmpOp(u32),
 
-    #[error("Invalid type for a conntrack key")]
+    #[error("Invalid type for a range expression: {0}")]
+    UnknownRangeOp(u32),
+
+    #[error("Invalid result type for a fib expression: {0}")]
+    UnknownFibResult(u32),
+
+    #[error("Invalid type for a socket key: {0}")]
+    UnknownSocketKey(u32),
+
+    #[error("Invalid type for a conntrack key: {0}")]
     UnknownConntrackKey(u32),
 
-    #[error("Invalid type for an extension header expression")]
+    #[error("Invalid type for an extension header expression: {0}")]
     UnknownExtHdrOp(u32),
 
-    #[error("Unsupported value for a link layer header field")]
+    #[error("Unsupported value for a link layer header field at offset {0} with length {1}")]
     UnknownLinkLayerHeaderField(u32, u32),
 
-    #[error("Unsupported value for an IPv4 header field")]
+    #[error("Unsupported value for an IPv4 header field at offset {0} with length {1}")]
     UnknownIPv4HeaderField(u32, u32),
 
-    #[error("Unsupported value for an IPv6 header field")]
+    #[error("Unsupported value for an IPv6 header field at offset {0} with length {1}")]
     UnknownIPv6HeaderField(u32, u32),
 
-    #[error("Unsupported value for a TCP header field")]
+    #[error("Unsupported value for a TCP header field at offset {0} with length {1}")]
     UnknownTCPHeaderField(u32, u32),
 
-    #[error("Unsupported value for an UDP header field")]
+    #[error("Unsupported value for an UDP header field at offset {0} with length {1}")]
     UnknownUDPHeaderField(u32, u32),
 
-    #[error("Unsupported value for an ICMPv6 header field")]
+    #[error("Unsupported value for a DCCP header field at offset {0} with length {1}")]
+    UnknownDCCPHeaderField(u32, u32),
+
+    #[error("Unsupported value for an ICMPv6 header field at offset {0} with length {1}")]
     UnknownICMPv6HeaderField(u32, u32),
 
     #[error("Missing the 'base' attribute to deserialize the payload object")]
@@ -109,16 +145,22 @@ pub enum DecodeError {
     #[error("The object does not contain a name for the expression being parsed")]
     MissingExpressionName,
 
-    #[error("Unsupported attribute type")]
+    #[error("The object does not contain a type for the data being parsed")]
+    MissingObjectType,
+
+    #[error("Invalid type for a stateful object: {0}")]
+    UnknownObjectType(u32),
+
+    #[error("Unsupported attribute type {0}")]
     UnsupportedAttributeType(u16),
 
-    #[error("Unexpected message type")]
+    #[error("Unexpected message type {0}")]
     UnexpectedType(u16),
 
     #[error("The decoded String is not UTF8 compliant")]
     StringDecodeFailure(#[from] FromUtf8Error),
 
-    #[error("Invalid value for a protocol family")]
+    #[error("Invalid value {0} for a protocol family")]
     UnknownProtocolFamily(i32),
 }
 
@@ -130,17 +172,109 @@ pub enum BuilderError {
     #[error("The table does not have a name")]
     MissingTableName,
 
+    #[error("The chain or rule does not belong to the table")]
+    ObjectOutsideOfTable,
+
     #[error("Missing information in the chain to create a rule")]
     MissingChainInformationError,
 
+    #[error("The chain does not have a handle")]
+    MissingChainHandle,
+
+    #[error("A netdev chain must be bound to at least one device")]
+    MissingHookDevice,
+
+    #[error("The chains of the family cannot register on this hook")]
+    InvalidHook,
+
     #[error("Missing name for the set")]
     MissingSetName,
 
+    #[error("The start of the range is greater than its end")]
+    InvertedRange,
+
+    #[error("No service is allowed, so all the packets would be dropped")]
+    EmptyServiceList,
+
+    #[error("The set must hold at least one element")]
+    EmptySet,
+
+    #[error("The addresses of a set must all be of the same family")]
+    MixedAddressFamilies,
+
+    #[error("The set is not a map")]
+    NotAMap,
+
+    #[error("The concatenated fields do not fit in the registers")]
+    TooManyConcatFields,
+
+    #[error("The flow label does not fit in 20 bits")]
+    InvalidFlowLabel,
+
     #[error("The interface name is too long to be written")]
     InterfaceNameTooLong,
 
+    #[error("The interface name prefix is empty")]
+    EmptyPrefix,
+
+    #[error("The name is longer than the 255 bytes nftables allows")]
+    NameTooLong,
+
+    #[error("Couldn't find the index of the interface")]
+    InterfaceLookupFailed(#[source] std::io::Error),
+
     #[error("The log prefix string is more than 127 characters long")]
     TooLongLogPrefix,
+
+    #[error("The comment is more than 127 bytes long or contains a null byte")]
+    InvalidComment,
+
+    #[error("The offset of the payload is larger than 255 bytes")]
+    PayloadOffsetTooLarge,
+
+    #[error("The length of the payload must be between 1 and 64 bytes")]
+    InvalidPayloadLength,
+
+    #[error(
+        "The length of the compared data does not match the length of the data in the register"
+    )]
+    RegisterLengthMismatch,
+
+    #[error("The type of the object cannot be deduced from its data")]
+    MissingObjectType,
+
+    #[error("Missing name for the object")]
+    MissingObjectName,
+
+    #[error("The object is not a counter")]
+    NotACounter,
+
+    #[error("The reject code is not supported in the family of the rule")]
+    InvalidRejectCode,
+
+    #[error("The NAT expressions only work in the base chains of type nat with a matching hook")]
+    InvalidChainForNat,
+
+    #[error("The NAT expression does not have a type")]
+    MissingNatType,
+
+    #[error("The time of the day is not less than 24 hours")]
+    InvalidTimeOfDay,
+
+    #[error("Only the jump and goto verdicts point to a chain")]
+    InvalidVerdictForChain,
+
+    #[error("The link layer header is only available in the bridge and netdev tables")]
+    MissingLinkLayerHeader,
+
+    #[error("Only the base chains, registered on a hook, have a policy and a type")]
+    MissingHookForChain,
+
+    #[error("The type of the chain is not supported on its hook or in its family")]
+    InvalidHookForChainType,
+
+    #[error("The IP version can only be deduced in the ip and ip6 tables")]
+    AmbiguousIpVersion,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -154,14 +288,23 @@ pub enum QueryError {
     #[error("Error while reading from netlink socket")]
     NetlinkRecvError(#[source] nix::Error),
 
+    #[error("Couldn't set the size of the netlink socket buffers")]
+    SocketOptionError(#[source] nix::Error),
+
     #[error("Error while processing an incoming netlink message")]
     ProcessNetlinkError(#[from] DecodeError),
 
+    /// The kernel answered a request of the given message type with an object that couldn't be
+    /// parsed.
+    #[error("Couldn't parse an object received in answer to a request of type {0}")]
+    InvalidObject(u16, #[source] DecodeError),
+
     #[error("Error while building netlink objects in Rust")]
     BuilderError(#[from] BuilderError),
 
-    #[error("Error received from the kernel")]
-    NetlinkError(nlmsgerr),
+    /// An error returned by the kernel, with the reason given by the kernel if any.
+    #[error("Error received from the kernel{}", .1.as_ref().map(|msg| format!(": {}", msg)).unwrap_or_default())]
+    NetlinkError(nlmsgerr, Option<String>),
 
     #[error("Couldn't allocate a netlink object, out of memory ?")]
     NetlinkAllocationFailed,
@@ -180,4 +323,7 @@ pub enum QueryError {
 
     #[error("Couldn't close the socket")]
     CloseFailed(#[source] Errno),
+
+    #[error("The kernel did not answer with the requested object")]
+    MissingObject,
 }
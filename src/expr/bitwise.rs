@@ -37,11 +37,39 @@ impl Bitwise {
         if mask.len() != xor.len() {
             return Err(BuilderError::IncompatibleLength);
         }
-        Ok(Bitwise::default()
+        Ok(Bitwise::from_mask_and_xor(mask, xor))
+    }
+
+    /// Returns a new `Bitwise` instance computing the bitwise AND of the value it's applied to
+    /// and `mask`.
+    pub fn and(mask: impl Into<Vec<u8>>) -> Self {
+        let mask = mask.into();
+        let xor = vec![0; mask.len()];
+        Bitwise::from_mask_and_xor(mask, xor)
+    }
+
+    /// Returns a new `Bitwise` instance computing the bitwise OR of the value it's applied to
+    /// and `value`, as `(x & !value) ^ value`.
+    pub fn or(value: impl Into<Vec<u8>>) -> Self {
+        let xor = value.into();
+        let mask = xor.iter().map(|b| !b).collect();
+        Bitwise::from_mask_and_xor(mask, xor)
+    }
+
+    /// Returns a new `Bitwise` instance computing the bitwise XOR of the value it's applied to
+    /// and `value`.
+    pub fn xor(value: impl Into<Vec<u8>>) -> Self {
+        let xor = value.into();
+        let mask = vec![0xff; xor.len()];
+        Bitwise::from_mask_and_xor(mask, xor)
+    }
+
+    fn from_mask_and_xor(mask: Vec<u8>, xor: Vec<u8>) -> Self {
+        Bitwise::default()
             .with_sreg(Register::Reg1)
             .with_dreg(Register::Reg1)
             .with_len(mask.len() as u32)
             .with_xor(NfNetlinkData::default().with_value(xor))
-            .with_mask(NfNetlinkData::default().with_value(mask)))
+            .with_mask(NfNetlinkData::default().with_value(mask))
     }
 }
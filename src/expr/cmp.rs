@@ -1,6 +1,9 @@
+use std::net::IpAddr;
+
 use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
 
 use crate::{
+    data_type::ip_to_vec,
     parser_impls::NfNetlinkData,
     sys::{
         NFTA_CMP_DATA, NFTA_CMP_OP, NFTA_CMP_SREG, NFT_CMP_EQ, NFT_CMP_GT, NFT_CMP_GTE, NFT_CMP_LT,
@@ -29,6 +32,22 @@ pub enum CmpOp {
 }
 
 /// Comparator expression. Allows comparing the content of the netfilter register with any value.
+///
+/// The kernel only compares a register with constant data: no expression compares the contents
+/// of two registers, so two fields of a packet can't be compared with each other, as in
+/// `ip saddr == ip daddr`. The usual workarounds are:
+/// - for anti-spoofing, a [`Fib`] lookup of the source address, which finds the packets whose
+///   source isn't routed back through their input interface (`fib saddr . iif oif missing`);
+/// - loading the fields next to each other in 4 bytes registers (see [`Register::reg32`]) and
+///   looking their concatenation up in a set of the allowed pairs (see [`Lookup`]).
+///
+/// Any register may be compared, not only `Reg1`, by setting the source register with
+/// [`Cmp::with_sreg`].
+///
+/// [`Fib`]: struct.Fib.html
+/// [`Register::reg32`]: enum.Register.html#method.reg32
+/// [`Lookup`]: struct.Lookup.html
+/// [`Cmp::with_sreg`]: struct.Cmp.html#method.with_sreg
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 #[nfnetlink_struct]
 pub struct Cmp {
@@ -50,8 +69,43 @@ impl Cmp {
             data: Some(NfNetlinkData::default().with_value(data.into())),
         }
     }
+
+    /// Returns a new expression checking that the register holds `value` in network byte order,
+    /// as do the fields of the packet headers.
+    pub fn eq_be(value: impl NetworkOrder) -> Self {
+        Cmp::new(CmpOp::Eq, value.to_network_bytes())
+    }
+
+    /// Returns a new expression checking that the register holds the TCP or UDP port `port`.
+    pub fn eq_port(port: u16) -> Self {
+        Cmp::eq_be(port)
+    }
+
+    /// Returns a new expression checking that the register holds the address `ip`.
+    pub fn eq_ip(ip: IpAddr) -> Self {
+        Cmp::new(CmpOp::Eq, ip_to_vec(ip))
+    }
+}
+
+/// An integer that can be converted to network (big endian) byte order.
+pub trait NetworkOrder {
+    fn to_network_bytes(&self) -> Vec<u8>;
 }
 
+macro_rules! impl_network_order {
+    ($($ty:ty),*) => {
+        $(
+            impl NetworkOrder for $ty {
+                fn to_network_bytes(&self) -> Vec<u8> {
+                    self.to_be_bytes().to_vec()
+                }
+            }
+        )*
+    };
+}
+
+impl_network_order!(u8, u16, u32, u64, u128);
+
 impl Expression for Cmp {
     fn get_name() -> &'static str {
         "cmp"
@@ -0,0 +1,33 @@
+use rustables_macros::nfnetlink_struct;
+
+use super::Expression;
+use crate::sys::{NFTA_CONNLIMIT_COUNT, NFTA_CONNLIMIT_FLAGS, NFT_CONNLIMIT_F_INV};
+
+/// A connlimit expression matches packets depending on the number of connections currently
+/// tracked by conntrack, like `ct count 100` in nftables.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+pub struct ConnLimit {
+    #[field(NFTA_CONNLIMIT_COUNT)]
+    count: u32,
+    #[field(NFTA_CONNLIMIT_FLAGS)]
+    flags: u32,
+}
+
+impl ConnLimit {
+    /// Creates a connlimit expression matching as long as there are at most `count`
+    /// connections, or, if `over` is true, as soon as there are more than `count` connections.
+    pub fn new(count: u32, over: bool) -> Self {
+        ConnLimit::default().with_count(count).with_flags(if over {
+            NFT_CONNLIMIT_F_INV
+        } else {
+            0
+        })
+    }
+}
+
+impl Expression for ConnLimit {
+    fn get_name() -> &'static str {
+        "connlimit"
+    }
+}
@@ -1,26 +1,119 @@
 use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
 
 use crate::sys::{
-    NFTA_CT_DIRECTION, NFTA_CT_DREG, NFTA_CT_KEY, NFTA_CT_SREG, NFT_CT_MARK, NFT_CT_STATE,
+    IP_CT_DIR_ORIGINAL, IP_CT_DIR_REPLY, NFTA_CT_DIRECTION, NFTA_CT_DREG, NFTA_CT_KEY,
+    NFTA_CT_SREG, NFT_CT_BYTES, NFT_CT_DST, NFT_CT_DST_IP, NFT_CT_DST_IP6, NFT_CT_MARK,
+    NFT_CT_PKTS, NFT_CT_PROTOCOL, NFT_CT_PROTO_DST, NFT_CT_PROTO_SRC, NFT_CT_SECMARK, NFT_CT_SRC,
+    NFT_CT_SRC_IP, NFT_CT_SRC_IP6, NFT_CT_STATE, NFT_CT_STATUS,
 };
 
 use super::{Expression, Register};
 
 bitflags::bitflags! {
+    /// The states of a connection, as loaded by `Conntrack::new(ConntrackKey::State)`. The
+    /// states can be combined, e.g. `ConnTrackState::ESTABLISHED | ConnTrackState::RELATED`,
+    /// to match any of them with [`Rule::ct_states`](crate::Rule::ct_states).
     pub struct ConnTrackState: u32 {
+        /// The packet doesn't belong to a valid connection.
         const INVALID = 1;
+        /// The packet belongs to a connection that saw packets in both directions.
         const ESTABLISHED = 2;
+        /// The packet starts a connection related to an existing one, e.g. an ICMP error or an
+        /// FTP data connection.
         const RELATED = 4;
+        /// The packet starts a new connection.
         const NEW = 8;
+        /// The packet is not tracked, e.g. because of a `notrack` rule.
         const UNTRACKED = 64;
     }
 }
 
+bitflags::bitflags! {
+    /// The status bits of a connection, as loaded by `Conntrack::new(ConntrackKey::Status)`,
+    /// to match them with [`Rule::ct_status`](crate::Rule::ct_status).
+    pub struct ConnTrackStatus: u32 {
+        /// The connection was anticipated by a conntrack helper, e.g. an FTP data connection.
+        /// Unlike [`ConnTrackState::RELATED`], this excludes the ICMP errors.
+        const EXPECTED = 1;
+        /// The connection saw packets in both directions.
+        const SEEN_REPLY = 2;
+        /// The connection is assured, and won't be evicted early when the table is full.
+        const ASSURED = 4;
+        /// The connection was committed to the conntrack table.
+        const CONFIRMED = 8;
+        /// The source of the connection is translated.
+        const SRC_NAT = 16;
+        /// The destination of the connection is translated.
+        const DST_NAT = 32;
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 #[nfnetlink_enum(u32, nested = true)]
 pub enum ConntrackKey {
     State = NFT_CT_STATE,
+    /// Status bits of the connection (see [`ConnTrackStatus`]).
+    Status = NFT_CT_STATUS,
     Mark = NFT_CT_MARK,
+    /// Security mark of the connection, usually copied from the packets (see
+    /// [`Rule::save_ct_secmark`]).
+    ///
+    /// [`Rule::save_ct_secmark`]: ../struct.Rule.html#method.save_ct_secmark
+    SecMark = NFT_CT_SECMARK,
+    /// Source address of the tuple, whose length depends on the family of the table.
+    Src = NFT_CT_SRC,
+    /// Destination address of the tuple, whose length depends on the family of the table.
+    Dst = NFT_CT_DST,
+    /// Layer 4 protocol of the tuple.
+    Protocol = NFT_CT_PROTOCOL,
+    /// Source port of the tuple.
+    ProtoSrc = NFT_CT_PROTO_SRC,
+    /// Destination port of the tuple.
+    ProtoDst = NFT_CT_PROTO_DST,
+    /// IPv4 source address of the tuple.
+    SrcIp = NFT_CT_SRC_IP,
+    /// IPv4 destination address of the tuple.
+    DstIp = NFT_CT_DST_IP,
+    /// IPv6 source address of the tuple.
+    SrcIp6 = NFT_CT_SRC_IP6,
+    /// IPv6 destination address of the tuple.
+    DstIp6 = NFT_CT_DST_IP6,
+    /// Number of bytes of the connection, as a 64 bits integer in host byte order: those of the
+    /// given direction, or of both directions when no direction is given. It is only counted when
+    /// conntrack accounting is enabled (the `net.netfilter.nf_conntrack_acct` sysctl).
+    Bytes = NFT_CT_BYTES,
+    /// Number of packets of the connection, counted like `Bytes`.
+    Packets = NFT_CT_PKTS,
+}
+
+impl ConntrackKey {
+    /// The number of bytes loaded in the destination register for this key, if it is known
+    /// regardless of the family of the table.
+    pub(crate) fn len(&self) -> Option<u32> {
+        match self {
+            ConntrackKey::Protocol => Some(1),
+            ConntrackKey::ProtoSrc | ConntrackKey::ProtoDst => Some(2),
+            ConntrackKey::State
+            | ConntrackKey::Status
+            | ConntrackKey::Mark
+            | ConntrackKey::SecMark
+            | ConntrackKey::SrcIp
+            | ConntrackKey::DstIp => Some(4),
+            ConntrackKey::Bytes | ConntrackKey::Packets => Some(8),
+            ConntrackKey::SrcIp6 | ConntrackKey::DstIp6 => Some(16),
+            ConntrackKey::Src | ConntrackKey::Dst => None,
+        }
+    }
+}
+
+/// The direction of the conntrack tuple to read the keys from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ConntrackDirection {
+    /// The tuple of the packets sent by the initiator of the connection, before any NAT.
+    Original = IP_CT_DIR_ORIGINAL as u8,
+    /// The tuple of the packets expected in reply.
+    Reply = IP_CT_DIR_REPLY as u8,
 }
 
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
@@ -47,6 +140,11 @@ impl Conntrack {
         Self::default().with_dreg(Register::Reg1).with_key(key)
     }
 
+    /// Returns a new expression loading `key` from the tuple of the given `direction`.
+    pub fn tuple(key: ConntrackKey, direction: ConntrackDirection) -> Self {
+        Self::new(key).with_direction(direction as u8)
+    }
+
     pub fn set_mark_value(&mut self, reg: Register) {
         self.set_sreg(reg);
         self.set_key(ConntrackKey::Mark);
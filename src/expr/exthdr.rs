@@ -2,7 +2,8 @@ use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
 
 use crate::sys::{
     NFTA_EXTHDR_DREG, NFTA_EXTHDR_FLAGS, NFTA_EXTHDR_LEN, NFTA_EXTHDR_OFFSET, NFTA_EXTHDR_OP,
-    NFTA_EXTHDR_SREG, NFTA_EXTHDR_TYPE, NFT_EXTHDR_OP_IPV6, NFT_EXTHDR_OP_TCPOPT,
+    NFTA_EXTHDR_SREG, NFTA_EXTHDR_TYPE, NFT_EXTHDR_OP_IPV4, NFT_EXTHDR_OP_IPV6,
+    NFT_EXTHDR_OP_TCPOPT,
 };
 
 use super::{Expression, Register};
@@ -15,6 +16,8 @@ pub enum ExtHdrOp {
     IPv6 = NFT_EXTHDR_OP_IPV6,
     /// TCP options.
     TCPOpt = NFT_EXTHDR_OP_TCPOPT,
+    /// IPv4 options (Linux 5.3 and later).
+    IPv4 = NFT_EXTHDR_OP_IPV4,
 }
 
 /// Interacts with layer 4 header options.
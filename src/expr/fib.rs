@@ -0,0 +1,84 @@
+use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
+
+use crate::sys::{
+    NFTA_FIB_DREG, NFTA_FIB_FLAGS, NFTA_FIB_RESULT, NFT_FIB_RESULT_ADDRTYPE, NFT_FIB_RESULT_OIF,
+    NFT_FIB_RESULT_OIFNAME,
+};
+
+use super::{Expression, Register};
+
+/// The information loaded by a [`Fib`] expression.
+///
+/// [`Fib`]: struct.Fib.html
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[nfnetlink_enum(u32)]
+pub enum FibResult {
+    /// Index of the output interface.
+    Oif = NFT_FIB_RESULT_OIF,
+    /// Name of the output interface.
+    OifName = NFT_FIB_RESULT_OIFNAME,
+    /// Type of the address, see [`AddrType`].
+    ///
+    /// [`AddrType`]: enum.AddrType.html
+    AddrType = NFT_FIB_RESULT_ADDRTYPE,
+}
+
+impl FibResult {
+    /// The number of bytes loaded in the destination register for this result.
+    pub(crate) fn len(&self) -> u32 {
+        match self {
+            FibResult::Oif | FibResult::AddrType => 4,
+            FibResult::OifName => libc::IFNAMSIZ as u32,
+        }
+    }
+}
+
+/// The type of an address, as loaded by a [`Fib`] expression with the [`FibResult::AddrType`]
+/// result.
+///
+/// [`Fib`]: struct.Fib.html
+/// [`FibResult::AddrType`]: enum.FibResult.html#variant.AddrType
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(u8)]
+pub enum AddrType {
+    Unspec = libc::RTN_UNSPEC,
+    /// A gateway or a direct route.
+    Unicast = libc::RTN_UNICAST,
+    /// An address of the host.
+    Local = libc::RTN_LOCAL,
+    Broadcast = libc::RTN_BROADCAST,
+    Anycast = libc::RTN_ANYCAST,
+    Multicast = libc::RTN_MULTICAST,
+    Blackhole = libc::RTN_BLACKHOLE,
+    Unreachable = libc::RTN_UNREACHABLE,
+    Prohibit = libc::RTN_PROHIBIT,
+}
+
+/// Looks up the packet in the forwarding information base (the routing table), like `fib daddr
+/// type` in nftables.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[nfnetlink_struct]
+pub struct Fib {
+    #[field(NFTA_FIB_DREG)]
+    dreg: Register,
+    #[field(NFTA_FIB_RESULT)]
+    result: FibResult,
+    /// The packet fields used for the lookup, a combination of the `NFTA_FIB_F_*` flags.
+    #[field(NFTA_FIB_FLAGS)]
+    flags: u32,
+}
+
+impl Fib {
+    pub fn new(result: FibResult, flags: u32) -> Self {
+        Fib::default()
+            .with_dreg(Register::Reg1)
+            .with_result(result)
+            .with_flags(flags)
+    }
+}
+
+impl Expression for Fib {
+    fn get_name() -> &'static str {
+        "fib"
+    }
+}
@@ -0,0 +1,28 @@
+use rustables_macros::nfnetlink_struct;
+
+use super::Expression;
+use crate::sys::NFTA_FLOW_TABLE_NAME;
+
+/// A flow offload expression adds the flow of the packet to a [`Flowtable`], referenced by its
+/// name, like `flow add @fastpath` in nftables. The flowtable must be declared in the table of
+/// the rule.
+///
+/// [`Flowtable`]: ../struct.Flowtable.html
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+pub struct FlowOffload {
+    #[field(NFTA_FLOW_TABLE_NAME)]
+    table_name: String,
+}
+
+impl FlowOffload {
+    pub fn new(flowtable: impl Into<String>) -> Self {
+        FlowOffload::default().with_table_name(flowtable.into())
+    }
+}
+
+impl Expression for FlowOffload {
+    fn get_name() -> &'static str {
+        "flow_offload"
+    }
+}
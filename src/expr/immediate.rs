@@ -1,7 +1,11 @@
+use std::net::IpAddr;
+
 use rustables_macros::nfnetlink_struct;
 
 use super::{Expression, Register, Verdict, VerdictKind, VerdictType};
 use crate::{
+    data_type::ip_to_vec,
+    error::BuilderError,
     parser_impls::NfNetlinkData,
     sys::{NFTA_IMMEDIATE_DATA, NFTA_IMMEDIATE_DREG},
 };
@@ -22,24 +26,31 @@ impl Immediate {
             .with_data(NfNetlinkData::default().with_value(data))
     }
 
+    /// Returns an `Immediate` expression loading the address `ip` into `register`. The data is
+    /// 4 bytes long for IPv4 addresses and 16 bytes long for IPv6 addresses.
+    pub fn new_ip(ip: IpAddr, register: Register) -> Self {
+        Immediate::new_data(ip_to_vec(ip), register)
+    }
+
     pub fn new_verdict(kind: VerdictKind) -> Self {
-        let code = match kind {
-            VerdictKind::Drop => VerdictType::Drop,
-            VerdictKind::Accept => VerdictType::Accept,
-            VerdictKind::Queue => VerdictType::Queue,
-            VerdictKind::Continue => VerdictType::Continue,
-            VerdictKind::Break => VerdictType::Break,
-            VerdictKind::Jump { .. } => VerdictType::Jump,
-            VerdictKind::Goto { .. } => VerdictType::Goto,
-            VerdictKind::Return => VerdictType::Return,
-        };
-        let mut data = Verdict::default().with_code(code);
-        if let VerdictKind::Jump { chain } | VerdictKind::Goto { chain } = kind {
-            data.set_chain(chain);
-        }
         Immediate::default()
             .with_dreg(Register::Verdict)
-            .with_data(NfNetlinkData::default().with_verdict(data))
+            .with_data(NfNetlinkData::default().with_verdict(Verdict::from(kind)))
+    }
+
+    /// Returns an `Immediate` expression jumping (`VerdictType::Jump`) or going
+    /// (`VerdictType::Goto`) to the chain named `chain`, in the table of the rule. Returns
+    /// `BuilderError::InvalidVerdictForChain` for the other verdicts.
+    pub fn new_verdict_to_chain(
+        verdict: VerdictType,
+        chain: impl Into<String>,
+    ) -> Result<Self, BuilderError> {
+        let chain = chain.into();
+        Ok(Immediate::new_verdict(match verdict {
+            VerdictType::Jump => VerdictKind::Jump { chain },
+            VerdictType::Goto => VerdictKind::Goto { chain },
+            _ => return Err(BuilderError::InvalidVerdictForChain),
+        }))
     }
 }
 
@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use rustables_macros::nfnetlink_struct;
+
+use super::Expression;
+use crate::sys::{NFTA_LAST_MSECS, NFTA_LAST_SET};
+
+/// A last expression records when the rule last matched, like `last` in nftables, which shows
+/// the rules that are never hit (`last used never`). The kernel fills it in the rules it
+/// returns, see [`Last::elapsed`].
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+pub struct Last {
+    /// Whether the rule matched at least once.
+    #[field(NFTA_LAST_SET)]
+    set: u32,
+    /// The number of milliseconds since the rule last matched.
+    #[field(NFTA_LAST_MSECS)]
+    msecs: u64,
+}
+
+impl Last {
+    /// Returns the time elapsed since the rule last matched when it was retrieved from the
+    /// kernel, or `None` if it never matched.
+    pub fn elapsed(&self) -> Option<Duration> {
+        match self.get_set() {
+            Some(set) if *set != 0 => Some(Duration::from_millis(
+                self.get_msecs().copied().unwrap_or(0),
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl Expression for Last {
+    fn get_name() -> &'static str {
+        "last"
+    }
+}
@@ -0,0 +1,68 @@
+use rustables_macros::nfnetlink_struct;
+
+use super::Expression;
+use crate::sys::{
+    NFTA_LIMIT_BURST, NFTA_LIMIT_FLAGS, NFTA_LIMIT_RATE, NFTA_LIMIT_TYPE, NFTA_LIMIT_UNIT,
+    NFT_LIMIT_PKTS,
+};
+
+/// The period over which the rate of a [`Limit`] is measured.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LimitUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+}
+
+impl LimitUnit {
+    /// Returns the length of this period in seconds, as expected by the kernel.
+    pub fn seconds(&self) -> u64 {
+        match self {
+            LimitUnit::Second => 1,
+            LimitUnit::Minute => 60,
+            LimitUnit::Hour => 60 * 60,
+            LimitUnit::Day => 60 * 60 * 24,
+            LimitUnit::Week => 60 * 60 * 24 * 7,
+        }
+    }
+}
+
+/// A limit expression matches packets as long as their rate stays below a given rate, like
+/// `limit rate 10/second burst 5 packets` in nftables. It may also be declared as a named object
+/// shared by several rules, which then share a single aggregate rate.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+pub struct Limit {
+    #[field(NFTA_LIMIT_RATE)]
+    rate: u64,
+    /// The length of the period of the rate, in seconds.
+    #[field(NFTA_LIMIT_UNIT)]
+    unit: u64,
+    #[field(NFTA_LIMIT_BURST)]
+    burst: u32,
+    /// Whether the rate counts packets (`NFT_LIMIT_PKTS`) or bytes (`NFT_LIMIT_PKT_BYTES`).
+    #[field(NFTA_LIMIT_TYPE, name_in_functions = "type")]
+    limit_type: u32,
+    #[field(NFTA_LIMIT_FLAGS)]
+    flags: u32,
+}
+
+impl Limit {
+    /// Creates a limit matching up to `rate` packets per `unit`, with `burst` packets allowed
+    /// above that rate.
+    pub fn new(rate: u64, unit: LimitUnit, burst: u32) -> Self {
+        Limit::default()
+            .with_rate(rate)
+            .with_unit(unit.seconds())
+            .with_burst(burst)
+            .with_type(NFT_LIMIT_PKTS)
+    }
+}
+
+impl Expression for Limit {
+    fn get_name() -> &'static str {
+        "limit"
+    }
+}
@@ -2,7 +2,9 @@ use rustables_macros::nfnetlink_struct;
 
 use super::{Expression, Register};
 use crate::error::BuilderError;
-use crate::sys::{NFTA_LOOKUP_DREG, NFTA_LOOKUP_SET, NFTA_LOOKUP_SET_ID, NFTA_LOOKUP_SREG};
+use crate::sys::{
+    NFTA_LOOKUP_DREG, NFTA_LOOKUP_SET, NFTA_LOOKUP_SET_ID, NFTA_LOOKUP_SREG, NFT_SET_MAP,
+};
 use crate::Set;
 
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
@@ -31,6 +33,16 @@ impl Lookup {
 
         Ok(res)
     }
+
+    /// Creates a new lookup entry in a map, which loads the value associated with the key into
+    /// `dreg`. May return BuilderError::MissingSetName if the map has no name, or
+    /// BuilderError::NotAMap if the set is not a map.
+    pub fn new_map(map: &Set, dreg: Register) -> Result<Self, BuilderError> {
+        if map.get_flags().copied().unwrap_or(0) & NFT_SET_MAP == 0 {
+            return Err(BuilderError::NotAMap);
+        }
+        Ok(Lookup::new(map)?.with_dreg(dreg))
+    }
 }
 
 impl Expression for Lookup {
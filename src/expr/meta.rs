@@ -10,8 +10,12 @@ use crate::sys;
 pub enum MetaType {
     /// Packet ethertype protocol (skb->protocol), invalid in OUTPUT.
     Protocol = sys::NFT_META_PROTOCOL,
+    /// Packet length (skb->len).
+    Len = sys::NFT_META_LEN,
     /// Packet mark.
     Mark = sys::NFT_META_MARK,
+    /// Packet priority (skb->priority), the tc class id of the packet.
+    Priority = sys::NFT_META_PRIORITY,
     /// Packet input interface index (dev->ifindex).
     Iif = sys::NFT_META_IIF,
     /// Packet output interface index (dev->ifindex).
@@ -20,6 +24,10 @@ pub enum MetaType {
     IifName = sys::NFT_META_IIFNAME,
     /// Packet output interface name (dev->name).
     OifName = sys::NFT_META_OIFNAME,
+    /// Name of the bridge the packet was received on, in the bridge family.
+    BriIifName = sys::NFT_META_BRI_IIFNAME,
+    /// Name of the bridge the packet is sent on, in the bridge family.
+    BriOifName = sys::NFT_META_BRI_OIFNAME,
     /// Packet input interface type (dev->type).
     IifType = libc::NFT_META_IIFTYPE,
     /// Packet output interface type (dev->type).
@@ -34,8 +42,59 @@ pub enum MetaType {
     L4Proto = sys::NFT_META_L4PROTO,
     /// Socket control group (skb->sk->sk_classid).
     Cgroup = sys::NFT_META_CGROUP,
+    /// Number of the CPU processing the packet.
+    Cpu = sys::NFT_META_CPU,
     /// A 32bit pseudo-random number.
     PRandom = sys::NFT_META_PRANDOM,
+    /// Packet type (skb->pkt_type), such as unicast or broadcast.
+    PktType = sys::NFT_META_PKTTYPE,
+    /// Security mark of the packet (skb->secmark), set by the security subsystem (e.g. SELinux).
+    SecMark = sys::NFT_META_SECMARK,
+    /// Whether the packet has a security path, i.e. it was decrypted by IPsec, as a 1 byte
+    /// boolean.
+    SecPath = sys::NFT_META_SECPATH,
+    /// Packet input interface group (dev->group).
+    IifGroup = sys::NFT_META_IIFGROUP,
+    /// Packet output interface group (dev->group).
+    OifGroup = sys::NFT_META_OIFGROUP,
+    /// Current time, as nanoseconds since the Unix epoch (Linux 5.4 or later).
+    TimeNs = sys::NFT_META_TIME_NS,
+    /// Current day of the week, from 0 for Sunday to 6 for Saturday (Linux 5.4 or later).
+    TimeDay = sys::NFT_META_TIME_DAY,
+    /// Current time of the day, as seconds since midnight UTC (Linux 5.4 or later).
+    TimeHour = sys::NFT_META_TIME_HOUR,
+}
+
+impl MetaType {
+    /// The number of bytes loaded in the destination register for this key.
+    pub(crate) fn len(&self) -> u32 {
+        match self {
+            MetaType::NfProto
+            | MetaType::L4Proto
+            | MetaType::PktType
+            | MetaType::SecPath
+            | MetaType::TimeDay => 1,
+            MetaType::Protocol | MetaType::IifType | MetaType::OifType => 2,
+            MetaType::Len
+            | MetaType::Mark
+            | MetaType::Priority
+            | MetaType::Iif
+            | MetaType::Oif
+            | MetaType::SkUid
+            | MetaType::SkGid
+            | MetaType::Cgroup
+            | MetaType::Cpu
+            | MetaType::PRandom
+            | MetaType::SecMark
+            | MetaType::IifGroup
+            | MetaType::OifGroup
+            | MetaType::TimeHour => 4,
+            MetaType::TimeNs => 8,
+            MetaType::IifName | MetaType::OifName | MetaType::BriIifName | MetaType::BriOifName => {
+                libc::IFNAMSIZ as u32
+            }
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Default, Debug)]
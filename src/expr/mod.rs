@@ -21,6 +21,9 @@ pub use self::byteorder::*;
 mod cmp;
 pub use self::cmp::*;
 
+mod connlimit;
+pub use self::connlimit::*;
+
 mod counter;
 pub use self::counter::*;
 
@@ -30,9 +33,21 @@ pub use self::ct::*;
 mod exthdr;
 pub use self::exthdr::*;
 
+mod fib;
+pub use self::fib::*;
+
+mod flow_offload;
+pub use self::flow_offload::*;
+
 mod immediate;
 pub use self::immediate::*;
 
+mod last;
+pub use self::last::*;
+
+mod limit;
+pub use self::limit::*;
+
 mod log;
 pub use self::log::*;
 
@@ -48,11 +63,17 @@ pub use self::meta::*;
 mod nat;
 pub use self::nat::*;
 
+mod objref;
+pub use self::objref::*;
+
 mod payload;
 pub use self::payload::*;
 
 mod reject;
-pub use self::reject::{IcmpCode, Reject, RejectType};
+pub use self::reject::{IcmpCode, Icmpv4Code, Icmpv6Code, Reject, RejectCode, RejectType};
+
+mod range;
+pub use self::range::*;
 
 mod register;
 pub use self::register::Register;
@@ -60,6 +81,9 @@ pub use self::register::Register;
 mod rt;
 pub use self::rt::*;
 
+mod socket;
+pub use self::socket::*;
+
 mod verdict;
 pub use self::verdict::*;
 
@@ -76,6 +100,22 @@ pub struct RawExpression {
     data: ExpressionVariant,
 }
 
+impl RawExpression {
+    /// Returns the content of this expression if it is of type `T`, for example to read back the
+    /// packet and byte totals of a [`Counter`] in a rule retrieved from the kernel.
+    pub fn decode_expr<'a, T>(&'a self) -> Option<&'a T>
+    where
+        &'a T: TryFrom<&'a ExpressionVariant>,
+    {
+        self.get_data().and_then(|data| data.try_into().ok())
+    }
+
+    /// Returns the kind of this expression, parsed from its name.
+    pub fn kind(&self) -> Option<ExprKind> {
+        self.get_name().map(|name| ExprKind::from_name(name))
+    }
+}
+
 impl<T> From<T> for RawExpression
 where
     T: Expression,
@@ -125,6 +165,18 @@ macro_rules! create_expr_variant {
                     $enum::$name(val)
                 }
             }
+
+            impl<'a> TryFrom<&'a $enum> for &'a $type {
+                type Error = ();
+
+                fn try_from(val: &'a $enum) -> Result<Self, Self::Error> {
+                    match val {
+                        $enum::$name(val) => Ok(val),
+                        #[allow(unreachable_patterns)]
+                        _ => Err(()),
+                    }
+                }
+            }
         )+
 
         impl $crate::nlmsg::AttributeDecoder for RawExpression {
@@ -180,19 +232,89 @@ create_expr_variant!(
     [Bitwise, Bitwise],
     [Byteorder, Byteorder],
     [Cmp, Cmp],
+    [ConnLimit, ConnLimit],
     [Conntrack, Conntrack],
     [Counter, Counter],
     [ExpressionRaw, ExpressionRaw],
     [ExtHdr, ExtHdr],
+    [Fib, Fib],
+    [FlowOffload, FlowOffload],
+    [Immediate, Immediate],
+    [Last, Last],
+    [Limit, Limit],
+    [Log, Log],
+    [Lookup, Lookup],
+    [Masquerade, Masquerade],
+    [Meta, Meta],
+    [Nat, Nat],
+    [ObjRef, ObjRef],
+    [Payload, Payload],
+    [Range, Range],
+    [Reject, Reject],
+    [Rt, Rt],
+    [Socket, Socket]
+);
+
+macro_rules! create_expr_kind {
+    ($enum:ident $(, [$name:ident, $type:ty])+) => {
+        /// The kind of an expression, as identified by its name.
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum $enum {
+            $(
+                $name,
+            )+
+            /// An expression not supported by this library, with its name.
+            Other(String),
+        }
+
+        impl $enum {
+            pub fn from_name(name: &str) -> Self {
+                match name {
+                    $(
+                        x if x == <$type>::get_name() => $enum::$name,
+                    )+
+                    name => $enum::Other(name.to_string()),
+                }
+            }
+
+            /// Returns the name of the expression, as sent to netfilter.
+            pub fn name(&self) -> &str {
+                match self {
+                    $(
+                        $enum::$name => <$type>::get_name(),
+                    )+
+                    $enum::Other(name) => name,
+                }
+            }
+        }
+    };
+}
+
+create_expr_kind!(
+    ExprKind,
+    [Bitwise, Bitwise],
+    [Byteorder, Byteorder],
+    [Cmp, Cmp],
+    [ConnLimit, ConnLimit],
+    [Conntrack, Conntrack],
+    [Counter, Counter],
+    [ExtHdr, ExtHdr],
+    [Fib, Fib],
+    [FlowOffload, FlowOffload],
     [Immediate, Immediate],
+    [Last, Last],
+    [Limit, Limit],
     [Log, Log],
     [Lookup, Lookup],
     [Masquerade, Masquerade],
     [Meta, Meta],
     [Nat, Nat],
+    [ObjRef, ObjRef],
     [Payload, Payload],
+    [Range, Range],
     [Reject, Reject],
-    [Rt, Rt]
+    [Rt, Rt],
+    [Socket, Socket]
 );
 
 pub type ExpressionList = NfNetlinkList<RawExpression>;
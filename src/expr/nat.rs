@@ -22,12 +22,25 @@ pub enum NatType {
 pub struct Nat {
     #[field(sys::NFTA_NAT_TYPE)]
     pub nat_type: NatType,
+    /// The family of the addresses, `Ipv4` or `Ipv6`, which matters in inet tables.
     #[field(sys::NFTA_NAT_FAMILY)]
     pub family: ProtocolFamily,
+    /// The register holding the new address, or the first address of the range.
     #[field(sys::NFTA_NAT_REG_ADDR_MIN)]
     pub ip_register: Register,
+    /// The register holding the last address of the range, if any.
+    #[field(sys::NFTA_NAT_REG_ADDR_MAX)]
+    pub ip_register_max: Register,
+    /// The register holding the new port, or the first port of the range, in network byte
+    /// order. It is only used with the `NF_NAT_RANGE_PROTO_SPECIFIED` flag.
     #[field(sys::NFTA_NAT_REG_PROTO_MIN)]
     pub port_register: Register,
+    /// The register holding the last port of the range, if any.
+    #[field(sys::NFTA_NAT_REG_PROTO_MAX)]
+    pub port_register_max: Register,
+    /// A combination of the `NF_NAT_RANGE_*` flags.
+    #[field(sys::NFTA_NAT_FLAGS)]
+    pub flags: u32,
 }
 
 impl Expression for Nat {
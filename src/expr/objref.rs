@@ -0,0 +1,39 @@
+use rustables_macros::nfnetlink_struct;
+
+use super::{Expression, Register};
+use crate::object::ObjectType;
+use crate::sys::{
+    NFTA_OBJREF_IMM_NAME, NFTA_OBJREF_IMM_TYPE, NFTA_OBJREF_SET_ID, NFTA_OBJREF_SET_NAME,
+    NFTA_OBJREF_SET_SREG,
+};
+
+/// Applies a named stateful object (e.g. a conntrack helper) to the packets matching the rule.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct]
+pub struct ObjRef {
+    #[field(NFTA_OBJREF_IMM_TYPE)]
+    imm_type: ObjectType,
+    #[field(NFTA_OBJREF_IMM_NAME)]
+    imm_name: String,
+    #[field(NFTA_OBJREF_SET_SREG)]
+    set_sreg: Register,
+    #[field(NFTA_OBJREF_SET_NAME)]
+    set_name: String,
+    #[field(NFTA_OBJREF_SET_ID)]
+    set_id: u32,
+}
+
+impl ObjRef {
+    /// Returns a new `ObjRef` expression referencing the object of type `obj_type` called `name`.
+    pub fn new(obj_type: ObjectType, name: impl Into<String>) -> Self {
+        ObjRef::default()
+            .with_imm_type(obj_type)
+            .with_imm_name(name.into())
+    }
+}
+
+impl Expression for ObjRef {
+    fn get_name() -> &'static str {
+        "objref"
+    }
+}
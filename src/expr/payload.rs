@@ -2,8 +2,11 @@ use rustables_macros::nfnetlink_struct;
 
 use super::{Expression, Register};
 use crate::{
-    error::DecodeError,
-    sys::{self, NFT_PAYLOAD_LL_HEADER, NFT_PAYLOAD_NETWORK_HEADER, NFT_PAYLOAD_TRANSPORT_HEADER},
+    error::{BuilderError, DecodeError},
+    sys::{
+        self, NFT_DATA_VALUE_MAXLEN, NFT_PAYLOAD_LL_HEADER, NFT_PAYLOAD_NETWORK_HEADER,
+        NFT_PAYLOAD_TRANSPORT_HEADER,
+    },
 };
 
 /// Payload expressions refer to data from the packet's payload.
@@ -22,12 +25,45 @@ pub struct Payload {
     sreg: Register,
 }
 
+impl Payload {
+    /// Creates a payload expression loading `len` bytes at `offset` from the start of the
+    /// `base` header into `dreg`, for the fields that [`HighLevelPayload`] doesn't describe.
+    pub fn raw(
+        base: PayloadBase,
+        offset: u32,
+        len: u32,
+        dreg: Register,
+    ) -> Result<Self, BuilderError> {
+        // the kernel stores the offset on a single byte
+        if offset > u8::MAX as u32 {
+            return Err(BuilderError::PayloadOffsetTooLarge);
+        }
+        if len == 0 || len > NFT_DATA_VALUE_MAXLEN {
+            return Err(BuilderError::InvalidPayloadLength);
+        }
+        Ok(Payload::default()
+            .with_base(base as u32)
+            .with_offset(offset)
+            .with_len(len)
+            .with_dreg(dreg))
+    }
+}
+
 impl Expression for Payload {
     fn get_name() -> &'static str {
         "payload"
     }
 }
 
+/// The header from which the offset of a payload expression is computed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(u32)]
+pub enum PayloadBase {
+    LinkLayer = NFT_PAYLOAD_LL_HEADER,
+    Network = NFT_PAYLOAD_NETWORK_HEADER,
+    Transport = NFT_PAYLOAD_TRANSPORT_HEADER,
+}
+
 /// Payload expressions refer to data from the packet's payload.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum HighLevelPayload {
@@ -202,6 +238,12 @@ impl IPv4HeaderField {
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[non_exhaustive]
 pub enum IPv6HeaderField {
+    /// The 3 bytes holding the 20 bits of the flow label, which must be masked to exclude the
+    /// end of the traffic class.
+    FlowLabel,
+    /// The protocol of the header following the fixed header, which is that of the first
+    /// extension header, if any, rather than the transport protocol. See
+    /// [`Rule::ipv6_l4proto`](crate::Rule::ipv6_l4proto) to match the latter.
     NextHeader,
     HopLimit,
     Saddr,
@@ -212,6 +254,7 @@ impl HeaderField for IPv6HeaderField {
     fn offset(&self) -> u32 {
         use self::IPv6HeaderField::*;
         match *self {
+            FlowLabel => 1,
             NextHeader => 6,
             HopLimit => 7,
             Saddr => 8,
@@ -222,6 +265,7 @@ impl HeaderField for IPv6HeaderField {
     fn len(&self) -> u32 {
         use self::IPv6HeaderField::*;
         match *self {
+            FlowLabel => 3,
             NextHeader => 1,
             HopLimit => 1,
             Saddr => 16,
@@ -233,6 +277,7 @@ impl HeaderField for IPv6HeaderField {
 impl IPv6HeaderField {
     pub fn from_raw_data(offset: u32, len: u32) -> Result<Self, DecodeError> {
         Ok(match (offset, len) {
+            (1, 3) => Self::FlowLabel,
             (6, 1) => Self::NextHeader,
             (7, 1) => Self::HopLimit,
             (8, 16) => Self::Saddr,
@@ -247,6 +292,7 @@ impl IPv6HeaderField {
 pub enum TransportHeaderField {
     Tcp(TCPHeaderField),
     Udp(UDPHeaderField),
+    Dccp(DCCPHeaderField),
     ICMPv6(ICMPv6HeaderField),
 }
 
@@ -256,6 +302,7 @@ impl HeaderField for TransportHeaderField {
         match *self {
             Tcp(ref f) => f.offset(),
             Udp(ref f) => f.offset(),
+            Dccp(ref f) => f.offset(),
             ICMPv6(ref f) => f.offset(),
         }
     }
@@ -265,6 +312,7 @@ impl HeaderField for TransportHeaderField {
         match *self {
             Tcp(ref f) => f.len(),
             Udp(ref f) => f.len(),
+            Dccp(ref f) => f.len(),
             ICMPv6(ref f) => f.len(),
         }
     }
@@ -275,6 +323,11 @@ impl HeaderField for TransportHeaderField {
 pub enum TCPHeaderField {
     Sport,
     Dport,
+    Sequence,
+    AckSeq,
+    Window,
+    Checksum,
+    UrgPtr,
 }
 
 impl HeaderField for TCPHeaderField {
@@ -283,6 +336,11 @@ impl HeaderField for TCPHeaderField {
         match *self {
             Sport => 0,
             Dport => 2,
+            Sequence => 4,
+            AckSeq => 8,
+            Window => 14,
+            Checksum => 16,
+            UrgPtr => 18,
         }
     }
 
@@ -291,6 +349,11 @@ impl HeaderField for TCPHeaderField {
         match *self {
             Sport => 2,
             Dport => 2,
+            Sequence => 4,
+            AckSeq => 4,
+            Window => 2,
+            Checksum => 2,
+            UrgPtr => 2,
         }
     }
 }
@@ -300,6 +363,11 @@ impl TCPHeaderField {
         Ok(match (offset, len) {
             (0, 2) => Self::Sport,
             (2, 2) => Self::Dport,
+            (4, 4) => Self::Sequence,
+            (8, 4) => Self::AckSeq,
+            (14, 2) => Self::Window,
+            (16, 2) => Self::Checksum,
+            (18, 2) => Self::UrgPtr,
             _ => return Err(DecodeError::UnknownTCPHeaderField(offset, len)),
         })
     }
@@ -311,6 +379,7 @@ pub enum UDPHeaderField {
     Sport,
     Dport,
     Len,
+    Checksum,
 }
 
 impl HeaderField for UDPHeaderField {
@@ -320,6 +389,7 @@ impl HeaderField for UDPHeaderField {
             Sport => 0,
             Dport => 2,
             Len => 4,
+            Checksum => 6,
         }
     }
 
@@ -329,6 +399,7 @@ impl HeaderField for UDPHeaderField {
             Sport => 2,
             Dport => 2,
             Len => 2,
+            Checksum => 2,
         }
     }
 }
@@ -339,11 +410,47 @@ impl UDPHeaderField {
             (0, 2) => Self::Sport,
             (2, 2) => Self::Dport,
             (4, 2) => Self::Len,
+            (6, 2) => Self::Checksum,
             _ => return Err(DecodeError::UnknownUDPHeaderField(offset, len)),
         })
     }
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DCCPHeaderField {
+    Sport,
+    Dport,
+}
+
+impl HeaderField for DCCPHeaderField {
+    fn offset(&self) -> u32 {
+        use self::DCCPHeaderField::*;
+        match *self {
+            Sport => 0,
+            Dport => 2,
+        }
+    }
+
+    fn len(&self) -> u32 {
+        use self::DCCPHeaderField::*;
+        match *self {
+            Sport => 2,
+            Dport => 2,
+        }
+    }
+}
+
+impl DCCPHeaderField {
+    pub fn from_raw_data(offset: u32, len: u32) -> Result<Self, DecodeError> {
+        Ok(match (offset, len) {
+            (0, 2) => Self::Sport,
+            (2, 2) => Self::Dport,
+            _ => return Err(DecodeError::UnknownDCCPHeaderField(offset, len)),
+        })
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[non_exhaustive]
 pub enum ICMPv6HeaderField {
@@ -0,0 +1,57 @@
+use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
+
+use crate::{
+    parser_impls::NfNetlinkData,
+    sys::{
+        NFTA_RANGE_FROM_DATA, NFTA_RANGE_OP, NFTA_RANGE_SREG, NFTA_RANGE_TO_DATA, NFT_RANGE_EQ,
+        NFT_RANGE_NEQ,
+    },
+};
+
+use super::{Expression, Register};
+
+/// Range operator.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[nfnetlink_enum(u32, nested = true)]
+pub enum RangeOp {
+    /// The value is inside the range.
+    Eq = NFT_RANGE_EQ,
+    /// The value is outside the range.
+    Neq = NFT_RANGE_NEQ,
+}
+
+/// Range expression. Matches if the content of the register lies between two values (inclusive).
+/// As with [`Cmp`], the values are compared byte by byte, so they should be in network byte order.
+///
+/// [`Cmp`]: struct.Cmp.html
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[nfnetlink_struct]
+pub struct Range {
+    #[field(NFTA_RANGE_SREG)]
+    sreg: Register,
+    #[field(NFTA_RANGE_OP)]
+    op: RangeOp,
+    #[field(NFTA_RANGE_FROM_DATA)]
+    from_data: NfNetlinkData,
+    #[field(NFTA_RANGE_TO_DATA)]
+    to_data: NfNetlinkData,
+}
+
+impl Range {
+    /// Returns a new range expression comparing the value loaded in the register with the range
+    /// `from`..=`to` using the operator `op`.
+    pub fn new(op: RangeOp, from: impl Into<Vec<u8>>, to: impl Into<Vec<u8>>) -> Self {
+        Range {
+            sreg: Some(Register::Reg1),
+            op: Some(op),
+            from_data: Some(NfNetlinkData::default().with_value(from.into())),
+            to_data: Some(NfNetlinkData::default().with_value(to.into())),
+        }
+    }
+}
+
+impl Expression for Range {
+    fn get_name() -> &'static str {
+        "range"
+    }
+}
@@ -2,10 +2,19 @@ use std::fmt::Debug;
 
 use rustables_macros::nfnetlink_enum;
 
-use crate::sys::{NFT_REG_1, NFT_REG_2, NFT_REG_3, NFT_REG_4, NFT_REG_VERDICT};
+use crate::sys::{
+    NFT_REG32_00, NFT_REG32_01, NFT_REG32_02, NFT_REG32_03, NFT_REG32_04, NFT_REG32_05,
+    NFT_REG32_06, NFT_REG32_07, NFT_REG32_08, NFT_REG32_09, NFT_REG32_10, NFT_REG32_11,
+    NFT_REG32_12, NFT_REG32_13, NFT_REG32_14, NFT_REG32_15, NFT_REG_1, NFT_REG_2, NFT_REG_3,
+    NFT_REG_4, NFT_REG_VERDICT,
+};
 
 /// A netfilter data register. The expressions store and read data to and from these when
 /// evaluating rule statements.
+///
+/// The 16 bytes registers `Reg1` to `Reg4` overlap with the 4 bytes registers `Reg32_00` to
+/// `Reg32_15`, which allow storing several small values next to each other, as required by
+/// the lookups of concatenated keys.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 #[nfnetlink_enum(u32)]
 pub enum Register {
@@ -14,4 +23,43 @@ pub enum Register {
     Reg2 = NFT_REG_2,
     Reg3 = NFT_REG_3,
     Reg4 = NFT_REG_4,
+    Reg32_00 = NFT_REG32_00,
+    Reg32_01 = NFT_REG32_01,
+    Reg32_02 = NFT_REG32_02,
+    Reg32_03 = NFT_REG32_03,
+    Reg32_04 = NFT_REG32_04,
+    Reg32_05 = NFT_REG32_05,
+    Reg32_06 = NFT_REG32_06,
+    Reg32_07 = NFT_REG32_07,
+    Reg32_08 = NFT_REG32_08,
+    Reg32_09 = NFT_REG32_09,
+    Reg32_10 = NFT_REG32_10,
+    Reg32_11 = NFT_REG32_11,
+    Reg32_12 = NFT_REG32_12,
+    Reg32_13 = NFT_REG32_13,
+    Reg32_14 = NFT_REG32_14,
+    Reg32_15 = NFT_REG32_15,
+}
+
+impl Register {
+    /// Returns the 4 bytes register with the given `index`, between 0 and 15.
+    pub fn reg32(index: u32) -> Option<Register> {
+        if index > 15 {
+            return None;
+        }
+        Register::try_from(NFT_REG32_00 + index).ok()
+    }
+
+    /// Returns the number of bytes of data that can be stored from the start of this register up
+    /// to the end of the data registers, or `None` for the verdict register.
+    pub(crate) fn available_len(&self) -> Option<u32> {
+        // the data registers span 64 bytes
+        match *self {
+            Register::Verdict => None,
+            Register::Reg1 | Register::Reg2 | Register::Reg3 | Register::Reg4 => {
+                Some(64 - (*self as u32 - NFT_REG_1) * 16)
+            }
+            reg => Some(64 - (reg as u32 - NFT_REG32_00) * 4),
+        }
+    }
 }
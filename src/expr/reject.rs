@@ -1,6 +1,7 @@
 use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
 
 use crate::sys;
+use crate::ProtocolFamily;
 
 use super::Expression;
 
@@ -16,8 +17,30 @@ impl Expression for Reject {
 pub struct Reject {
     #[field(sys::NFTA_REJECT_TYPE, name_in_functions = "type")]
     reject_type: RejectType,
+    /// The meaning of the code depends on the type of the rejection: it is an [`IcmpCode`] for
+    /// [`RejectType::IcmpxUnreach`], and an ICMP or ICMPv6 code (depending on the family of the
+    /// rejected packet) for [`RejectType::IcmpUnreach`]. An [`IcmpCode`] can still be passed to
+    /// the setters, and read back with `IcmpCode::try_from`.
     #[field(sys::NFTA_REJECT_ICMP_CODE)]
-    icmp_code: IcmpCode,
+    icmp_code: u8,
+}
+
+impl Reject {
+    /// Creates a reject expression answering the rejected packets with `code`.
+    pub fn new(code: RejectCode) -> Self {
+        match code {
+            RejectCode::TcpRst => Reject::default().with_type(RejectType::TcpRst),
+            RejectCode::Icmpx(code) => Reject::default()
+                .with_type(RejectType::IcmpxUnreach)
+                .with_icmp_code(code),
+            RejectCode::Icmp(code) => Reject::default()
+                .with_type(RejectType::IcmpUnreach)
+                .with_icmp_code(code as u8),
+            RejectCode::Icmpv6(code) => Reject::default()
+                .with_type(RejectType::IcmpUnreach)
+                .with_icmp_code(code as u8),
+        }
+    }
 }
 
 /// An ICMP reject code.
@@ -29,7 +52,8 @@ pub enum RejectType {
     IcmpxUnreach = sys::NFT_REJECT_ICMPX_UNREACH,
 }
 
-/// An ICMP reject code.
+/// A family-agnostic ICMP reject code, translated by the kernel to the matching ICMP or ICMPv6
+/// code depending on the family of the rejected packet.
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 #[nfnetlink_enum(u8)]
 pub enum IcmpCode {
@@ -38,3 +62,77 @@ pub enum IcmpCode {
     HostUnreach = sys::NFT_REJECT_ICMPX_HOST_UNREACH,
     AdminProhibited = sys::NFT_REJECT_ICMPX_ADMIN_PROHIBITED,
 }
+
+impl From<IcmpCode> for u8 {
+    fn from(val: IcmpCode) -> Self {
+        val as u8
+    }
+}
+
+/// An ICMP "destination unreachable" code, only valid for IPv4 packets.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[repr(u8)]
+pub enum Icmpv4Code {
+    /// `ICMP_NET_UNREACH`
+    NetUnreach = 0,
+    /// `ICMP_HOST_UNREACH`
+    HostUnreach = 1,
+    /// `ICMP_PROT_UNREACH`
+    ProtUnreach = 2,
+    /// `ICMP_PORT_UNREACH`
+    PortUnreach = 3,
+    /// `ICMP_NET_ANO`
+    NetProhibited = 9,
+    /// `ICMP_HOST_ANO`
+    HostProhibited = 10,
+    /// `ICMP_PKT_FILTERED`
+    AdminProhibited = 13,
+}
+
+/// An ICMPv6 "destination unreachable" code, only valid for IPv6 packets.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[repr(u8)]
+pub enum Icmpv6Code {
+    /// `ICMPV6_NOROUTE`
+    NoRoute = 0,
+    /// `ICMPV6_ADM_PROHIBITED`
+    AdminProhibited = 1,
+    /// `ICMPV6_ADDR_UNREACH`
+    AddrUnreach = 3,
+    /// `ICMPV6_PORT_UNREACH`
+    PortUnreach = 4,
+    /// `ICMPV6_POLICY_FAIL`
+    PolicyFail = 5,
+    /// `ICMPV6_REJECT_ROUTE`
+    RejectRoute = 6,
+}
+
+/// The answer sent back to the emitter of a rejected packet.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum RejectCode {
+    /// A TCP reset, only valid for TCP packets.
+    TcpRst,
+    /// A family-agnostic ICMP code.
+    Icmpx(IcmpCode),
+    /// An ICMP code, for IPv4 packets.
+    Icmp(Icmpv4Code),
+    /// An ICMPv6 code, for IPv6 packets.
+    Icmpv6(Icmpv6Code),
+}
+
+impl RejectCode {
+    /// Returns the "administratively prohibited" code of the rules of `family`: the
+    /// family-agnostic code in `inet`, `bridge` and `netdev` tables, which the kernel translates
+    /// to ICMP or ICMPv6 depending on the rejected packet, and the ICMP (resp. ICMPv6) code in
+    /// `ip` (resp. `ip6`) tables. Returns `None` for the families that can't reject packets.
+    pub fn admin_prohibited(family: ProtocolFamily) -> Option<RejectCode> {
+        match family {
+            ProtocolFamily::Inet | ProtocolFamily::Bridge | ProtocolFamily::NetDev => {
+                Some(RejectCode::Icmpx(IcmpCode::AdminProhibited))
+            }
+            ProtocolFamily::Ipv4 => Some(RejectCode::Icmp(Icmpv4Code::AdminProhibited)),
+            ProtocolFamily::Ipv6 => Some(RejectCode::Icmpv6(Icmpv6Code::AdminProhibited)),
+            _ => None,
+        }
+    }
+}
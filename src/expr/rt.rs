@@ -2,6 +2,7 @@ use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
 
 use crate::sys::{
     NFTA_RT_DREG, NFTA_RT_KEY, NFT_RT_CLASSID, NFT_RT_NEXTHOP4, NFT_RT_NEXTHOP6, NFT_RT_TCPMSS,
+    NFT_RT_XFRM,
 };
 
 use super::{Expression, Register};
@@ -18,6 +19,9 @@ pub enum RtKey {
     NextHop6 = NFT_RT_NEXTHOP6,
     /// TCP MSS.
     TCPMSS = NFT_RT_TCPMSS,
+    /// Whether the route of the packet goes through an IPsec transformation, as a 1 byte
+    /// boolean.
+    Xfrm = NFT_RT_XFRM,
 }
 
 /// Loads routing information into a register.
@@ -0,0 +1,64 @@
+use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
+
+use crate::sys::{
+    NFTA_SOCKET_DREG, NFTA_SOCKET_KEY, NFTA_SOCKET_LEVEL, NFT_SOCKET_CGROUPV2, NFT_SOCKET_MARK,
+    NFT_SOCKET_TRANSPARENT, NFT_SOCKET_WILDCARD,
+};
+
+use super::{Expression, Register};
+
+/// Kind of socket information.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[nfnetlink_enum(u32)]
+pub enum SocketKey {
+    /// Whether the socket is transparent (IP_TRANSPARENT).
+    Transparent = NFT_SOCKET_TRANSPARENT,
+    /// Socket mark (SO_MARK).
+    Mark = NFT_SOCKET_MARK,
+    /// Whether the socket is bound to a wildcard address.
+    Wildcard = NFT_SOCKET_WILDCARD,
+    /// Id of the cgroup v2 ancestor of the socket at the given level.
+    CgroupV2 = NFT_SOCKET_CGROUPV2,
+}
+
+impl SocketKey {
+    /// The number of bytes loaded in the destination register for this key.
+    pub(crate) fn len(&self) -> u32 {
+        match self {
+            SocketKey::Transparent | SocketKey::Wildcard => 1,
+            SocketKey::Mark => 4,
+            SocketKey::CgroupV2 => 8,
+        }
+    }
+}
+
+/// Loads information about the socket associated with the packet into a register.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[nfnetlink_struct]
+pub struct Socket {
+    #[field(NFTA_SOCKET_KEY)]
+    key: SocketKey,
+    #[field(NFTA_SOCKET_DREG)]
+    dreg: Register,
+    #[field(NFTA_SOCKET_LEVEL)]
+    level: u32,
+}
+
+impl Socket {
+    pub fn new(key: SocketKey) -> Self {
+        Socket::default().with_dreg(Register::Reg1).with_key(key)
+    }
+
+    /// Loads the id of the cgroup v2 the socket belongs to, or more precisely of its ancestor at
+    /// depth `level` in the cgroup hierarchy (the root cgroup is at level 0, "system.slice" at
+    /// level 1 and so on).
+    pub fn cgroupv2(level: u32) -> Self {
+        Socket::new(SocketKey::CgroupV2).with_level(level)
+    }
+}
+
+impl Expression for Socket {
+    fn get_name() -> &'static str {
+        "socket"
+    }
+}
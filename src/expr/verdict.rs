@@ -48,3 +48,23 @@ pub enum VerdictKind {
     },
     Return,
 }
+
+impl From<VerdictKind> for Verdict {
+    fn from(kind: VerdictKind) -> Self {
+        let code = match kind {
+            VerdictKind::Drop => VerdictType::Drop,
+            VerdictKind::Accept => VerdictType::Accept,
+            VerdictKind::Queue => VerdictType::Queue,
+            VerdictKind::Continue => VerdictType::Continue,
+            VerdictKind::Break => VerdictType::Break,
+            VerdictKind::Jump { .. } => VerdictType::Jump,
+            VerdictKind::Goto { .. } => VerdictType::Goto,
+            VerdictKind::Return => VerdictType::Return,
+        };
+        let mut verdict = Verdict::default().with_code(code);
+        if let VerdictKind::Jump { chain } | VerdictKind::Goto { chain } = kind {
+            verdict.set_chain(chain);
+        }
+        verdict
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt::Debug;
+
+use rustables_macros::nfnetlink_struct;
+
+use crate::chain::{ChainPriority, HookDevices};
+use crate::error::{BuilderError, QueryError};
+use crate::nlmsg::NfNetlinkObject;
+use crate::sys::{
+    NFTA_FLOWTABLE_FLAGS, NFTA_FLOWTABLE_HANDLE, NFTA_FLOWTABLE_HOOK, NFTA_FLOWTABLE_HOOK_DEVS,
+    NFTA_FLOWTABLE_HOOK_NUM, NFTA_FLOWTABLE_HOOK_PRIORITY, NFTA_FLOWTABLE_NAME,
+    NFTA_FLOWTABLE_TABLE, NFTA_FLOWTABLE_USE, NFT_MSG_DELFLOWTABLE, NFT_MSG_GETFLOWTABLE,
+    NFT_MSG_NEWFLOWTABLE, NF_NETDEV_INGRESS,
+};
+use crate::{Batch, ProtocolFamily, Table};
+
+/// The hook of a [`Flowtable`], which always registers on the ingress path of its devices.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct(nested = true)]
+pub struct FlowtableHook {
+    #[field(NFTA_FLOWTABLE_HOOK_NUM)]
+    num: u32,
+    #[field(NFTA_FLOWTABLE_HOOK_PRIORITY)]
+    priority: u32,
+    #[field(NFTA_FLOWTABLE_HOOK_DEVS)]
+    devices: HookDevices,
+}
+
+/// A flowtable, which holds the established flows offloaded by the [`FlowOffload`] expression:
+/// the packets of these flows skip the classic forwarding path, including the rules of the
+/// chains, from the ingress hook of the devices of the flowtable.
+///
+/// [`FlowOffload`]: expr/struct.FlowOffload.html
+#[nfnetlink_struct(derive_deserialize = false)]
+#[derive(PartialEq, Eq, Default, Debug)]
+pub struct Flowtable {
+    family: ProtocolFamily,
+    #[field(NFTA_FLOWTABLE_TABLE)]
+    table: String,
+    #[field(NFTA_FLOWTABLE_NAME)]
+    name: String,
+    #[field(NFTA_FLOWTABLE_HOOK)]
+    hook: FlowtableHook,
+    /// `NFT_FLOWTABLE_HW_OFFLOAD` to offload the flows to the hardware, and
+    /// `NFT_FLOWTABLE_COUNTER` to keep counting the offloaded packets in the conntrack entries
+    /// of the flows.
+    #[field(NFTA_FLOWTABLE_FLAGS)]
+    flags: u32,
+    /// The number of references to the flowtable, only available on flowtables retrieved from
+    /// the kernel.
+    #[field(NFTA_FLOWTABLE_USE)]
+    use_count: u32,
+    /// Identifier assigned by the kernel, only available on flowtables retrieved from the
+    /// kernel.
+    #[field(NFTA_FLOWTABLE_HANDLE)]
+    handle: u64,
+}
+
+impl Flowtable {
+    /// Creates a flowtable named `name` inside the given [`Table`], hooked with `priority` on
+    /// the ingress path of `devices`.
+    ///
+    /// [`Table`]: struct.Table.html
+    pub fn new(
+        table: &Table,
+        name: impl Into<String>,
+        priority: ChainPriority,
+        devices: &[&str],
+    ) -> Result<Flowtable, BuilderError> {
+        if devices.is_empty() {
+            return Err(BuilderError::MissingHookDevice);
+        }
+        if devices.iter().any(|dev| dev.len() >= libc::IFNAMSIZ) {
+            return Err(BuilderError::InterfaceNameTooLong);
+        }
+        let hook = FlowtableHook::default()
+            .with_num(NF_NETDEV_INGRESS)
+            .with_priority(priority as u32)
+            .with_devices(HookDevices(
+                devices.iter().map(|dev| dev.to_string()).collect(),
+            ));
+        let mut flowtable = Flowtable::default()
+            .with_table(table.get_name().ok_or(BuilderError::MissingTableName)?)
+            .with_name(name.into())
+            .with_hook(hook);
+        flowtable.family = table.get_family();
+        Ok(flowtable)
+    }
+
+    /// Returns the devices of this flowtable.
+    pub fn devices(&self) -> &[String] {
+        self.get_hook()
+            .and_then(|hook| hook.get_devices())
+            .map_or(&[], |devices| devices.0.as_slice())
+    }
+
+    /// Appends this flowtable to `batch`
+    pub fn add_to_batch(self, batch: &mut Batch) -> Self {
+        batch.add(&self, crate::MsgType::Add);
+        self
+    }
+}
+
+impl NfNetlinkObject for Flowtable {
+    const MSG_TYPE_ADD: u32 = NFT_MSG_NEWFLOWTABLE;
+    const MSG_TYPE_DEL: u32 = NFT_MSG_DELFLOWTABLE;
+
+    fn get_family(&self) -> ProtocolFamily {
+        self.family
+    }
+
+    fn set_family(&mut self, family: ProtocolFamily) {
+        self.family = family;
+    }
+}
+
+pub fn list_flowtables_for_table(table: &Table) -> Result<Vec<Flowtable>, QueryError> {
+    let mut result = Vec::new();
+    crate::query::list_objects_with_data(
+        NFT_MSG_GETFLOWTABLE as u16,
+        &|flowtable: Flowtable, (table, flowtables): &mut (&Table, &mut Vec<Flowtable>)| {
+            if flowtable.get_table() == table.get_name()
+                && flowtable.get_family() == table.get_family()
+            {
+                flowtables.push(flowtable);
+            } else {
+                info!(
+                    "Ignoring flowtable {:?} because it doesn't map the table {:?}",
+                    flowtable.get_name(),
+                    table.get_name()
+                );
+            }
+            Ok(())
+        },
+        None,
+        &mut (table, &mut result),
+    )?;
+    Ok(result)
+}
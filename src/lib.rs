@@ -51,7 +51,10 @@ use rustables_macros::nfnetlink_enum;
 use std::convert::TryFrom;
 
 mod batch;
-pub use batch::{default_batch_page_size, Batch};
+pub use batch::{
+    apply_owned_table, default_batch_page_size, Batch, BatchMessages, BatchObjectKind,
+    BatchOperation, FinalizedBatch,
+};
 
 pub mod data_type;
 
@@ -61,27 +64,46 @@ pub use table::Table;
 
 mod chain;
 pub use chain::list_chains_for_table;
-pub use chain::{Chain, ChainPolicy, ChainPriority, ChainType, Hook, HookClass};
+pub use chain::{
+    Chain, ChainPolicy, ChainPriority, ChainType, Hook, HookClass, HookDevices, NetDevHookClass,
+};
+
+mod flowtable;
+pub use flowtable::list_flowtables_for_table;
+pub use flowtable::{Flowtable, FlowtableHook};
 
 pub mod error;
 
 pub mod query;
 
 pub(crate) mod nlmsg;
+pub use nlmsg::{
+    pad_netlink_object, pad_netlink_object_with_variable_size, AttributeDecoder,
+    NfNetlinkAttribute, NfNetlinkDeserializable, NfNetlinkObject, WithUnknownAttributes,
+};
+
+mod object;
+pub use object::{reset_counter, CtHelper, Object, ObjectData, ObjectType};
+
 pub(crate) mod parser;
+pub use parser::write_attribute;
 pub(crate) mod parser_impls;
 
 mod rule;
-pub use rule::list_rules_for_chain;
-pub use rule::Rule;
+pub use rule::{
+    count_rules_for_chain, get_rule, list_rules_for_chain, list_rules_for_chain_with_reset,
+};
+pub use rule::{Rule, RuleDiff, RuleGroup};
 
 pub mod expr;
 
 mod rule_methods;
-pub use rule_methods::{iface_index, Protocol};
+pub use rule_methods::{
+    iface_index, EcnCodepoint, EtherType, IcmpType, PktType, Protocol, Weekday,
+};
 
 pub mod set;
-pub use set::Set;
+pub use set::{MapBuilder, Set, VerdictMapBuilder};
 
 pub mod sys;
 
@@ -93,16 +115,30 @@ mod tests;
 /// that object. If a [`Table`] object is sent with `MsgType::Add` then that table will be added
 /// to netfilter, if sent with `MsgType::Del` it will be removed.
 ///
+/// Adding an object that already exists is not an error: tables, chains, sets and objects are
+/// left untouched (except for the attributes given in the message, e.g. the policy of a chain),
+/// which makes `MsgType::Add` suitable to apply the same ruleset several times. Note that rules
+/// have no name, so adding the same rule twice still appends a second copy of it.
+/// `MsgType::Create` instead fails with `EEXIST` when the object already exists, like
+/// `nft create` does.
+///
 /// [`Table`]: struct.Table.html
 /// [`Chain`]: struct.Chain.html
 /// [`Rule`]: struct.Rule.html
 /// [`MsgType`]: enum.MsgType.html
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum MsgType {
-    /// Add the object to netfilter.
+    /// Add the object to netfilter, or update it if it already exists.
     Add,
+    /// Add the object to netfilter, failing if it already exists.
+    Create,
     /// Remove the object from netfilter.
     Del,
+    /// Replace an existing object in netfilter. Only rules can be replaced, and the object must
+    /// hold the handle of the rule to replace (see [`Batch::replace`]).
+    ///
+    /// [`Batch::replace`]: struct.Batch.html#method.replace
+    Replace,
 }
 
 /// Denotes a protocol. Used to specify which protocol a table or set belongs to.
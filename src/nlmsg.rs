@@ -4,7 +4,7 @@ use crate::{
     error::DecodeError,
     sys::{
         nfgenmsg, nlmsghdr, NFNETLINK_V0, NFNL_MSG_BATCH_BEGIN, NFNL_MSG_BATCH_END,
-        NFNL_SUBSYS_NFTABLES, NLMSG_ALIGNTO, NLM_F_ACK, NLM_F_CREATE,
+        NFNL_SUBSYS_NFTABLES, NLMSG_ALIGNTO, NLM_F_ACK, NLM_F_CREATE, NLM_F_EXCL, NLM_F_REPLACE,
     },
     MsgType, ProtocolFamily,
 };
@@ -117,30 +117,136 @@ pub type NetlinkType = u16;
 
 pub trait AttributeDecoder {
     fn decode_attribute(&mut self, attr_type: NetlinkType, buf: &[u8]) -> Result<(), DecodeError>;
+
+    /// Parses a buffer holding only a list of attributes, without any netlink message header
+    /// in front of them, such as the ones produced by [`NfNetlinkAttribute::to_bytes`].
+    fn from_bytes(buf: &[u8]) -> Result<Self, DecodeError>
+    where
+        Self: Default + Sized,
+    {
+        crate::parser::read_attributes(buf)
+    }
 }
 
 pub trait NfNetlinkDeserializable: Sized {
     fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError>;
 }
 
+/// A top-level netfilter object, such as a table, a chain or a rule, that can be added to a
+/// [`Batch`] and parsed back from a netlink message.
+///
+/// The payload of the object is the list of its attributes: it is written with
+/// [`NfNetlinkAttribute`], and read one attribute at a time with
+/// [`AttributeDecoder::decode_attribute`], which should return
+/// `DecodeError::UnsupportedAttributeType` for the attributes it does not know (they are then
+/// skipped). Implementing this trait for a type that also implements `Default` provides
+/// [`NfNetlinkDeserializable`], which parses a whole message, checks its type against
+/// `MSG_TYPE_ADD` and `MSG_TYPE_DEL` and sets the family from the message header.
+///
+/// The types of this crate implement it through a procedural macro that is internal to the crate,
+/// but it can be implemented by hand for custom objects, e.g. a minimal description of a
+/// flowtable holding only its table and name, when the other attributes of the complete
+/// [`Flowtable`] are not needed:
+///
+/// ```
+/// use rustables::error::DecodeError;
+/// use rustables::sys::{
+///     NFTA_FLOWTABLE_NAME, NFTA_FLOWTABLE_TABLE, NFT_MSG_DELFLOWTABLE, NFT_MSG_NEWFLOWTABLE,
+/// };
+/// use rustables::{
+///     pad_netlink_object, pad_netlink_object_with_variable_size, write_attribute,
+///     AttributeDecoder, Batch, MsgType, NfNetlinkAttribute, NfNetlinkDeserializable,
+///     NfNetlinkObject, ProtocolFamily,
+/// };
+///
+/// #[derive(Debug, Default, PartialEq)]
+/// struct FlowtableName {
+///     family: ProtocolFamily,
+///     table: String,
+///     name: String,
+/// }
+///
+/// fn attr_size(value: &String) -> usize {
+///     pad_netlink_object::<rustables::sys::nlattr>()
+///         + pad_netlink_object_with_variable_size(value.get_size())
+/// }
+///
+/// impl NfNetlinkAttribute for FlowtableName {
+///     fn get_size(&self) -> usize {
+///         attr_size(&self.table) + attr_size(&self.name)
+///     }
+///
+///     fn write_payload(&self, addr: &mut [u8]) {
+///         write_attribute(NFTA_FLOWTABLE_TABLE, &self.table, addr);
+///         let addr = &mut addr[attr_size(&self.table)..];
+///         write_attribute(NFTA_FLOWTABLE_NAME, &self.name, addr);
+///     }
+/// }
+///
+/// impl AttributeDecoder for FlowtableName {
+///     fn decode_attribute(&mut self, attr_type: u16, buf: &[u8]) -> Result<(), DecodeError> {
+///         match attr_type {
+///             NFTA_FLOWTABLE_TABLE => self.table = String::deserialize(buf)?.0,
+///             NFTA_FLOWTABLE_NAME => self.name = String::deserialize(buf)?.0,
+///             _ => return Err(DecodeError::UnsupportedAttributeType(attr_type)),
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// impl NfNetlinkObject for FlowtableName {
+///     const MSG_TYPE_ADD: u32 = NFT_MSG_NEWFLOWTABLE;
+///     const MSG_TYPE_DEL: u32 = NFT_MSG_DELFLOWTABLE;
+///
+///     fn get_family(&self) -> ProtocolFamily {
+///         self.family
+///     }
+///
+///     fn set_family(&mut self, family: ProtocolFamily) {
+///         self.family = family;
+///     }
+/// }
+///
+/// let flowtable = FlowtableName {
+///     family: ProtocolFamily::Inet,
+///     table: "filter".to_string(),
+///     name: "fastpath".to_string(),
+/// };
+/// let mut batch = Batch::new();
+/// batch.add(&flowtable, MsgType::Add);
+/// let batch = batch.finalize();
+///
+/// // skip the batch begin message
+/// let msg = batch.iter().nth(1).unwrap();
+/// assert_eq!(FlowtableName::deserialize(msg).unwrap().0, flowtable);
+/// ```
+///
+/// [`Batch`]: struct.Batch.html
+/// [`Flowtable`]: struct.Flowtable.html
+/// [`NfNetlinkAttribute`]: trait.NfNetlinkAttribute.html
+/// [`AttributeDecoder::decode_attribute`]: trait.AttributeDecoder.html#tymethod.decode_attribute
+/// [`NfNetlinkDeserializable`]: trait.NfNetlinkDeserializable.html
 pub trait NfNetlinkObject:
     Sized + AttributeDecoder + NfNetlinkDeserializable + NfNetlinkAttribute
 {
+    /// The netlink message type used to create the object, e.g. `NFT_MSG_NEWTABLE`.
     const MSG_TYPE_ADD: u32;
+    /// The netlink message type used to delete the object, e.g. `NFT_MSG_DELTABLE`.
     const MSG_TYPE_DEL: u32;
 
     fn add_or_remove<'a>(&self, writer: &mut NfNetlinkWriter<'a>, msg_type: MsgType, seq: u32) {
         let raw_msg_type = match msg_type {
-            MsgType::Add => Self::MSG_TYPE_ADD,
+            MsgType::Add | MsgType::Create | MsgType::Replace => Self::MSG_TYPE_ADD,
             MsgType::Del => Self::MSG_TYPE_DEL,
         } as u16;
         writer.write_header(
             raw_msg_type,
             self.get_family(),
-            (if let MsgType::Add = msg_type {
-                self.get_add_flags()
-            } else {
-                self.get_del_flags()
+            (match msg_type {
+                MsgType::Add => self.get_add_flags(),
+                MsgType::Create => self.get_add_flags() | NLM_F_EXCL,
+                MsgType::Del => self.get_del_flags(),
+                MsgType::Replace => NLM_F_REPLACE,
             } | NLM_F_ACK) as u16,
             seq,
             None,
@@ -150,6 +256,7 @@ pub trait NfNetlinkObject:
         writer.finalize_writing_object();
     }
 
+    /// The protocol family of the object, written in the header of the netlink message.
     fn get_family(&self) -> ProtocolFamily;
 
     fn set_family(&mut self, _family: ProtocolFamily) {
@@ -161,6 +268,7 @@ pub trait NfNetlinkObject:
         self
     }
 
+    /// The netlink flags of the messages creating the object.
     fn get_add_flags(&self) -> u32 {
         NLM_F_CREATE
     }
@@ -170,6 +278,51 @@ pub trait NfNetlinkObject:
     }
 }
 
+/// Decodes an object of type `T`, keeping the raw payload of the attributes that `T` does not
+/// know, instead of skipping them.
+///
+/// Only the attributes for which [`AttributeDecoder::decode_attribute`] returns
+/// `DecodeError::UnsupportedAttributeType` are kept, e.g. the ones added by a kernel more recent
+/// than this crate, so that callers can inspect them. The malformed attributes of known types
+/// still make the whole decoding fail.
+///
+/// [`AttributeDecoder::decode_attribute`]: trait.AttributeDecoder.html#tymethod.decode_attribute
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WithUnknownAttributes<T> {
+    /// The object, decoded from the attributes it knows about.
+    pub object: T,
+    /// The type (without the byteorder and nested flags) and the payload of the unknown
+    /// attributes, in the order they appear in the message.
+    pub unknown_attributes: Vec<(NetlinkType, Vec<u8>)>,
+}
+
+impl<T> WithUnknownAttributes<T> {
+    /// Returns the payload of the first unknown attribute of type `attr_type`, if any.
+    pub fn get_unknown_attribute(&self, attr_type: NetlinkType) -> Option<&[u8]> {
+        self.unknown_attributes
+            .iter()
+            .find(|(t, _)| *t == attr_type)
+            .map(|(_, payload)| payload.as_slice())
+    }
+
+    /// Drops the unknown attributes and returns the decoded object.
+    pub fn into_inner(self) -> T {
+        self.object
+    }
+}
+
+impl<T: AttributeDecoder> AttributeDecoder for WithUnknownAttributes<T> {
+    fn decode_attribute(&mut self, attr_type: NetlinkType, buf: &[u8]) -> Result<(), DecodeError> {
+        match self.object.decode_attribute(attr_type, buf) {
+            Err(DecodeError::UnsupportedAttributeType(_)) => {
+                self.unknown_attributes.push((attr_type, buf.to_vec()));
+                Ok(())
+            }
+            res => res,
+        }
+    }
+}
+
 pub trait NfNetlinkAttribute: Debug + Sized {
     // is it a nested argument that must be marked with a NLA_F_NESTED flag?
     fn is_nested(&self) -> bool {
@@ -182,4 +335,12 @@ pub trait NfNetlinkAttribute: Debug + Sized {
 
     // example body: std::ptr::copy_nonoverlapping(self as *const Self as *const u8, addr.as_mut_ptr(), self.get_size());
     fn write_payload(&self, addr: &mut [u8]);
+
+    /// Serializes the payload of this attribute (for structures, the list of their attributes)
+    /// to a standalone buffer.
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![0; self.get_size()];
+        self.write_payload(&mut buf);
+        buf
+    }
 }
@@ -0,0 +1,273 @@
+use std::fmt::Debug;
+
+use rustables_macros::{nfnetlink_enum, nfnetlink_struct};
+
+use crate::error::{BuilderError, DecodeError, QueryError};
+use crate::expr::{Counter, Limit, LimitUnit};
+use crate::nlmsg::{
+    AttributeDecoder, NetlinkType, NfNetlinkAttribute, NfNetlinkDeserializable, NfNetlinkObject,
+};
+use crate::sys::{
+    NFTA_CT_HELPER_L3PROTO, NFTA_CT_HELPER_L4PROTO, NFTA_CT_HELPER_NAME, NFTA_OBJ_DATA,
+    NFTA_OBJ_HANDLE, NFTA_OBJ_NAME, NFTA_OBJ_TABLE, NFTA_OBJ_TYPE, NFT_MSG_DELOBJ,
+    NFT_MSG_GETOBJ_RESET, NFT_MSG_NEWOBJ, NFT_OBJECT_CONNLIMIT, NFT_OBJECT_COUNTER,
+    NFT_OBJECT_CT_EXPECT, NFT_OBJECT_CT_HELPER, NFT_OBJECT_CT_TIMEOUT, NFT_OBJECT_LIMIT,
+    NFT_OBJECT_QUOTA, NFT_OBJECT_SECMARK, NFT_OBJECT_SYNPROXY, NFT_OBJECT_TUNNEL,
+};
+use crate::{Batch, Protocol, ProtocolFamily, Table};
+
+/// The type of a stateful object.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[nfnetlink_enum(u32)]
+pub enum ObjectType {
+    Counter = NFT_OBJECT_COUNTER,
+    Quota = NFT_OBJECT_QUOTA,
+    CtHelper = NFT_OBJECT_CT_HELPER,
+    Limit = NFT_OBJECT_LIMIT,
+    ConnLimit = NFT_OBJECT_CONNLIMIT,
+    Tunnel = NFT_OBJECT_TUNNEL,
+    CtTimeout = NFT_OBJECT_CT_TIMEOUT,
+    Secmark = NFT_OBJECT_SECMARK,
+    CtExpect = NFT_OBJECT_CT_EXPECT,
+    Synproxy = NFT_OBJECT_SYNPROXY,
+}
+
+/// A conntrack helper object, which assigns an application layer helper (e.g. "ftp") to the
+/// connections it is applied to.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[nfnetlink_struct(nested = true)]
+pub struct CtHelper {
+    #[field(NFTA_CT_HELPER_NAME)]
+    name: String,
+    #[field(NFTA_CT_HELPER_L3PROTO)]
+    l3proto: u16,
+    #[field(NFTA_CT_HELPER_L4PROTO)]
+    l4proto: u8,
+}
+
+impl CtHelper {
+    /// Creates a conntrack helper object for the kernel helper `name` (e.g. "ftp" or "sip"),
+    /// tracking connections of the given layer 4 `protocol`.
+    pub fn new(name: impl Into<String>, protocol: Protocol) -> Self {
+        CtHelper::default()
+            .with_name(name.into())
+            .with_l4proto(match protocol {
+                Protocol::TCP => libc::IPPROTO_TCP,
+                Protocol::UDP => libc::IPPROTO_UDP,
+                Protocol::DCCP => libc::IPPROTO_DCCP,
+            } as u8)
+    }
+}
+
+/// The type-specific content of a stateful [`Object`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectData {
+    Counter(Counter),
+    CtHelper(CtHelper),
+    Limit(Limit),
+    /// Data of an object type we do not handle yet.
+    ObjectRaw(Vec<u8>),
+}
+
+impl ObjectData {
+    fn deserialize_with_type(obj_type: ObjectType, buf: &[u8]) -> Result<Self, DecodeError> {
+        Ok(match obj_type {
+            ObjectType::Counter => ObjectData::Counter(Counter::deserialize(buf)?.0),
+            ObjectType::CtHelper => ObjectData::CtHelper(CtHelper::deserialize(buf)?.0),
+            ObjectType::Limit => ObjectData::Limit(Limit::deserialize(buf)?.0),
+            _ => {
+                info!(
+                    "Unsupported object type {:?}, generating an ObjectRaw",
+                    obj_type
+                );
+                ObjectData::ObjectRaw(buf.to_vec())
+            }
+        })
+    }
+}
+
+impl From<Counter> for ObjectData {
+    fn from(val: Counter) -> Self {
+        ObjectData::Counter(val)
+    }
+}
+
+impl From<CtHelper> for ObjectData {
+    fn from(val: CtHelper) -> Self {
+        ObjectData::CtHelper(val)
+    }
+}
+
+impl From<Limit> for ObjectData {
+    fn from(val: Limit) -> Self {
+        ObjectData::Limit(val)
+    }
+}
+
+impl NfNetlinkAttribute for ObjectData {
+    fn is_nested(&self) -> bool {
+        true
+    }
+
+    fn get_size(&self) -> usize {
+        match self {
+            ObjectData::Counter(val) => val.get_size(),
+            ObjectData::CtHelper(val) => val.get_size(),
+            ObjectData::Limit(val) => val.get_size(),
+            ObjectData::ObjectRaw(val) => val.get_size(),
+        }
+    }
+
+    fn write_payload(&self, addr: &mut [u8]) {
+        match self {
+            ObjectData::Counter(val) => val.write_payload(addr),
+            ObjectData::CtHelper(val) => val.write_payload(addr),
+            ObjectData::Limit(val) => val.write_payload(addr),
+            ObjectData::ObjectRaw(val) => val.write_payload(addr),
+        }
+    }
+}
+
+/// A stateful object. Objects reside inside [`Table`]s and can be referenced by name from the
+/// rules of that table (see [`ObjRef`]).
+///
+/// [`Table`]: struct.Table.html
+/// [`ObjRef`]: expr/struct.ObjRef.html
+#[nfnetlink_struct(derive_decoder = false, derive_deserialize = false)]
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Object {
+    family: ProtocolFamily,
+    #[field(NFTA_OBJ_TABLE)]
+    table: String,
+    #[field(NFTA_OBJ_NAME)]
+    name: String,
+    #[field(NFTA_OBJ_TYPE, name_in_functions = "type")]
+    obj_type: ObjectType,
+    #[field(NFTA_OBJ_DATA)]
+    data: ObjectData,
+    #[field(NFTA_OBJ_HANDLE)]
+    handle: u64,
+}
+
+impl Object {
+    /// Creates a new object named `name` inside the given [`Table`]. The type of the object is
+    /// deduced from `data`.
+    ///
+    /// [`Table`]: struct.Table.html
+    pub fn new(
+        table: &Table,
+        name: impl Into<String>,
+        data: impl Into<ObjectData>,
+    ) -> Result<Object, BuilderError> {
+        let data = data.into();
+        let obj_type = match data {
+            ObjectData::Counter(_) => ObjectType::Counter,
+            ObjectData::CtHelper(_) => ObjectType::CtHelper,
+            ObjectData::Limit(_) => ObjectType::Limit,
+            ObjectData::ObjectRaw(_) => return Err(BuilderError::MissingObjectType),
+        };
+        let mut obj = Object::default()
+            .with_table(table.get_name().ok_or(BuilderError::MissingTableName)?)
+            .with_name(name.into())
+            .with_type(obj_type)
+            .with_data(data);
+        obj.family = table.get_family();
+        Ok(obj)
+    }
+
+    /// Creates a limit object named `name` inside the given [`Table`], matching up to `rate`
+    /// packets per `unit` with `burst` packets allowed above that rate. The rules referencing it
+    /// (see [`Rule::limit_ref`]) share a single aggregate rate.
+    ///
+    /// [`Table`]: struct.Table.html
+    /// [`Rule::limit_ref`]: struct.Rule.html#method.limit_ref
+    pub fn limit(
+        table: &Table,
+        name: impl Into<String>,
+        rate: u64,
+        unit: LimitUnit,
+        burst: u32,
+    ) -> Result<Object, BuilderError> {
+        Object::new(table, name, Limit::new(rate, unit, burst))
+    }
+
+    /// Sets the totals of this counter object to `nb_bytes` and `nb_packets`, e.g. to carry over
+    /// the accounting baseline of a counter from another ruleset. Returns
+    /// `BuilderError::NotACounter` for the other types of objects.
+    ///
+    /// The kernel only reads the totals when it creates the counter: adding the object with
+    /// `MsgType::Add` while a counter of the same name exists leaves the latter untouched,
+    /// together with the rules referencing it, and `MsgType::Replace` is refused for objects.
+    /// Only [`reset_counter`] changes the totals of an existing counter, by setting them to zero.
+    ///
+    /// [`reset_counter`]: fn.reset_counter.html
+    pub fn set_counter(&mut self, nb_bytes: u64, nb_packets: u64) -> Result<(), BuilderError> {
+        match self.data {
+            Some(ObjectData::Counter(ref mut counter)) => {
+                counter.set_nb_bytes(nb_bytes);
+                counter.set_nb_packets(nb_packets);
+                Ok(())
+            }
+            _ => Err(BuilderError::NotACounter),
+        }
+    }
+
+    pub fn with_counter(mut self, nb_bytes: u64, nb_packets: u64) -> Result<Self, BuilderError> {
+        self.set_counter(nb_bytes, nb_packets)?;
+        Ok(self)
+    }
+
+    /// Appends this object to `batch`
+    pub fn add_to_batch(self, batch: &mut Batch) -> Self {
+        batch.add(&self, crate::MsgType::Add);
+        self
+    }
+}
+
+impl AttributeDecoder for Object {
+    fn decode_attribute(&mut self, attr_type: NetlinkType, buf: &[u8]) -> Result<(), DecodeError> {
+        debug!("Decoding attribute {} in an object", attr_type);
+        match attr_type {
+            NFTA_OBJ_TABLE => self.table = Some(String::deserialize(buf)?.0),
+            NFTA_OBJ_NAME => self.name = Some(String::deserialize(buf)?.0),
+            NFTA_OBJ_TYPE => self.obj_type = Some(ObjectType::deserialize(buf)?.0),
+            NFTA_OBJ_DATA => {
+                // the kernel always sends the type before the data, which is how we know how
+                // to interpret the latter
+                let obj_type = self.obj_type.ok_or(DecodeError::MissingObjectType)?;
+                self.data = Some(ObjectData::deserialize_with_type(obj_type, buf)?);
+            }
+            NFTA_OBJ_HANDLE => self.handle = Some(u64::deserialize(buf)?.0),
+            _ => return Err(DecodeError::UnsupportedAttributeType(attr_type)),
+        }
+        Ok(())
+    }
+}
+
+impl NfNetlinkObject for Object {
+    const MSG_TYPE_ADD: u32 = NFT_MSG_NEWOBJ;
+    const MSG_TYPE_DEL: u32 = NFT_MSG_DELOBJ;
+
+    fn get_family(&self) -> ProtocolFamily {
+        self.family
+    }
+
+    fn set_family(&mut self, family: ProtocolFamily) {
+        self.family = family;
+    }
+}
+
+/// Atomically reads and resets the totals of the named counter `counter`, which is identified by
+/// its family, table and name. Returns the totals as they were right before the reset.
+pub fn reset_counter(counter: &Object) -> Result<Counter, QueryError> {
+    let mut filter = Object::default()
+        .with_table(counter.get_table().ok_or(BuilderError::MissingTableName)?)
+        .with_name(counter.get_name().ok_or(BuilderError::MissingObjectName)?)
+        .with_type(ObjectType::Counter);
+    filter.family = counter.family;
+
+    match crate::query::get_object(NFT_MSG_GETOBJ_RESET as u16, &filter)?.data {
+        Some(ObjectData::Counter(counter)) => Ok(counter),
+        _ => Err(QueryError::MissingObject),
+    }
+}
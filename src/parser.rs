@@ -4,30 +4,47 @@ use std::{
 };
 
 use crate::{
-    error::DecodeError,
+    error::{BuilderError, DecodeError},
     nlmsg::{
         get_operation_from_nlmsghdr_type, get_subsystem_from_nlmsghdr_type, pad_netlink_object,
         pad_netlink_object_with_variable_size, AttributeDecoder, NetlinkType, NfNetlinkAttribute,
     },
     sys::{
         nfgenmsg, nlattr, nlmsgerr, nlmsghdr, NFNETLINK_V0, NFNL_MSG_BATCH_BEGIN,
-        NFNL_MSG_BATCH_END, NFNL_SUBSYS_NFTABLES, NLA_F_NESTED, NLA_TYPE_MASK, NLMSG_DONE,
-        NLMSG_ERROR, NLMSG_MIN_TYPE, NLMSG_NOOP, NLM_F_DUMP_INTR,
+        NFNL_MSG_BATCH_END, NFNL_SUBSYS_NFTABLES, NLA_F_NESTED, NLA_TYPE_MASK, NLMSGERR_ATTR_MSG,
+        NLMSG_DONE, NLMSG_ERROR, NLMSG_MIN_TYPE, NLMSG_NOOP, NLM_F_ACK_TLVS, NLM_F_CAPPED,
+        NLM_F_DUMP_INTR,
     },
+    ProtocolFamily,
 };
 
+impl nfgenmsg {
+    /// Returns the protocol family of the object carried by the message, or
+    /// `DecodeError::UnknownProtocolFamily` if the kernel sent a family this crate doesn't know.
+    pub fn family(&self) -> Result<ProtocolFamily, DecodeError> {
+        ProtocolFamily::try_from(self.nfgen_family as i32)
+    }
+}
+
 pub fn get_nlmsghdr(buf: &[u8]) -> Result<nlmsghdr, DecodeError> {
     let size_of_hdr = size_of::<nlmsghdr>();
 
     if buf.len() < size_of_hdr {
-        return Err(DecodeError::BufTooSmall);
+        return Err(DecodeError::BufTooSmall {
+            expected: size_of_hdr,
+            available: buf.len(),
+        });
     }
 
     let nlmsghdr_ptr = buf[0..size_of_hdr].as_ptr() as *const nlmsghdr;
     let nlmsghdr = unsafe { *nlmsghdr_ptr };
 
     if nlmsghdr.nlmsg_len as usize > buf.len() || (nlmsghdr.nlmsg_len as usize) < size_of_hdr {
-        return Err(DecodeError::NlMsgTooSmall);
+        return Err(DecodeError::NlMsgTooSmall {
+            len: nlmsghdr.nlmsg_len as usize,
+            min: size_of_hdr,
+            available: buf.len(),
+        });
     }
 
     if nlmsghdr.nlmsg_flags & NLM_F_DUMP_INTR as u16 != 0 {
@@ -41,7 +58,11 @@ pub fn get_nlmsghdr(buf: &[u8]) -> Result<nlmsghdr, DecodeError> {
 pub enum NlMsg<'a> {
     Done,
     Noop,
-    Error(nlmsgerr),
+    /// A positive acknowledgement, i.e. an `NLMSG_ERROR` message whose error code is 0.
+    Ack(nlmsgerr),
+    /// An error with the message the kernel may have attached to it when extended
+    /// acknowledgements are enabled.
+    Error(nlmsgerr, Option<String>),
     NfGenMsg(nfgenmsg, &'a [u8]),
 }
 
@@ -59,7 +80,11 @@ pub fn parse_nlmsg<'a>(buf: &'a [u8]) -> Result<(nlmsghdr, NlMsg<'a>), DecodeErr
             x if x == NLMSG_NOOP => return Ok((hdr, NlMsg::Noop)),
             x if x == NLMSG_ERROR => {
                 if (hdr.nlmsg_len as usize) < size_of_hdr + size_of::<nlmsgerr>() {
-                    return Err(DecodeError::NlMsgTooSmall);
+                    return Err(DecodeError::NlMsgTooSmall {
+                        len: hdr.nlmsg_len as usize,
+                        min: size_of_hdr + size_of::<nlmsgerr>(),
+                        available: buf.len(),
+                    });
                 }
                 let mut err = unsafe {
                     *(buf[size_of_hdr..size_of_hdr + size_of::<nlmsgerr>()].as_ptr()
@@ -67,7 +92,24 @@ pub fn parse_nlmsg<'a>(buf: &'a [u8]) -> Result<(nlmsghdr, NlMsg<'a>), DecodeErr
                 };
                 // some APIs return negative values, while other return positive values
                 err.error = err.error.abs();
-                return Ok((hdr, NlMsg::Error(err)));
+                if err.error == 0 {
+                    return Ok((hdr, NlMsg::Ack(err)));
+                }
+                let message = if hdr.nlmsg_flags & NLM_F_ACK_TLVS as u16 != 0 {
+                    let mut tlvs_offset = size_of_hdr + size_of::<nlmsgerr>();
+                    // unless the kernel capped it, the payload of the message that caused the
+                    // error is echoed before the attributes
+                    if hdr.nlmsg_flags & NLM_F_CAPPED as u16 == 0 {
+                        tlvs_offset += pad_netlink_object_with_variable_size(
+                            (err.msg.nlmsg_len as usize).saturating_sub(size_of::<nlmsghdr>()),
+                        );
+                    }
+                    buf.get(tlvs_offset..hdr.nlmsg_len as usize)
+                        .and_then(parse_ext_ack_message)
+                } else {
+                    None
+                };
+                return Ok((hdr, NlMsg::Error(err, message)));
             }
             x if x == NLMSG_DONE => return Ok((hdr, NlMsg::Done)),
             x => return Err(DecodeError::UnsupportedType(x as u16)),
@@ -88,7 +130,11 @@ pub fn parse_nlmsg<'a>(buf: &'a [u8]) -> Result<(nlmsghdr, NlMsg<'a>), DecodeErr
     if hdr.nlmsg_len as usize > buf.len()
         || (hdr.nlmsg_len as usize) < size_of_hdr + size_of_nfgenmsg
     {
-        return Err(DecodeError::NlMsgTooSmall);
+        return Err(DecodeError::NlMsgTooSmall {
+            len: hdr.nlmsg_len as usize,
+            min: size_of_hdr + size_of_nfgenmsg,
+            available: buf.len(),
+        });
     }
 
     let nfgenmsg_ptr = buf[size_of_hdr..size_of_hdr + size_of_nfgenmsg].as_ptr() as *const nfgenmsg;
@@ -103,6 +149,58 @@ pub fn parse_nlmsg<'a>(buf: &'a [u8]) -> Result<(nlmsghdr, NlMsg<'a>), DecodeErr
     Ok((hdr, NlMsg::NfGenMsg(nfgenmsg, raw_value)))
 }
 
+/// Extracts the human-readable message from the extended acknowledgement attributes of an error.
+fn parse_ext_ack_message(mut buf: &[u8]) -> Option<String> {
+    let header_len = pad_netlink_object::<nlattr>();
+    while buf.len() >= header_len {
+        let nlattr = unsafe { *transmute::<*const u8, *const nlattr>(buf.as_ptr()) };
+        let attr_len = nlattr.nla_len as usize;
+        if attr_len < header_len || attr_len > buf.len() {
+            return None;
+        }
+        if nlattr.nla_type & NLA_TYPE_MASK as u16 == NLMSGERR_ATTR_MSG as u16 {
+            let msg = &buf[header_len..attr_len];
+            // the message is null-terminated
+            let msg = msg.split(|c| *c == 0).next().unwrap_or(msg);
+            return Some(String::from_utf8_lossy(msg).into_owned());
+        }
+        buf = &buf[pad_netlink_object_with_variable_size(attr_len).min(buf.len())..];
+    }
+    None
+}
+
+/// The type of the userdata entry holding the comment of an object, which is the same for all
+/// the objects (tables, chains, rules and set elements) in the format written by nft.
+const COMMENT_UDATA_TYPE: u8 = 0;
+
+/// Encodes `comment` as userdata that nft recognizes as the comment of an object. Returns
+/// `BuilderError::InvalidComment` if the comment is more than 127 bytes long or contains a null
+/// byte.
+pub(crate) fn encode_comment_userdata(comment: &str) -> Result<Vec<u8>, BuilderError> {
+    if comment.len() > 127 || comment.contains('\0') {
+        return Err(BuilderError::InvalidComment);
+    }
+    // a single type-length-value entry, whose value is null-terminated
+    let mut userdata = vec![COMMENT_UDATA_TYPE, comment.len() as u8 + 1];
+    userdata.extend(comment.as_bytes());
+    userdata.push(0);
+    Ok(userdata)
+}
+
+/// Extracts the comment from the userdata of an object, if there is one.
+pub(crate) fn decode_comment_userdata(mut userdata: &[u8]) -> Option<String> {
+    while let [ty, len, rest @ ..] = userdata {
+        let value = rest.get(..*len as usize)?;
+        if *ty == COMMENT_UDATA_TYPE {
+            // don't rely on the value being null-terminated
+            let value = value.split(|c| *c == 0).next().unwrap_or(value);
+            return Some(String::from_utf8_lossy(value).into_owned());
+        }
+        userdata = &rest[*len as usize..];
+    }
+    None
+}
+
 /// Write the attribute, preceded by a `libc::nlattr`
 // rewrite of `mnl_attr_put`
 pub fn write_attribute<'a>(ty: NetlinkType, obj: &impl NfNetlinkAttribute, mut buf: &mut [u8]) {
@@ -135,11 +233,23 @@ pub(crate) fn read_attributes<T: AttributeDecoder + Default>(buf: &[u8]) -> Resu
     let mut remaining_size = buf.len();
     let mut pos = 0;
     let mut res = T::default();
-    while remaining_size > pad_netlink_object::<nlattr>() {
+    while remaining_size >= pad_netlink_object::<nlattr>() {
         let nlattr = unsafe { *transmute::<*const u8, *const nlattr>(buf[pos..].as_ptr()) };
         // ignore the byteorder and nested attributes
         let nla_type = nlattr.nla_type & NLA_TYPE_MASK as u16;
 
+        // reject the attributes whose length is inconsistent with the message, instead of
+        // reading out of bounds
+        if (nlattr.nla_len as usize) < pad_netlink_object::<nlattr>()
+            || nlattr.nla_len as usize > remaining_size
+        {
+            return Err(DecodeError::InvalidAttributeLength {
+                offset: pos,
+                len: nlattr.nla_len as usize,
+                available: remaining_size,
+            });
+        }
+
         pos += pad_netlink_object::<nlattr>();
         let attr_remaining_size = nlattr.nla_len as usize - pad_netlink_object::<nlattr>();
         match T::decode_attribute(&mut res, nla_type, &buf[pos..pos + attr_remaining_size]) {
@@ -153,11 +263,16 @@ pub(crate) fn read_attributes<T: AttributeDecoder + Default>(buf: &[u8]) -> Resu
         }
         pos += pad_netlink_object_with_variable_size(attr_remaining_size);
 
-        remaining_size -= pad_netlink_object_with_variable_size(nlattr.nla_len as usize);
+        remaining_size = remaining_size.saturating_sub(pad_netlink_object_with_variable_size(
+            nlattr.nla_len as usize,
+        ));
     }
 
     if remaining_size != 0 {
-        Err(DecodeError::InvalidDataSize)
+        Err(DecodeError::TrailingData {
+            offset: buf.len() - remaining_size,
+            len: remaining_size,
+        })
     } else {
         Ok(res)
     }
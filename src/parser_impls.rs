@@ -10,11 +10,10 @@ use crate::{
     expr::Verdict,
     nlmsg::{
         pad_netlink_object, pad_netlink_object_with_variable_size, AttributeDecoder,
-        NfNetlinkAttribute, NfNetlinkDeserializable, NfNetlinkObject,
+        NfNetlinkAttribute, NfNetlinkDeserializable, NfNetlinkObject, WithUnknownAttributes,
     },
     parser::{parse_object, write_attribute},
     sys::{nlattr, NFTA_DATA_VALUE, NFTA_DATA_VERDICT, NFTA_LIST_ELEM, NLA_TYPE_MASK},
-    ProtocolFamily,
 };
 
 impl NfNetlinkAttribute for u8 {
@@ -153,9 +152,25 @@ where
         self
     }
 
+    /// Iterates over the objects in the order they are written in the message, which, for the
+    /// lists returned by the kernel (e.g. the expressions of a rule), is the order the kernel
+    /// stores them in.
     pub fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T> {
         self.objs.iter()
     }
+
+    /// Returns the object at position `index` in the list.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.objs.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.objs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objs.is_empty()
+    }
 }
 
 impl<T> NfNetlinkAttribute for NfNetlinkList<T>
@@ -190,7 +205,7 @@ where
         let mut objs = Vec::new();
 
         let mut pos = 0;
-        while buf.len() - pos > pad_netlink_object::<nlattr>() {
+        while buf.len() - pos >= pad_netlink_object::<nlattr>() {
             let nlattr = unsafe { *transmute::<*const u8, *const nlattr>(buf[pos..].as_ptr()) };
             // ignore the byteorder and nested attributes
             let nla_type = nlattr.nla_type & NLA_TYPE_MASK as u16;
@@ -198,6 +213,15 @@ where
             if nla_type != NFTA_LIST_ELEM {
                 return Err(DecodeError::UnsupportedAttributeType(nla_type));
             }
+            if (nlattr.nla_len as usize) < pad_netlink_object::<nlattr>()
+                || nlattr.nla_len as usize > buf.len() - pos
+            {
+                return Err(DecodeError::InvalidAttributeLength {
+                    offset: pos,
+                    len: nlattr.nla_len as usize,
+                    available: buf.len() - pos,
+                });
+            }
 
             let (obj, remaining) = T::deserialize(
                 &buf[pos + pad_netlink_object::<nlattr>()..pos + nlattr.nla_len as usize],
@@ -207,11 +231,16 @@ where
             }
             objs.push(obj);
 
-            pos += pad_netlink_object_with_variable_size(nlattr.nla_len as usize);
+            // the last attribute may lack its padding
+            pos = (pos + pad_netlink_object_with_variable_size(nlattr.nla_len as usize))
+                .min(buf.len());
         }
 
         if pos != buf.len() {
-            Err(DecodeError::InvalidDataSize)
+            Err(DecodeError::TrailingData {
+                offset: pos,
+                len: buf.len() - pos,
+            })
         } else {
             Ok((Self { objs }, &[]))
         }
@@ -240,7 +269,23 @@ where
             <T as NfNetlinkObject>::MSG_TYPE_ADD,
             <T as NfNetlinkObject>::MSG_TYPE_DEL,
         )?;
-        obj.set_family(ProtocolFamily::try_from(nfgenmsg.nfgen_family as i32)?);
+        obj.set_family(nfgenmsg.family()?);
+
+        Ok((obj, remaining_data))
+    }
+}
+
+impl<T> NfNetlinkDeserializable for WithUnknownAttributes<T>
+where
+    T: NfNetlinkObject + AttributeDecoder + Default + Sized,
+{
+    fn deserialize(buf: &[u8]) -> Result<(Self, &[u8]), DecodeError> {
+        let (mut obj, nfgenmsg, remaining_data) = parse_object::<WithUnknownAttributes<T>>(
+            buf,
+            <T as NfNetlinkObject>::MSG_TYPE_ADD,
+            <T as NfNetlinkObject>::MSG_TYPE_DEL,
+        )?;
+        obj.object.set_family(nfgenmsg.family()?);
 
         Ok((obj, remaining_data))
     }
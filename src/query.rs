@@ -1,21 +1,26 @@
 use std::os::unix::prelude::RawFd;
 
-use nix::sys::socket::{self, AddressFamily, MsgFlags, SockFlag, SockProtocol, SockType};
+use nix::sys::socket::{self, sockopt, AddressFamily, MsgFlags, SockFlag, SockProtocol, SockType};
 
 use crate::{
     error::QueryError,
     nlmsg::{
-        nft_nlmsg_maxsize, pad_netlink_object_with_variable_size, NfNetlinkAttribute,
-        NfNetlinkObject, NfNetlinkWriter,
+        get_operation_from_nlmsghdr_type, nft_nlmsg_maxsize, pad_netlink_object_with_variable_size,
+        NfNetlinkAttribute, NfNetlinkDeserializable, NfNetlinkObject, NfNetlinkWriter,
     },
-    parser::{parse_nlmsg, NlMsg},
-    sys::{NLM_F_DUMP, NLM_F_MULTI},
+    parser::{get_nlmsghdr, parse_nlmsg, NlMsg},
+    sys::{NETLINK_CAP_ACK, NETLINK_EXT_ACK, NLM_F_DUMP, NLM_F_MULTI},
     ProtocolFamily,
 };
 
+/// Receives and processes the messages sent by netfilter until the message with sequence number
+/// `max_seq` (or, if `acked` is set, its acknowledgement) is received, or until the end of a
+/// multipart message if `max_seq` is `None`. `cb` is called on each of the messages holding an
+/// object.
 pub(crate) fn recv_and_process<'a, T>(
     sock: RawFd,
     max_seq: Option<u32>,
+    acked: bool,
     cb: Option<&dyn Fn(&[u8], &mut T) -> Result<(), QueryError>>,
     working_data: &'a mut T,
 ) -> Result<(), QueryError> {
@@ -40,17 +45,16 @@ pub(crate) fn recv_and_process<'a, T>(
             debug!("Calling parse_nlmsg");
             let (nlmsghdr, msg) = parse_nlmsg(&buf)?;
             debug!("Got a valid netlink message: {:?} {:?}", nlmsghdr, msg);
+            let is_ack = matches!(msg, NlMsg::Ack(_) | NlMsg::Error(..));
 
             match msg {
                 NlMsg::Done => {
                     return Ok(());
                 }
-                NlMsg::Error(e) => {
-                    if e.error != 0 {
-                        return Err(QueryError::NetlinkError(e));
-                    }
+                NlMsg::Error(e, message) => {
+                    return Err(QueryError::NetlinkError(e, message));
                 }
-                NlMsg::Noop => {}
+                NlMsg::Ack(_) | NlMsg::Noop => {}
                 NlMsg::NfGenMsg(_genmsg, _data) => {
                     if let Some(cb) = cb {
                         cb(&buf[0..nlmsghdr.nlmsg_len as usize], working_data)?;
@@ -66,7 +70,8 @@ pub(crate) fn recv_and_process<'a, T>(
 
             // retrieve the next message
             if let Some(max_seq) = max_seq {
-                if nlmsghdr.nlmsg_seq >= max_seq {
+                // the objects echoed by the kernel come before the acknowledgements
+                if nlmsghdr.nlmsg_seq >= max_seq && (is_ack || !acked) {
                     return Ok(());
                 }
             }
@@ -87,6 +92,121 @@ pub(crate) fn recv_and_process<'a, T>(
     }
 }
 
+/// Options of the netlink sockets opened to communicate with netfilter.
+///
+/// The kernel drops the messages that don't fit in the receive buffer of a socket, which makes
+/// large dumps fail with `ENOBUFS` on busy systems. By default, the buffers keep the sizes set
+/// by the `net.core.rmem_default` and `net.core.wmem_default` sysctls, usually around 200
+/// KBytes. A few MBytes, e.g. 4 MBytes, are enough for dumps of tens of thousands of rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketOptions {
+    recv_buffer_size: Option<usize>,
+    send_buffer_size: Option<usize>,
+    extended_ack: bool,
+}
+
+impl Default for SocketOptions {
+    fn default() -> Self {
+        SocketOptions {
+            recv_buffer_size: None,
+            send_buffer_size: None,
+            extended_ack: true,
+        }
+    }
+}
+
+impl SocketOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the size of the receive buffer of the socket, with `SO_RCVBUFFORCE` if the process
+    /// has the `CAP_NET_ADMIN` capability, and otherwise with `SO_RCVBUF`, in which case the
+    /// size is capped by the `net.core.rmem_max` sysctl.
+    pub fn with_recv_buffer_size(mut self, size: usize) -> Self {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Sets the size of the send buffer of the socket, with `SO_SNDBUFFORCE` or `SO_SNDBUF`
+    /// as for [`SocketOptions::with_recv_buffer_size`]. Without the capability, the size is
+    /// capped by the `net.core.wmem_max` sysctl.
+    ///
+    /// [`SocketOptions::with_recv_buffer_size`]: struct.SocketOptions.html#method.with_recv_buffer_size
+    pub fn with_send_buffer_size(mut self, size: usize) -> Self {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Enables or disables the extended acknowledgements (`NETLINK_EXT_ACK`), enabled by
+    /// default, with which the kernel explains the errors it returns, e.g. "Chain not found" in
+    /// addition to `ENOENT`: the explanation is returned in `QueryError::NetlinkError`. They
+    /// come with `NETLINK_CAP_ACK`, so that the kernel doesn't echo the whole message that
+    /// caused an error. The kernels that don't support them (before Linux 4.12) simply return
+    /// the errors without explanation, but they can be disabled altogether here.
+    pub fn with_extended_ack(mut self, enable: bool) -> Self {
+        self.extended_ack = enable;
+        self
+    }
+
+    pub(crate) fn apply(&self, sock: RawFd) -> Result<(), QueryError> {
+        if let Some(size) = self.recv_buffer_size {
+            socket::setsockopt(sock, sockopt::RcvBufForce, &size)
+                .or_else(|_| socket::setsockopt(sock, sockopt::RcvBuf, &size))
+                .map_err(QueryError::SocketOptionError)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket::setsockopt(sock, sockopt::SndBufForce, &size)
+                .or_else(|_| socket::setsockopt(sock, sockopt::SndBuf, &size))
+                .map_err(QueryError::SocketOptionError)?;
+        }
+        if self.extended_ack {
+            // these options are not supported by older kernels, where the errors simply come
+            // without a message, so a failure here is not fatal
+            for (option, name) in [
+                (NETLINK_EXT_ACK, "extended acknowledgements"),
+                (NETLINK_CAP_ACK, "capped acknowledgements"),
+            ] {
+                if set_netlink_flag(sock, option).is_err() {
+                    debug!("Couldn't enable the {} on the netlink socket", name);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn set_netlink_flag(sock: RawFd, option: u32) -> Result<(), nix::Error> {
+    let enable: libc::c_int = 1;
+    let res = unsafe {
+        libc::setsockopt(
+            sock,
+            libc::SOL_NETLINK,
+            option as libc::c_int,
+            &enable as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    nix::errno::Errno::result(res).map(drop)
+}
+
+/// Opens a netlink socket to netfilter with the given `options`.
+pub(crate) fn open_netfilter_socket(options: &SocketOptions) -> Result<RawFd, QueryError> {
+    let sock = socket::socket(
+        AddressFamily::Netlink,
+        SockType::Raw,
+        SockFlag::empty(),
+        SockProtocol::NetlinkNetFilter,
+    )
+    .map_err(QueryError::NetlinkOpenError)?;
+    if let Err(e) = options.apply(sock) {
+        let _ = nix::unistd::close(sock);
+        return Err(e);
+    }
+
+    Ok(sock)
+}
+
 pub(crate) fn socket_close_wrapper<E>(
     sock: RawFd,
     cb: impl FnOnce(RawFd) -> Result<(), E>,
@@ -103,6 +223,18 @@ where
     Ok(ret?)
 }
 
+/// Deserializes an object received in answer to a request of type `data_type`, so that a
+/// malformed message doesn't get mistaken for an error in the netlink exchange itself.
+pub(crate) fn deserialize_object<Object: NfNetlinkDeserializable>(
+    data_type: u16,
+    buf: &[u8],
+) -> Result<Object, QueryError> {
+    debug!("Calling Object::deserialize()");
+    Object::deserialize(buf)
+        .map(|(obj, _)| obj)
+        .map_err(|e| QueryError::InvalidObject(data_type, e))
+}
+
 /// Returns a buffer containing a netlink message which requests a list of all the netfilter
 /// matching objects (e.g. tables, chains, rules, ...).
 /// Supply the type of objects to retrieve (e.g. libc::NFT_MSG_GETTABLE), and a search filter.
@@ -138,33 +270,133 @@ pub fn list_objects_with_data<'a, Object, Accumulator>(
     filter: Option<&Object>,
     working_data: &'a mut Accumulator,
 ) -> Result<(), QueryError>
+where
+    Object: NfNetlinkObject + NfNetlinkAttribute,
+{
+    list_objects_with_options(
+        data_type,
+        cb,
+        filter,
+        working_data,
+        &SocketOptions::default(),
+    )
+}
+
+/// Lists objects like [`list_objects_with_data`], over a socket opened with the given
+/// `options`, e.g. with a larger receive buffer for large dumps.
+pub fn list_objects_with_options<'a, Object, Accumulator>(
+    data_type: u16,
+    cb: &dyn Fn(Object, &mut Accumulator) -> Result<(), QueryError>,
+    filter: Option<&Object>,
+    working_data: &'a mut Accumulator,
+    options: &SocketOptions,
+) -> Result<(), QueryError>
 where
     Object: NfNetlinkObject + NfNetlinkAttribute,
 {
     debug!("Listing objects of kind {}", data_type);
-    let sock = socket::socket(
-        AddressFamily::Netlink,
-        SockType::Raw,
-        SockFlag::empty(),
-        SockProtocol::NetlinkNetFilter,
+    dump_objects(
+        data_type,
+        &|buf: &[u8], working_data: &mut Accumulator| {
+            cb(deserialize_object(data_type, buf)?, working_data)
+        },
+        filter,
+        working_data,
+        options,
     )
-    .map_err(QueryError::NetlinkOpenError)?;
+}
 
+/// Counts the objects of a certain type (e.g. libc::NFT_MSG_GETRULE) matching `filter`, without
+/// deserializing them.
+pub(crate) fn count_objects<Object>(data_type: u16, filter: &Object) -> Result<usize, QueryError>
+where
+    Object: NfNetlinkObject + NfNetlinkAttribute,
+{
+    debug!("Counting objects of kind {}", data_type);
+    let mut count = 0;
+    dump_objects(
+        data_type,
+        &count_object_message::<Object>,
+        Some(filter),
+        &mut count,
+        &SocketOptions::default(),
+    )?;
+    Ok(count)
+}
+
+/// Increments `count` if the message in `buf` holds an object of type `Object`.
+pub(crate) fn count_object_message<Object: NfNetlinkObject>(
+    buf: &[u8],
+    count: &mut usize,
+) -> Result<(), QueryError> {
+    let hdr = get_nlmsghdr(buf)?;
+    if get_operation_from_nlmsghdr_type(hdr.nlmsg_type) as u32 == Object::MSG_TYPE_ADD {
+        *count += 1;
+    }
+    Ok(())
+}
+
+/// A callback called on the raw netlink message of each object received.
+type RawObjectCallback<'a, T> = dyn Fn(&[u8], &mut T) -> Result<(), QueryError> + 'a;
+
+/// Dumps the objects of a certain type matching `filter`, calling `cb` on the raw message of
+/// each of them.
+fn dump_objects<Object, Accumulator>(
+    data_type: u16,
+    cb: &RawObjectCallback<'_, Accumulator>,
+    filter: Option<&Object>,
+    working_data: &mut Accumulator,
+    options: &SocketOptions,
+) -> Result<(), QueryError>
+where
+    Object: NfNetlinkAttribute,
+{
     let seq = 0;
 
     let chains_buf = get_list_of_objects(data_type, seq, filter)?;
-    socket::send(sock, &chains_buf, MsgFlags::empty()).map_err(QueryError::NetlinkSendError)?;
 
+    let sock = open_netfilter_socket(options)?;
     socket_close_wrapper(sock, move |sock| {
+        socket::send(sock, &chains_buf, MsgFlags::empty()).map_err(QueryError::NetlinkSendError)?;
         // the kernel should return NLM_F_MULTI objects
+        recv_and_process(sock, None, false, Some(cb), working_data)
+    })
+}
+
+/// Retrieves a single object of a certain type (e.g. libc::NFT_MSG_GETOBJ), identified by the
+/// attributes of `filter`. Unlike [`list_objects_with_data`], this does not dump all the objects
+/// matching the filter, so the request can safely reset the state of the object, as reset
+/// operations (e.g. libc::NFT_MSG_GETOBJ_RESET) would otherwise reset every object dumped.
+pub fn get_object<Object>(data_type: u16, filter: &Object) -> Result<Object, QueryError>
+where
+    Object: NfNetlinkObject + NfNetlinkAttribute,
+{
+    debug!("Retrieving an object of kind {}", data_type);
+    let seq = 0;
+
+    let mut buffer = Vec::new();
+    let mut writer = NfNetlinkWriter::new(&mut buffer);
+    writer.write_header(data_type, filter.get_family(), 0, seq, None);
+    let buf = writer.add_data_zeroed(filter.get_size());
+    filter.write_payload(buf);
+    writer.finalize_writing_object();
+
+    let mut res = None;
+    let sock = open_netfilter_socket(&SocketOptions::default())?;
+    socket_close_wrapper(sock, |sock| {
+        // close the socket even if the request cannot be sent
+        socket::send(sock, &buffer, MsgFlags::empty()).map_err(QueryError::NetlinkSendError)?;
+        // the kernel answers with a single message, so we stop at the first one
         recv_and_process(
             sock,
-            None,
-            Some(&|buf: &[u8], working_data: &mut Accumulator| {
-                debug!("Calling Object::deserialize()");
-                cb(Object::deserialize(buf)?.0, working_data)
+            Some(seq),
+            false,
+            Some(&|buf: &[u8], res: &mut Option<Object>| {
+                *res = Some(deserialize_object(data_type, buf)?);
+                Ok(())
             }),
-            working_data,
+            &mut res,
         )
-    })
+    })?;
+    res.ok_or(QueryError::MissingObject)
 }
@@ -1,12 +1,20 @@
-use std::fmt::Debug;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display};
 
 use rustables_macros::nfnetlink_struct;
 
-use crate::chain::Chain;
+use crate::chain::{Chain, ChainType, HookClass};
 use crate::error::{BuilderError, QueryError};
-use crate::expr::{ExpressionList, RawExpression};
+use ipnetwork::IpNetwork;
+
+use crate::data_type::{ip_to_vec, vec_to_ip};
+use crate::expr::{
+    CmpOp, ExpressionList, ExpressionVariant, FibResult, HighLevelPayload, IPv4HeaderField,
+    IPv6HeaderField, MetaType, NatType, NetworkHeaderField, RawExpression, Register,
+};
 use crate::nlmsg::NfNetlinkObject;
-use crate::query::list_objects_with_data;
+use crate::parser::{decode_comment_userdata, encode_comment_userdata};
+use crate::query::{count_objects, get_object, list_objects_with_data};
 use crate::sys::{
     NFTA_RULE_CHAIN, NFTA_RULE_EXPRESSIONS, NFTA_RULE_HANDLE, NFTA_RULE_ID, NFTA_RULE_POSITION,
     NFTA_RULE_TABLE, NFTA_RULE_USERDATA, NFT_MSG_DELRULE, NFT_MSG_NEWRULE, NLM_F_APPEND,
@@ -33,14 +41,19 @@ pub struct Rule {
     userdata: Vec<u8>,
     #[field(NFTA_RULE_ID)]
     id: u32,
+    /// The type and hook number of the base chain given to [`Rule::new`], against which the NAT
+    /// helpers check their expressions.
+    base_chain: Option<(Option<ChainType>, u32)>,
 }
 
 impl Rule {
-    /// Creates a new rule object in the given [`Chain`].
+    /// Creates a new rule object in the given [`Chain`]. The rule remembers the type and hook of
+    /// a base chain, so that the NAT helpers can check that the chain performs them (see
+    /// [`Rule::check_nat_chain`]).
     ///
     /// [`Chain`]: struct.Chain.html
     pub fn new(chain: &Chain) -> Result<Rule, BuilderError> {
-        Ok(Rule::default()
+        let mut rule = Rule::default()
             .with_family(chain.get_family())
             .with_table(
                 chain
@@ -51,7 +64,29 @@ impl Rule {
                 chain
                     .get_name()
                     .ok_or(BuilderError::MissingChainInformationError)?,
-            ))
+            );
+        rule.base_chain = chain
+            .get_hook()
+            .and_then(|hook| hook.get_class())
+            .map(|hook| (chain.get_type().copied(), *hook));
+        Ok(rule)
+    }
+
+    /// Creates a new rule object in the chain named `chain`, inside the table named `table` of
+    /// `family`. A rule only holds the names of its table and chain, so, unlike with
+    /// [`Rule::new`], the description of a rule can be built as plain data, e.g. from a
+    /// configuration file, without creating the [`Chain`] first.
+    ///
+    /// [`Chain`]: struct.Chain.html
+    pub fn in_chain(
+        family: ProtocolFamily,
+        table: impl Into<String>,
+        chain: impl Into<String>,
+    ) -> Rule {
+        Rule::default()
+            .with_family(family)
+            .with_table(table.into())
+            .with_chain(chain.into())
     }
 
     pub fn add_expr(&mut self, e: impl Into<RawExpression>) {
@@ -70,6 +105,304 @@ impl Rule {
         self
     }
 
+    /// Adds the expression `e` to this rule only if `cond` is true, e.g. to add a [`Counter`]
+    /// depending on the configuration without breaking the builder chain.
+    ///
+    /// [`Counter`]: expr/struct.Counter.html
+    pub fn add_expr_if(mut self, cond: bool, e: impl Into<RawExpression>) -> Self {
+        if cond {
+            self.add_expr(e);
+        }
+        self
+    }
+
+    /// Applies `f` to this rule and `value` if the latter is set, and returns the rule unchanged
+    /// otherwise, e.g. to log the packets only when a log group is configured:
+    ///
+    /// ```
+    /// # use rustables::{Chain, ProtocolFamily, Rule, Table};
+    /// # use rustables::expr::Log;
+    /// # let table = Table::new(ProtocolFamily::Inet).with_name("filter");
+    /// # let chain = Chain::new(&table).with_name("input");
+    /// let log_group: Option<u16> = Some(1);
+    /// let rule = Rule::new(&chain)?
+    ///     .maybe(log_group, |rule, group| {
+    ///         rule.with_expr(Log::default().with_group(group))
+    ///     })
+    ///     .drop();
+    /// # Ok::<(), rustables::error::BuilderError>(())
+    /// ```
+    pub fn maybe<T>(self, value: Option<T>, f: impl FnOnce(Self, T) -> Self) -> Self {
+        match value {
+            Some(value) => f(self, value),
+            None => self,
+        }
+    }
+
+    /// Returns the number of expressions in this rule.
+    pub fn expr_count(&self) -> usize {
+        self.get_expressions().map_or(0, |exprs| exprs.len())
+    }
+
+    /// Returns the expression at position `index` in this rule. The expressions of a rule
+    /// retrieved from the kernel are in the order they are evaluated, which is the order they
+    /// were added in, so the expressions of two rules can be compared one by one.
+    pub fn get_expr(&self, index: usize) -> Option<&RawExpression> {
+        self.get_expressions()?.get(index)
+    }
+
+    /// Compares the expressions of this rule with those of `other`, and returns the first
+    /// difference, or `None` if they hold the same expressions in the same order. The other
+    /// attributes, such as the handle or the position, are ignored, so that a rule built locally
+    /// can be compared with a rule retrieved from the kernel.
+    pub fn diff(&self, other: &Rule) -> Option<RuleDiff> {
+        let (count, other_count) = (self.expr_count(), other.expr_count());
+        for index in 0..count.min(other_count) {
+            let (left, right) = (self.get_expr(index)?, other.get_expr(index)?);
+            if left != right {
+                return Some(RuleDiff::Expression {
+                    index,
+                    left: format!("{:?}", left),
+                    right: format!("{:?}", right),
+                });
+            }
+        }
+        if count != other_count {
+            return Some(RuleDiff::ExpressionCount(count, other_count));
+        }
+        None
+    }
+
+    /// Returns whether this rule and `other` hold the same expressions, see [`Rule::diff`].
+    pub fn deep_eq(&self, other: &Rule) -> bool {
+        self.diff(other).is_none()
+    }
+
+    /// Checks that the NAT expressions of this rule can work in `chain`, which the rule should
+    /// belong to, as the kernel only performs NAT in the base chains of type `nat`: the
+    /// destination NAT must happen in the prerouting or output hooks, the source NAT in the
+    /// postrouting or input hooks, and the masquerading in the postrouting hook. Returns
+    /// `BuilderError::InvalidChainForNat` otherwise, and `BuilderError::MissingNatType` for a
+    /// `Nat` expression without a type.
+    ///
+    /// A regular chain has neither a type nor a hook, so the rules of such a chain always pass
+    /// the check, and the kernel validates them against the base chains jumping to it. The NAT
+    /// helpers, such as [`Rule::dnat`], already run the check against the chain given to
+    /// [`Rule::new`].
+    pub fn check_nat_chain(&self, chain: &Chain) -> Result<(), BuilderError> {
+        let hook = match chain.get_hook().and_then(|hook| hook.get_class()) {
+            Some(hook) => *hook,
+            None => return Ok(()),
+        };
+        for expr in self.get_expressions().iter().flat_map(|exprs| exprs.iter()) {
+            if let Some(expr) = expr.get_data() {
+                check_nat_hook(expr, chain.get_type(), hook, chain.get_family())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the NAT expression `expr` against the base chain this rule was created in with
+    /// [`Rule::new`], as [`Rule::check_nat_chain`] does. The rules of a regular chain, or
+    /// created with [`Rule::in_chain`], always pass the check.
+    pub(crate) fn check_nat_expr(&self, expr: &ExpressionVariant) -> Result<(), BuilderError> {
+        match &self.base_chain {
+            Some((chain_type, hook)) => {
+                check_nat_hook(expr, chain_type.as_ref(), *hook, self.family)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the source (resp. destination) addresses and networks matched by this rule, such
+    /// as the ones added by [`Rule::saddr`] and [`Rule::snetwork`], reconstructed from the
+    /// loads of the address fields of the IPv4 and IPv6 headers followed by an optional mask and
+    /// an equality comparison. Single addresses are returned as networks with a full prefix.
+    pub fn matched_networks(&self, source: bool) -> Vec<IpNetwork> {
+        let fields = if source {
+            [
+                NetworkHeaderField::IPv4(IPv4HeaderField::Saddr),
+                NetworkHeaderField::IPv6(IPv6HeaderField::Saddr),
+            ]
+        } else {
+            [
+                NetworkHeaderField::IPv4(IPv4HeaderField::Daddr),
+                NetworkHeaderField::IPv6(IPv6HeaderField::Daddr),
+            ]
+        };
+        let fields = fields.map(|field| HighLevelPayload::Network(field).build());
+
+        let mut networks = Vec::new();
+        // whether the previous expression loaded an address, and the mask applied to it
+        let mut loaded: Option<Option<Vec<u8>>> = None;
+        for expr in self.get_expressions().iter().flat_map(|exprs| exprs.iter()) {
+            loaded = match (expr.get_data(), loaded) {
+                (Some(ExpressionVariant::Payload(payload)), _) => fields
+                    .iter()
+                    .any(|field| {
+                        (field.get_base(), field.get_offset(), field.get_len())
+                            == (payload.get_base(), payload.get_offset(), payload.get_len())
+                    })
+                    .then_some(None),
+                (Some(ExpressionVariant::Bitwise(bitwise)), Some(None)) => bitwise
+                    .get_mask()
+                    .and_then(|mask| mask.get_value())
+                    .map(|mask| Some(mask.clone())),
+                (Some(ExpressionVariant::Cmp(cmp)), Some(mask)) => {
+                    let addr = cmp
+                        .get_data()
+                        .and_then(|data| data.get_value())
+                        .filter(|_| cmp.get_op() == Some(&CmpOp::Eq))
+                        .and_then(|data| vec_to_ip(data));
+                    if let Some(addr) = addr {
+                        let prefix = match mask {
+                            Some(mask) => mask.iter().map(|b| b.count_ones()).sum::<u32>() as u8,
+                            None => ip_to_vec(addr).len() as u8 * 8,
+                        };
+                        networks.extend(IpNetwork::new(addr, prefix).ok());
+                    }
+                    None
+                }
+                _ => None,
+            };
+        }
+        networks
+    }
+
+    /// Checks, on a best-effort basis, that the comparisons in this rule use as many bytes as were
+    /// loaded in the register they read, as a rule comparing 4 bytes against a 2-byte field
+    /// silently never matches. Returns `BuilderError::RegisterLengthMismatch` otherwise.
+    pub fn check_register_lengths(&self) -> Result<(), BuilderError> {
+        // the number of bytes loaded in each register, and whether the comparisons may only
+        // look at a prefix of them (as is the case for interface names)
+        let mut lengths: HashMap<Register, (u32, bool)> = HashMap::new();
+        let check =
+            |lengths: &HashMap<Register, (u32, bool)>, reg: &Register, len: u32| match lengths
+                .get(reg)
+            {
+                Some(&(loaded, prefix)) if len > loaded || (len < loaded && !prefix) => {
+                    Err(BuilderError::RegisterLengthMismatch)
+                }
+                _ => Ok(()),
+            };
+
+        for expr in self.get_expressions().iter().flat_map(|exprs| exprs.iter()) {
+            match expr.get_data() {
+                Some(ExpressionVariant::Payload(payload)) => {
+                    if let (Some(dreg), Some(len)) = (payload.get_dreg(), payload.get_len()) {
+                        lengths.insert(*dreg, (*len, false));
+                    }
+                }
+                Some(ExpressionVariant::Meta(meta)) => {
+                    if let (Some(dreg), Some(key)) = (meta.get_dreg(), meta.get_key()) {
+                        let prefix = matches!(
+                            key,
+                            MetaType::IifName
+                                | MetaType::OifName
+                                | MetaType::BriIifName
+                                | MetaType::BriOifName
+                        );
+                        lengths.insert(*dreg, (key.len(), prefix));
+                    }
+                }
+                Some(ExpressionVariant::Conntrack(ct)) => {
+                    if let Some(dreg) = ct.get_dreg() {
+                        match ct.get_key().and_then(|key| key.len()) {
+                            Some(len) => {
+                                lengths.insert(*dreg, (len, false));
+                            }
+                            None => {
+                                lengths.remove(dreg);
+                            }
+                        }
+                    }
+                }
+                Some(ExpressionVariant::ExtHdr(exthdr)) => {
+                    if let (Some(dreg), Some(len)) = (exthdr.get_dreg(), exthdr.get_len()) {
+                        lengths.insert(*dreg, (*len, false));
+                    }
+                }
+                Some(ExpressionVariant::Fib(fib)) => {
+                    if let (Some(dreg), Some(result)) = (fib.get_dreg(), fib.get_result()) {
+                        let prefix = *result == FibResult::OifName;
+                        lengths.insert(*dreg, (result.len(), prefix));
+                    }
+                }
+                Some(ExpressionVariant::Socket(socket)) => {
+                    if let (Some(dreg), Some(key)) = (socket.get_dreg(), socket.get_key()) {
+                        lengths.insert(*dreg, (key.len(), false));
+                    }
+                }
+                Some(ExpressionVariant::Immediate(immediate)) => {
+                    let value = immediate.get_data().and_then(|data| data.get_value());
+                    if let (Some(dreg), Some(value)) = (immediate.get_dreg(), value) {
+                        lengths.insert(*dreg, (value.len() as u32, false));
+                    }
+                }
+                Some(ExpressionVariant::Bitwise(bitwise)) => {
+                    if let (Some(sreg), Some(len)) = (bitwise.get_sreg(), bitwise.get_len()) {
+                        check(&lengths, sreg, *len)?;
+                    }
+                    if let (Some(dreg), Some(len)) = (bitwise.get_dreg(), bitwise.get_len()) {
+                        lengths.insert(*dreg, (*len, false));
+                    }
+                }
+                Some(ExpressionVariant::Byteorder(byteorder)) => {
+                    if let (Some(sreg), Some(len)) = (byteorder.get_sreg(), byteorder.get_len()) {
+                        check(&lengths, sreg, *len)?;
+                    }
+                    if let (Some(dreg), Some(len)) = (byteorder.get_dreg(), byteorder.get_len()) {
+                        lengths.insert(*dreg, (*len, false));
+                    }
+                }
+                Some(ExpressionVariant::Range(range)) => {
+                    let value = range.get_from_data().and_then(|data| data.get_value());
+                    if let (Some(sreg), Some(value)) = (range.get_sreg(), value) {
+                        check(&lengths, sreg, value.len() as u32)?;
+                    }
+                }
+                Some(ExpressionVariant::Cmp(cmp)) => {
+                    let value = cmp.get_data().and_then(|data| data.get_value());
+                    if let (Some(sreg), Some(value)) = (cmp.get_sreg(), value) {
+                        check(&lengths, sreg, value.len() as u32)?;
+                    }
+                }
+                Some(
+                    ExpressionVariant::ConnLimit(_)
+                    | ExpressionVariant::Counter(_)
+                    | ExpressionVariant::Last(_)
+                    | ExpressionVariant::Log(_)
+                    | ExpressionVariant::Masquerade(_)
+                    | ExpressionVariant::Nat(_)
+                    | ExpressionVariant::ObjRef(_)
+                    | ExpressionVariant::Reject(_),
+                ) => {}
+                // the other expressions may write to registers in ways we don't track
+                _ => lengths.clear(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Annotates the rule with `comment`, which is stored in its userdata in the same format as
+    /// `nft add rule ... comment "..."`, so `nft list ruleset` displays it. This replaces any
+    /// other userdata of the rule. Returns `BuilderError::InvalidComment` if the comment is more
+    /// than 127 bytes long or contains a null byte.
+    pub fn set_comment(&mut self, comment: &str) -> Result<(), BuilderError> {
+        self.set_userdata(encode_comment_userdata(comment)?);
+        Ok(())
+    }
+
+    pub fn with_comment(mut self, comment: &str) -> Result<Self, BuilderError> {
+        self.set_comment(comment)?;
+        Ok(self)
+    }
+
+    /// Returns the comment of the rule, if its userdata holds one.
+    pub fn get_comment(&self) -> Option<String> {
+        decode_comment_userdata(self.get_userdata()?)
+    }
+
     /// Appends this rule to `batch`
     pub fn add_to_batch(self, batch: &mut Batch) -> Self {
         batch.add(&self, crate::MsgType::Add);
@@ -77,6 +410,37 @@ impl Rule {
     }
 }
 
+/// Checks that a base chain of type `chain_type`, registered on the hook number `hook` of
+/// `family`, can perform the NAT expression `expr`, see [`Rule::check_nat_chain`]. The other
+/// expressions work in any chain.
+fn check_nat_hook(
+    expr: &ExpressionVariant,
+    chain_type: Option<&ChainType>,
+    hook: u32,
+    family: ProtocolFamily,
+) -> Result<(), BuilderError> {
+    let allowed = |classes: &[HookClass]| {
+        chain_type == Some(&ChainType::Nat)
+            && classes
+                .iter()
+                .any(|class| class.hook_num(family) == Some(hook))
+    };
+    let valid = match expr {
+        ExpressionVariant::Nat(nat) => match nat.get_nat_type() {
+            Some(NatType::DNat) => allowed(&[HookClass::PreRouting, HookClass::Out]),
+            Some(NatType::SNat) => allowed(&[HookClass::PostRouting, HookClass::In]),
+            None => return Err(BuilderError::MissingNatType),
+        },
+        ExpressionVariant::Masquerade(_) => allowed(&[HookClass::PostRouting]),
+        _ => true,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(BuilderError::InvalidChainForNat)
+    }
+}
+
 impl NfNetlinkObject for Rule {
     const MSG_TYPE_ADD: u32 = NFT_MSG_NEWRULE;
     const MSG_TYPE_DEL: u32 = NFT_MSG_DELRULE;
@@ -95,10 +459,89 @@ impl NfNetlinkObject for Rule {
     }
 }
 
+/// The first difference between the expressions of two rules, as returned by [`Rule::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleDiff {
+    /// The expressions at position `index` differ. `left` and `right` describe the expressions
+    /// of the rule `diff` was called on and of the other rule.
+    Expression {
+        index: usize,
+        left: String,
+        right: String,
+    },
+    /// One of the rules has more expressions than the other, of which the expressions are the
+    /// first ones of the longer rule. Holds the numbers of expressions of the two rules.
+    ExpressionCount(usize, usize),
+}
+
+impl Display for RuleDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleDiff::Expression { index, left, right } => {
+                write!(f, "expression {} differs: {} != {}", index, left, right)
+            }
+            RuleDiff::ExpressionCount(left, right) => {
+                write!(f, "{} expressions != {} expressions", left, right)
+            }
+        }
+    }
+}
+
+/// Creates rules in a single chain, without having to pass the chain to each of them.
+#[derive(Clone, Debug)]
+pub struct RuleGroup {
+    template: Rule,
+}
+
+impl RuleGroup {
+    /// Creates a new group of rules in the given [`Chain`].
+    ///
+    /// [`Chain`]: struct.Chain.html
+    pub fn new(chain: &Chain) -> Result<Self, BuilderError> {
+        Ok(RuleGroup {
+            template: Rule::new(chain)?,
+        })
+    }
+
+    /// Returns a new, empty rule in the chain of this group.
+    pub fn rule(&self) -> Rule {
+        self.template.clone()
+    }
+
+    /// Appends all the `rules` to `batch`, in order.
+    pub fn add_all(batch: &mut Batch, rules: impl IntoIterator<Item = Rule>) {
+        for rule in rules {
+            batch.add(&rule, crate::MsgType::Add);
+        }
+    }
+}
+
+/// The message type dumping rules and resetting their stateful expressions, such as counters
+/// and quotas, which the kernel headers the bindings are generated from do not define yet. It
+/// requires Linux 6.2 or later.
+const NFT_MSG_GETRULE_RESET: u16 = 25;
+
 pub fn list_rules_for_chain(chain: &Chain) -> Result<Vec<Rule>, QueryError> {
+    list_rules_for_chain_with_reset(chain, false)
+}
+
+/// Lists the rules of `chain` like [`list_rules_for_chain`], and if `reset` is set, atomically
+/// resets the stateful expressions of the rules, such as their counters, while dumping them.
+/// The returned rules then hold the values from right before the reset, so that per-rule
+/// counters can be used for accounting without losing the packets counted between a read and
+/// a reset. Resetting requires Linux 6.2 or later.
+pub fn list_rules_for_chain_with_reset(
+    chain: &Chain,
+    reset: bool,
+) -> Result<Vec<Rule>, QueryError> {
+    let msg_type = if reset {
+        NFT_MSG_GETRULE_RESET
+    } else {
+        libc::NFT_MSG_GETRULE as u16
+    };
     let mut result = Vec::new();
     list_objects_with_data(
-        libc::NFT_MSG_GETRULE as u16,
+        msg_type,
         &|rule: Rule, rules: &mut Vec<Rule>| {
             rules.push(rule);
             Ok(())
@@ -109,3 +552,21 @@ pub fn list_rules_for_chain(chain: &Chain) -> Result<Vec<Rule>, QueryError> {
     )?;
     Ok(result)
 }
+
+/// Returns the number of rules in `chain`. The rules are dumped like in
+/// [`list_rules_for_chain`], but only the messages are counted, without parsing them into
+/// [`Rule`]s, which is cheaper on chains holding many rules.
+pub fn count_rules_for_chain(chain: &Chain) -> Result<usize, QueryError> {
+    count_objects(libc::NFT_MSG_GETRULE as u16, &Rule::new(chain)?)
+}
+
+/// Retrieves the rule with the given `handle` in `chain`, without dumping the whole chain.
+/// Returns `None` if there is no such rule.
+pub fn get_rule(chain: &Chain, handle: u64) -> Result<Option<Rule>, QueryError> {
+    let filter = Rule::new(chain)?.with_handle(handle);
+    match get_object(libc::NFT_MSG_GETRULE as u16, &filter) {
+        Ok(rule) => Ok(Some(rule)),
+        Err(QueryError::NetlinkError(e, _)) if e.error == libc::ENOENT => Ok(None),
+        Err(e) => Err(e),
+    }
+}
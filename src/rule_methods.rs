@@ -1,19 +1,32 @@
 use std::ffi::CString;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
 
 use ipnetwork::IpNetwork;
 
 use crate::data_type::ip_to_vec;
 use crate::error::BuilderError;
-use crate::expr::ct::{ConnTrackState, Conntrack, ConntrackKey};
+use crate::expr::ct::{
+    ConnTrackState, ConnTrackStatus, Conntrack, ConntrackDirection, ConntrackKey,
+};
 use crate::expr::{
-    Bitwise, Byteorder, ByteorderOp, Cmp, CmpOp, ExtHdr, ExtHdrOp, HighLevelPayload,
-    IPv4HeaderField, IPv6HeaderField, Immediate, Masquerade, Meta, MetaType, Nat, NatType,
-    NetworkHeaderField, Payload, Register, Rt, RtKey, TCPHeaderField, TransportHeaderField,
-    UDPHeaderField, VerdictKind,
+    AddrType, Bitwise, Byteorder, ByteorderOp, Cmp, CmpOp, ConnLimit, Counter, DCCPHeaderField,
+    ExpressionVariant, ExtHdr, ExtHdrOp, Fib, FibResult, FlowOffload, HeaderField,
+    HighLevelPayload, IPv4HeaderField, IPv6HeaderField, Immediate, Last, Log, Lookup, Masquerade,
+    Meta, MetaType, Nat, NatType, NetworkHeaderField, ObjRef, Payload, PayloadBase, Range, RangeOp,
+    Register, Reject, RejectCode, Rt, RtKey, Socket, SocketKey, TCPHeaderField,
+    TransportHeaderField, UDPHeaderField, VerdictKind,
+};
+use crate::nlmsg::NfNetlinkObject;
+use crate::set::SetBuilder;
+use crate::sys::{
+    NFTA_FIB_F_DADDR, NFT_DATA_VERDICT, NFT_EXTHDR_F_PRESENT, NFT_PAYLOAD_TRANSPORT_HEADER,
+    NF_NAT_RANGE_PROTO_SPECIFIED,
 };
-use crate::sys::NFT_PAYLOAD_TRANSPORT_HEADER;
-use crate::{ProtocolFamily, Rule};
+use crate::{Batch, MsgType, ObjectType, ProtocolFamily, Rule, Set, Table};
+
+/// The type of the router alert option of the IPv6 hop-by-hop options header (RFC 2711).
+const IPV6_TLV_ROUTERALERT: u8 = 5;
 
 /// Simple protocol description. Note that it does not implement other layer 4 protocols as
 /// IGMP et al. See [`Rule::igmp`] for a workaround.
@@ -21,10 +34,104 @@ use crate::{ProtocolFamily, Rule};
 pub enum Protocol {
     TCP,
     UDP,
+    DCCP,
+}
+
+/// The Explicit Congestion Notification codepoint of an IP packet (RFC 3168).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum EcnCodepoint {
+    /// Not ECN-Capable Transport.
+    NotEct = 0b00,
+    /// ECN Capable Transport, ECT(1).
+    Ect1 = 0b01,
+    /// ECN Capable Transport, ECT(0).
+    Ect0 = 0b10,
+    /// Congestion Experienced.
+    Ce = 0b11,
+}
+
+/// The protocol of the payload of an Ethernet frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u16)]
+pub enum EtherType {
+    Ipv4 = libc::ETH_P_IP as u16,
+    Arp = libc::ETH_P_ARP as u16,
+    /// An 802.1Q VLAN tagged frame.
+    Vlan = libc::ETH_P_8021Q as u16,
+    Ipv6 = libc::ETH_P_IPV6 as u16,
+    /// The discovery stage of PPPoE.
+    PppoeDiscovery = libc::ETH_P_PPP_DISC as u16,
+    /// A PPPoE session.
+    PppoeSession = libc::ETH_P_PPP_SES as u16,
+    MplsUnicast = libc::ETH_P_MPLS_UC as u16,
+    MplsMulticast = libc::ETH_P_MPLS_MC as u16,
+}
+
+/// A type of ICMP message that exists in both ICMP and ICMPv6, under different numbers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IcmpType {
+    EchoRequest,
+    EchoReply,
+    DestinationUnreachable,
+    TimeExceeded,
+    ParameterProblem,
+}
+
+impl IcmpType {
+    /// Returns the number of this type in ICMP.
+    pub fn icmp_type(&self) -> u8 {
+        match self {
+            IcmpType::EchoRequest => 8,
+            IcmpType::EchoReply => 0,
+            IcmpType::DestinationUnreachable => 3,
+            IcmpType::TimeExceeded => 11,
+            IcmpType::ParameterProblem => 12,
+        }
+    }
+
+    /// Returns the number of this type in ICMPv6.
+    pub fn icmpv6_type(&self) -> u8 {
+        match self {
+            IcmpType::EchoRequest => 128,
+            IcmpType::EchoReply => 129,
+            IcmpType::DestinationUnreachable => 1,
+            IcmpType::TimeExceeded => 3,
+            IcmpType::ParameterProblem => 4,
+        }
+    }
+}
+
+/// The type of a packet, as seen by the host it is received on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PktType {
+    /// A packet addressed to this host.
+    Unicast = libc::PACKET_HOST,
+    /// A packet sent to all the hosts on the link.
+    Broadcast = libc::PACKET_BROADCAST,
+    /// A packet sent to a multicast group.
+    Multicast = libc::PACKET_MULTICAST,
+    /// A packet addressed to another host, usually seen in promiscuous mode.
+    Other = libc::PACKET_OTHERHOST,
+}
+
+/// A day of the week, numbered as the kernel does when loading it with
+/// [`MetaType::TimeDay`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Weekday {
+    Sunday = 0,
+    Monday = 1,
+    Tuesday = 2,
+    Wednesday = 3,
+    Thursday = 4,
+    Friday = 5,
+    Saturday = 6,
 }
 
 impl Rule {
-    fn match_port(mut self, port: u16, protocol: Protocol, source: bool) -> Self {
+    fn load_port(mut self, protocol: Protocol, source: bool) -> Self {
         self = self.protocol(protocol);
         self.add_expr(
             HighLevelPayload::Transport(match protocol {
@@ -38,18 +145,34 @@ impl Rule {
                 } else {
                     UDPHeaderField::Dport
                 }),
+                Protocol::DCCP => TransportHeaderField::Dccp(if source {
+                    DCCPHeaderField::Sport
+                } else {
+                    DCCPHeaderField::Dport
+                }),
             })
             .build(),
         );
-        self.add_expr(Cmp::new(CmpOp::Eq, port.to_be_bytes()));
+        self
+    }
+
+    fn match_port(mut self, port: u16, protocol: Protocol, source: bool) -> Self {
+        self = self.load_port(protocol, source);
+        self.add_expr(Cmp::eq_port(port));
+        self
+    }
+
+    fn match_port_range(mut self, start: u16, end: u16, protocol: Protocol, source: bool) -> Self {
+        self = self.load_port(protocol, source);
+        self.add_expr(Cmp::new(CmpOp::Gte, start.to_be_bytes()));
+        self.add_expr(Cmp::new(CmpOp::Lte, end.to_be_bytes()));
         self
     }
 
     pub fn match_ip(mut self, ip: IpAddr, source: bool) -> Self {
-        self.add_expr(Meta::new(MetaType::NfProto));
         match ip {
             IpAddr::V4(addr) => {
-                self.add_expr(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV4 as u8]));
+                self = self.if_ipv4();
                 self.add_expr(
                     HighLevelPayload::Network(NetworkHeaderField::IPv4(if source {
                         IPv4HeaderField::Saddr
@@ -58,10 +181,10 @@ impl Rule {
                     }))
                     .build(),
                 );
-                self.add_expr(Cmp::new(CmpOp::Eq, addr.octets()));
+                self.add_expr(Cmp::eq_ip(addr.into()));
             }
             IpAddr::V6(addr) => {
-                self.add_expr(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8]));
+                self = self.if_ipv6();
                 self.add_expr(
                     HighLevelPayload::Network(NetworkHeaderField::IPv6(if source {
                         IPv6HeaderField::Saddr
@@ -70,17 +193,84 @@ impl Rule {
                     }))
                     .build(),
                 );
-                self.add_expr(Cmp::new(CmpOp::Eq, addr.octets()));
+                self.add_expr(Cmp::eq_ip(addr.into()));
             }
         }
         self
     }
 
-    pub fn match_network(mut self, net: IpNetwork, source: bool) -> Result<Self, BuilderError> {
-        self.add_expr(Meta::new(MetaType::NfProto));
-        match net {
-            IpNetwork::V4(_) => {
-                self.add_expr(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV4 as u8]));
+    /// Matches packets whose header field loaded by `payload` equals `value` once masked with
+    /// `mask`, which is the usual way to match a few bits of a field or the prefix of an address.
+    /// `mask` and `value` must cover the whole field, otherwise
+    /// `BuilderError::IncompatibleLength` is returned.
+    pub fn match_masked(
+        mut self,
+        payload: Payload,
+        mask: &[u8],
+        value: &[u8],
+    ) -> Result<Self, BuilderError> {
+        if matches!(payload.get_len(), Some(len) if *len as usize != mask.len())
+            || mask.len() != value.len()
+        {
+            return Err(BuilderError::IncompatibleLength);
+        }
+        self.add_expr(payload);
+        self.add_expr(Bitwise::and(mask));
+        self.add_expr(Cmp::new(CmpOp::Eq, value));
+        Ok(self)
+    }
+
+    pub fn match_network(self, net: IpNetwork, source: bool) -> Result<Self, BuilderError> {
+        let (rule, field) = match net {
+            IpNetwork::V4(_) => (
+                self.if_ipv4(),
+                NetworkHeaderField::IPv4(if source {
+                    IPv4HeaderField::Saddr
+                } else {
+                    IPv4HeaderField::Daddr
+                }),
+            ),
+            IpNetwork::V6(_) => (
+                self.if_ipv6(),
+                NetworkHeaderField::IPv6(if source {
+                    IPv6HeaderField::Saddr
+                } else {
+                    IPv6HeaderField::Daddr
+                }),
+            ),
+        };
+        rule.match_masked(
+            HighLevelPayload::Network(field).build(),
+            &ip_to_vec(net.mask()),
+            &ip_to_vec(net.network()),
+        )
+    }
+
+    fn match_networks(
+        mut self,
+        nets: &[IpNetwork],
+        source: bool,
+        batch: &mut Batch,
+    ) -> Result<Self, BuilderError> {
+        let table = Table::new(self.get_family()).with_name(
+            self.get_table()
+                .ok_or(BuilderError::MissingChainInformationError)?,
+        );
+        let (set, elements) = match nets.first() {
+            None => return Err(BuilderError::EmptySet),
+            Some(IpNetwork::V4(_)) => {
+                let mut set_builder = SetBuilder::<Ipv4Addr>::anonymous(&table)?.with_intervals();
+                let mut ranges = Vec::with_capacity(nets.len());
+                for net in nets {
+                    match net {
+                        IpNetwork::V4(net) => ranges.push((net.network(), net.broadcast())),
+                        IpNetwork::V6(_) => return Err(BuilderError::MixedAddressFamilies),
+                    }
+                }
+                for (start, end) in merge_ranges(ranges) {
+                    set_builder.add_range(&start, &end)?;
+                }
+                self = self.if_ipv4();
                 self.add_expr(
                     HighLevelPayload::Network(NetworkHeaderField::IPv4(if source {
                         IPv4HeaderField::Saddr
@@ -89,10 +279,21 @@ impl Rule {
                     }))
                     .build(),
                 );
-                self.add_expr(Bitwise::new(ip_to_vec(net.mask()), 0u32.to_be_bytes())?);
+                set_builder.finish()
             }
-            IpNetwork::V6(_) => {
-                self.add_expr(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8]));
+            Some(IpNetwork::V6(_)) => {
+                let mut set_builder = SetBuilder::<Ipv6Addr>::anonymous(&table)?.with_intervals();
+                let mut ranges = Vec::with_capacity(nets.len());
+                for net in nets {
+                    match net {
+                        IpNetwork::V6(net) => ranges.push((net.network(), net.broadcast())),
+                        IpNetwork::V4(_) => return Err(BuilderError::MixedAddressFamilies),
+                    }
+                }
+                for (start, end) in merge_ranges(ranges) {
+                    set_builder.add_range(&start, &end)?;
+                }
+                self = self.if_ipv6();
                 self.add_expr(
                     HighLevelPayload::Network(NetworkHeaderField::IPv6(if source {
                         IPv6HeaderField::Saddr
@@ -101,15 +302,165 @@ impl Rule {
                     }))
                     .build(),
                 );
-                self.add_expr(Bitwise::new(ip_to_vec(net.mask()), 0u128.to_be_bytes())?);
+                set_builder.finish()
+            }
+        };
+
+        self.add_expr(Lookup::new(&set)?);
+        batch.add(&set, MsgType::Add);
+        batch.add(&elements, MsgType::Add);
+        Ok(self)
+    }
+
+    fn match_ecn(mut self, codepoint: EcnCodepoint, ipv6: bool) -> Result<Self, BuilderError> {
+        // the ECN bits are the two lowest bits of the IPv4 ToS field, which is the second byte of
+        // the header. In IPv6, the traffic class straddles the first two bytes of the header, so
+        // the ECN bits end up in the middle of the second byte.
+        let shift = if ipv6 {
+            self = self.if_ipv6();
+            4
+        } else {
+            self = self.if_ipv4();
+            0
+        };
+        self.match_masked(
+            Payload::raw(PayloadBase::Network, 1, 1, Register::Reg1)?,
+            &[0b11u8 << shift],
+            &[(codepoint as u8) << shift],
+        )
+    }
+
+    fn match_iface_name(mut self, key: MetaType, name: &str) -> Result<Self, BuilderError> {
+        if name.len() >= libc::IFNAMSIZ {
+            return Err(BuilderError::InterfaceNameTooLong);
+        }
+        let mut name_vec = name.as_bytes().to_vec();
+        // null terminator
+        name_vec.push(0u8);
+
+        self.add_expr(Meta::new(key));
+        self.add_expr(Cmp::new(CmpOp::Eq, name_vec));
+        Ok(self)
+    }
+
+    fn match_ct_counter(mut self, key: ConntrackKey, op: CmpOp, value: u64) -> Self {
+        self.add_expr(Conntrack::new(key));
+        // the counter is loaded in host byte order, but the ordering comparisons compare bytes
+        self.add_expr(
+            Byteorder::default()
+                .with_sreg(Register::Reg1)
+                .with_dreg(Register::Reg1)
+                .with_op(ByteorderOp::HtoN)
+                .with_len(8u32)
+                .with_siz(8u32),
+        );
+        self.add_expr(Cmp::new(op, value.to_be_bytes()));
+        self
+    }
+
+    fn match_meta_range(mut self, key: MetaType, op: RangeOp, start: u32, end: u32) -> Self {
+        self.add_expr(Meta::new(key));
+        // the meta key is loaded in host byte order, but the range compares bytes
+        self.add_expr(
+            Byteorder::default()
+                .with_sreg(Register::Reg1)
+                .with_dreg(Register::Reg1)
+                .with_op(ByteorderOp::HtoN)
+                .with_len(4u32)
+                .with_siz(4u32),
+        );
+        self.add_expr(Range::new(op, start.to_be_bytes(), end.to_be_bytes()));
+        self
+    }
+
+    fn nat(
+        mut self,
+        nat_type: NatType,
+        addr: IpAddr,
+        ports: Option<(u16, u16)>,
+    ) -> Result<Self, BuilderError> {
+        let mut nat = Nat::default()
+            .with_nat_type(nat_type)
+            .with_family(match addr {
+                IpAddr::V4(_) => ProtocolFamily::Ipv4,
+                IpAddr::V6(_) => ProtocolFamily::Ipv6,
+            })
+            .with_ip_register(Register::Reg1);
+        self.check_nat_expr(&ExpressionVariant::from(nat.clone()))?;
+        self.add_expr(Immediate::new_ip(addr, Register::Reg1));
+        if let Some((port_min, port_max)) = ports {
+            self.add_expr(Immediate::new_data(
+                port_min.to_be_bytes().to_vec(),
+                Register::Reg2,
+            ));
+            nat.set_port_register(Register::Reg2);
+            if port_max != port_min {
+                self.add_expr(Immediate::new_data(
+                    port_max.to_be_bytes().to_vec(),
+                    Register::Reg3,
+                ));
+                nat.set_port_register_max(Register::Reg3);
             }
+            nat.set_flags(NF_NAT_RANGE_PROTO_SPECIFIED);
         }
-        self.add_expr(Cmp::new(CmpOp::Eq, ip_to_vec(net.network())));
+        self.add_expr(nat);
         Ok(self)
     }
 }
 
 impl Rule {
+    /// Matches IPv4 packets, which is mostly useful in inet tables. In bridge and netdev
+    /// tables, whose packets have no netfilter protocol, this matches their ethertype instead.
+    pub fn if_ipv4(mut self) -> Self {
+        if self.has_ethertype_guard() {
+            return self.ether_type(EtherType::Ipv4);
+        }
+        self.add_expr(Meta::new(MetaType::NfProto));
+        self.add_expr(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV4 as u8]));
+        self
+    }
+    /// Matches IPv6 packets, which is mostly useful in inet tables. As with [`Rule::if_ipv4`],
+    /// this matches the ethertype in bridge and netdev tables.
+    pub fn if_ipv6(mut self) -> Self {
+        if self.has_ethertype_guard() {
+            return self.ether_type(EtherType::Ipv6);
+        }
+        self.add_expr(Meta::new(MetaType::NfProto));
+        self.add_expr(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8]));
+        self
+    }
+    fn has_ethertype_guard(&self) -> bool {
+        matches!(
+            self.get_family(),
+            ProtocolFamily::Bridge | ProtocolFamily::NetDev
+        )
+    }
+    /// Matches packets carrying the given `ether_type` protocol, like `meta protocol` in
+    /// nftables. In bridge and netdev tables, this must guard the matches on the network
+    /// header.
+    pub fn ether_type(mut self, ether_type: EtherType) -> Self {
+        self.add_expr(Meta::new(MetaType::Protocol));
+        self.add_expr(Cmp::new(CmpOp::Eq, (ether_type as u16).to_be_bytes()));
+        self
+    }
+    /// Matches packets of the layer 3 `protocol`, like `meta protocol` in nftables. Unlike
+    /// [`Rule::if_ipv4`] and [`Rule::if_ipv6`], this always matches the ethertype rather than
+    /// the netfilter protocol, which isn't set yet in e.g. netdev ingress chains.
+    pub fn l3_protocol(self, protocol: EtherType) -> Self {
+        self.ether_type(protocol)
+    }
+    /// Matches the frames carrying the protocol of the raw `ethertype`, e.g. `0x88cc` for LLDP,
+    /// for the protocols [`EtherType`] doesn't name. As the other protocols only show up before
+    /// the network layer, this is restricted to the bridge and netdev tables: in the other tables,
+    /// `BuilderError::MissingLinkLayerHeader` is returned.
+    pub fn ethertype(mut self, ethertype: u16) -> Result<Self, BuilderError> {
+        if !self.has_ethertype_guard() {
+            return Err(BuilderError::MissingLinkLayerHeader);
+        }
+        self.add_expr(Meta::new(MetaType::Protocol));
+        self.add_expr(Cmp::new(CmpOp::Eq, ethertype.to_be_bytes()));
+        Ok(self)
+    }
     /// Matches ICMP packets.
     pub fn icmp(mut self) -> Self {
         self.add_expr(Meta::new(MetaType::L4Proto));
@@ -122,6 +473,45 @@ impl Rule {
         self.add_expr(Cmp::new(CmpOp::Eq, [libc::IPPROTO_ICMPV6 as u8]));
         self
     }
+    /// Matches the ICMP and ICMPv6 messages of any of the given `types`, in a single rule, like
+    /// `meta l4proto . icmp type { icmp . echo-request, ipv6-icmp . echo-request }` in nftables,
+    /// e.g. to accept the pings of both families in an inet table. The pairs of protocol and
+    /// type, whose numbers differ between ICMP and ICMPv6, are stored in an anonymous set, which
+    /// is added to `batch`: the rule must be added to that same batch afterwards. Returns
+    /// `BuilderError::EmptySet` if `types` is empty.
+    pub fn icmp_types_any_family(
+        mut self,
+        types: &[IcmpType],
+        batch: &mut Batch,
+    ) -> Result<Self, BuilderError> {
+        if types.is_empty() {
+            return Err(BuilderError::EmptySet);
+        }
+        let table = Table::new(self.get_family()).with_name(
+            self.get_table()
+                .ok_or(BuilderError::MissingChainInformationError)?,
+        );
+        let mut set_builder = SetBuilder::<([u8; 1], [u8; 1])>::anonymous(&table)?;
+        for icmp_type in types {
+            set_builder.add(&([libc::IPPROTO_ICMP as u8], [icmp_type.icmp_type()]));
+            set_builder.add(&([libc::IPPROTO_ICMPV6 as u8], [icmp_type.icmpv6_type()]));
+        }
+        let (set, elements) = set_builder.finish();
+
+        // both types are the first byte of the ICMP header, and each field of the concatenation
+        // is loaded in its own 4 bytes register
+        self.add_expr(Meta::new(MetaType::L4Proto).with_dreg(Register::Reg32_00));
+        self.add_expr(Payload::raw(
+            PayloadBase::Transport,
+            0,
+            1,
+            Register::Reg32_01,
+        )?);
+        self.add_expr(Lookup::new(&set)?.with_sreg(Register::Reg32_00));
+        batch.add(&set, MsgType::Add);
+        batch.add(&elements, MsgType::Add);
+        Ok(self)
+    }
     /// Matches IGMP packets.
     pub fn igmp(mut self) -> Self {
         self.add_expr(Meta::new(MetaType::L4Proto));
@@ -130,14 +520,129 @@ impl Rule {
     }
     /// Matches 6in4 packets.
     pub fn ip6in4(mut self) -> Self {
-        self.add_expr(Meta::new(MetaType::NfProto));
-        self.add_expr(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV4 as u8]));
+        self = self.if_ipv4();
         self.add_expr(
             HighLevelPayload::Network(NetworkHeaderField::IPv4(IPv4HeaderField::Protocol)).build(),
         );
         self.add_expr(Cmp::new(CmpOp::Eq, [41 as u8]));
         self
     }
+    /// Matches IPv4 packets whose time to live compares to `ttl` according to `op`, like
+    /// `ip ttl < 10` in nftables.
+    pub fn ttl(mut self, op: CmpOp, ttl: u8) -> Self {
+        self = self.if_ipv4();
+        self.add_expr(
+            HighLevelPayload::Network(NetworkHeaderField::IPv4(IPv4HeaderField::Ttl)).build(),
+        );
+        self.add_expr(Cmp::new(op, [ttl]));
+        self
+    }
+    /// Matches IPv6 packets whose hop limit compares to `hop_limit` according to `op`, like
+    /// `ip6 hoplimit < 10` in nftables.
+    pub fn hop_limit(mut self, op: CmpOp, hop_limit: u8) -> Self {
+        self = self.if_ipv6();
+        self.add_expr(
+            HighLevelPayload::Network(NetworkHeaderField::IPv6(IPv6HeaderField::HopLimit)).build(),
+        );
+        self.add_expr(Cmp::new(op, [hop_limit]));
+        self
+    }
+    /// Matches the packets expiring at the next hop, whose TTL (in ip tables) or hop limit (in
+    /// ip6 tables) is 1, such as traceroute probes, e.g. to drop them or rate limit them at the
+    /// edge of a network. As a single rule cannot match both IP versions, this returns
+    /// `BuilderError::AmbiguousIpVersion` in the other tables: inet tables need two rules, built
+    /// with `ttl(CmpOp::Eq, 1)` and `hop_limit(CmpOp::Eq, 1)`.
+    pub fn ttl_expiring(self) -> Result<Self, BuilderError> {
+        match self.get_family() {
+            ProtocolFamily::Ipv4 => Ok(self.ttl(CmpOp::Eq, 1)),
+            ProtocolFamily::Ipv6 => Ok(self.hop_limit(CmpOp::Eq, 1)),
+            _ => Err(BuilderError::AmbiguousIpVersion),
+        }
+    }
+    /// Matches the packets carrying a router alert option, such as the IGMP (in ip tables) and
+    /// MLD (in ip6 tables) control packets, e.g. to handle them in multicast snooping policies
+    /// along with [`Rule::igmp`].
+    ///
+    /// In IPv4, the option may be anywhere among the IP options, which requires Linux 5.3 or
+    /// later. In IPv6, the hop-by-hop options header is expected to start with the router alert
+    /// option, as in the packets sent by hosts implementing MLD. As a single rule cannot match
+    /// both IP versions, this returns `BuilderError::AmbiguousIpVersion` in the other tables.
+    ///
+    /// [`Rule::igmp`]: struct.Rule.html#method.igmp
+    pub fn has_router_alert(mut self) -> Result<Self, BuilderError> {
+        match self.get_family() {
+            ProtocolFamily::Ipv4 => {
+                // with the presence flag, the expression loads a single byte, set to 1 if the
+                // packet carries the option
+                self.add_expr(
+                    ExtHdr::default()
+                        .with_dreg(Register::Reg1)
+                        .with_typ(libc::IPOPT_RA)
+                        .with_offset(0u32)
+                        .with_len(1u32)
+                        .with_flags(NFT_EXTHDR_F_PRESENT)
+                        .with_op(ExtHdrOp::IPv4),
+                );
+                self.add_expr(Cmp::new(CmpOp::Eq, [1u8]));
+            }
+            ProtocolFamily::Ipv6 => {
+                // the type of the first option follows the next header and length fields of the
+                // hop-by-hop options header
+                self.add_expr(
+                    ExtHdr::default()
+                        .with_dreg(Register::Reg1)
+                        .with_typ(libc::IPPROTO_HOPOPTS as u8)
+                        .with_offset(2u32)
+                        .with_len(1u32)
+                        .with_op(ExtHdrOp::IPv6),
+                );
+                self.add_expr(Cmp::new(CmpOp::Eq, [IPV6_TLV_ROUTERALERT]));
+            }
+            _ => return Err(BuilderError::AmbiguousIpVersion),
+        }
+        Ok(self)
+    }
+    /// Matches IPv4 packets with the given ECN `codepoint`.
+    pub fn ecn(self, codepoint: EcnCodepoint) -> Result<Self, BuilderError> {
+        self.match_ecn(codepoint, false)
+    }
+    /// Matches IPv4 packets carrying options, such as router alert or timestamp, i.e. whose
+    /// header is longer than the 20 bytes of the fixed fields.
+    pub fn has_ip_options(mut self) -> Result<Self, BuilderError> {
+        self = self.if_ipv4();
+        // the header length, in 32 bits words, is the low nibble of the first byte
+        self.add_expr(Payload::raw(PayloadBase::Network, 0, 1, Register::Reg1)?);
+        self.add_expr(Bitwise::new([0x0fu8], [0u8])?);
+        self.add_expr(Cmp::new(CmpOp::Gt, [5u8]));
+        Ok(self)
+    }
+    /// Matches IPv6 packets with the given 20 bits flow `label`.
+    pub fn flow_label(self, label: u32) -> Result<Self, BuilderError> {
+        if label > 0xfffff {
+            return Err(BuilderError::InvalidFlowLabel);
+        }
+        // the flow label starts in the middle of the second byte of the header, after the
+        // traffic class
+        self.if_ipv6().match_masked(
+            HighLevelPayload::Network(NetworkHeaderField::IPv6(IPv6HeaderField::FlowLabel)).build(),
+            &[0x0f, 0xff, 0xff],
+            &label.to_be_bytes()[1..],
+        )
+    }
+    /// Matches IPv6 packets carrying the transport `protocol`, like `meta l4proto` in nftables.
+    /// The kernel finds this protocol past the extension headers, such as the hop-by-hop
+    /// options, whereas matching [`IPv6HeaderField::NextHeader`] only reads the header
+    /// following the fixed one and misses the packets carrying extension headers.
+    pub fn ipv6_l4proto(mut self, protocol: u8) -> Self {
+        self = self.if_ipv6();
+        self.add_expr(Meta::new(MetaType::L4Proto));
+        self.add_expr(Cmp::new(CmpOp::Eq, [protocol]));
+        self
+    }
+    /// Matches IPv6 packets with the given ECN `codepoint`.
+    pub fn ecn6(self, codepoint: EcnCodepoint) -> Result<Self, BuilderError> {
+        self.match_ecn(codepoint, true)
+    }
     /// Matches packets from source `port` and `protocol`.
     pub fn sport(self, port: u16, protocol: Protocol) -> Self {
         self.match_port(port, protocol, false)
@@ -146,6 +651,31 @@ impl Rule {
     pub fn dport(self, port: u16, protocol: Protocol) -> Self {
         self.match_port(port, protocol, false)
     }
+    /// Matches packets from a source port in the range `start`..=`end` and `protocol`.
+    pub fn sport_range(self, start: u16, end: u16, protocol: Protocol) -> Self {
+        self.match_port_range(start, end, protocol, true)
+    }
+    /// Matches packets to a destination port in the range `start`..=`end` and `protocol`.
+    pub fn dport_range(self, start: u16, end: u16, protocol: Protocol) -> Self {
+        self.match_port_range(start, end, protocol, false)
+    }
+    /// Matches DNS queries, to UDP port 53. DNS over TCP, used for large responses and zone
+    /// transfers, must be matched separately with `dport(53, Protocol::TCP)`.
+    pub fn dns(self) -> Self {
+        self.dport(53, Protocol::UDP)
+    }
+    /// Matches SSH connections, to TCP port 22.
+    pub fn ssh(self) -> Self {
+        self.dport(22, Protocol::TCP)
+    }
+    /// Matches HTTP connections, to TCP port 80.
+    pub fn http(self) -> Self {
+        self.dport(80, Protocol::TCP)
+    }
+    /// Matches HTTPS connections, to TCP port 443.
+    pub fn https(self) -> Self {
+        self.dport(443, Protocol::TCP)
+    }
     /// Matches packets on `protocol`.
     pub fn protocol(mut self, protocol: Protocol) -> Self {
         self.add_expr(Meta::new(MetaType::L4Proto));
@@ -154,21 +684,300 @@ impl Rule {
             [match protocol {
                 Protocol::TCP => libc::IPPROTO_TCP,
                 Protocol::UDP => libc::IPPROTO_UDP,
+                Protocol::DCCP => libc::IPPROTO_DCCP,
             } as u8],
         ));
         self
     }
+    /// Matches packets while there are at most `count` tracked connections, or, if `over` is
+    /// true, once there are more than `count` of them (`ct count over 100` in nftables), e.g.
+    /// to drop new connections above a threshold:
+    ///
+    /// ```
+    /// # use rustables::{Chain, ProtocolFamily, Rule, Table};
+    /// # let table = Table::new(ProtocolFamily::Inet).with_name("filter");
+    /// # let chain = Chain::new(&table).with_name("input");
+    /// let rule = Rule::new(&chain)
+    ///     .unwrap()
+    ///     .connlimit(100, true)
+    ///     .drop();
+    /// ```
+    ///
+    /// Note that the connections are counted over the whole rule: limiting the connections per
+    /// source address requires a dynamic set keyed on that address.
+    pub fn connlimit(mut self, count: u32, over: bool) -> Self {
+        self.add_expr(ConnLimit::new(count, over));
+        self
+    }
+    /// Records when the rule last matched, which can be read back from the rule retrieved from the
+    /// kernel with `decode_expr::<Last>()`, see [`Last::elapsed`].
+    pub fn last(mut self) -> Self {
+        self.add_expr(Last::default());
+        self
+    }
     /// Matches packets in an already established connection.
-    pub fn established(mut self) -> Result<Self, BuilderError> {
-        let allowed_states = ConnTrackState::ESTABLISHED.bits();
+    pub fn established(self) -> Result<Self, BuilderError> {
+        self.ct_states(ConnTrackState::ESTABLISHED)
+    }
+    /// Matches packets whose connection is in any of the `states`, like
+    /// `ct state established,related` in nftables:
+    ///
+    /// ```
+    /// # use rustables::expr::ConnTrackState;
+    /// # use rustables::{Chain, ProtocolFamily, Rule, Table};
+    /// # let table = Table::new(ProtocolFamily::Inet).with_name("filter");
+    /// # let chain = Chain::new(&table).with_name("input");
+    /// let rule = Rule::new(&chain)
+    ///     .unwrap()
+    ///     .ct_states(ConnTrackState::ESTABLISHED | ConnTrackState::RELATED)
+    ///     .unwrap()
+    ///     .accept();
+    /// ```
+    pub fn ct_states(mut self, states: ConnTrackState) -> Result<Self, BuilderError> {
         self.add_expr(Conntrack::new(ConntrackKey::State));
+        // the state is loaded in host byte order
+        self.add_expr(Bitwise::new(
+            states.bits().to_ne_bytes(),
+            0u32.to_be_bytes(),
+        )?);
+        self.add_expr(Cmp::new(CmpOp::Neq, 0u32.to_be_bytes()));
+        Ok(self)
+    }
+    /// Matches packets whose connection has any of the status bits of `status`, like
+    /// `ct status dnat` in nftables.
+    pub fn ct_status(mut self, status: ConnTrackStatus) -> Result<Self, BuilderError> {
+        self.add_expr(Conntrack::new(ConntrackKey::Status));
+        // the status is loaded in host byte order
         self.add_expr(Bitwise::new(
-            allowed_states.to_le_bytes(),
+            status.bits().to_ne_bytes(),
             0u32.to_be_bytes(),
         )?);
         self.add_expr(Cmp::new(CmpOp::Neq, 0u32.to_be_bytes()));
         Ok(self)
     }
+    /// Matches packets of the connections anticipated by a conntrack helper, like
+    /// `ct status expected` in nftables, e.g. to only accept the data connections announced
+    /// on an FTP control connection rather than all the `RELATED` ones.
+    pub fn ct_expected(self) -> Result<Self, BuilderError> {
+        self.ct_status(ConnTrackStatus::EXPECTED)
+    }
+    /// Matches packets whose connection has the address `ip` in the tuple of the given
+    /// `direction`, as the source address if `source` is true and as the destination address
+    /// otherwise.
+    pub fn ct_addr(mut self, direction: ConntrackDirection, source: bool, ip: IpAddr) -> Self {
+        let key = match (ip, source) {
+            (IpAddr::V4(_), true) => ConntrackKey::SrcIp,
+            (IpAddr::V4(_), false) => ConntrackKey::DstIp,
+            (IpAddr::V6(_), true) => ConntrackKey::SrcIp6,
+            (IpAddr::V6(_), false) => ConntrackKey::DstIp6,
+        };
+        self.add_expr(Conntrack::tuple(key, direction));
+        self.add_expr(Cmp::eq_ip(ip));
+        self
+    }
+    /// Matches packets whose connection was initiated towards `ip`, which is the destination
+    /// address before DNAT rewrote it.
+    pub fn ct_original_daddr(self, ip: IpAddr) -> Self {
+        self.ct_addr(ConntrackDirection::Original, false, ip)
+    }
+    /// Matches packets whose connection was initiated from `ip`, which is the source address
+    /// before SNAT rewrote it.
+    pub fn ct_original_saddr(self, ip: IpAddr) -> Self {
+        self.ct_addr(ConntrackDirection::Original, true, ip)
+    }
+    /// Matches packets whose connection was initiated towards `port`, using `protocol`.
+    pub fn ct_original_dport(mut self, port: u16, protocol: Protocol) -> Self {
+        self = self.protocol(protocol);
+        self.add_expr(Conntrack::tuple(
+            ConntrackKey::ProtoDst,
+            ConntrackDirection::Original,
+        ));
+        self.add_expr(Cmp::eq_port(port));
+        self
+    }
+    /// Matches packets sent by a local socket owned by a user whose uid is in the range
+    /// `start`..=`end`. The originating socket is only known for locally generated packets, so
+    /// this should only be used in the output (and postrouting) chains.
+    pub fn skuid_range(self, start: u32, end: u32) -> Self {
+        self.match_meta_range(MetaType::SkUid, RangeOp::Eq, start, end)
+    }
+    /// Matches packets whose length, in bytes, is in the range `min`..=`max`, both bounds
+    /// included. The length covers the network header and its payload, but not the link layer
+    /// header. For instance, `length_range(9001, u32::MAX)` matches the jumbo frames, like
+    /// `meta length > 9000` in nftables, and `length_range(0, 63)` matches the runts. Returns
+    /// `BuilderError::InvertedRange` if `min` is greater than `max`, as the rule could never
+    /// match.
+    pub fn length_range(self, min: u32, max: u32) -> Result<Self, BuilderError> {
+        if min > max {
+            return Err(BuilderError::InvertedRange);
+        }
+        Ok(self.match_meta_range(MetaType::Len, RangeOp::Eq, min, max))
+    }
+    /// Matches packets sent by a local socket owned by a group whose gid is in the range
+    /// `start`..=`end`. As with [`Rule::skuid_range`], this only works in the output (and
+    /// postrouting) chains.
+    pub fn skgid_range(self, start: u32, end: u32) -> Self {
+        self.match_meta_range(MetaType::SkGid, RangeOp::Eq, start, end)
+    }
+    /// Matches packets whose socket belongs to the cgroup v2 with the given `id`, or to one of its
+    /// descendants. The id of a cgroup is the inode number of its directory in the cgroup
+    /// filesystem, and `level` is its depth in the hierarchy (e.g. 1 for
+    /// "/sys/fs/cgroup/system.slice").
+    ///
+    /// This requires Linux 5.13 or later. As the socket is only known for locally generated
+    /// packets, this should only be used in the output (and postrouting) chains.
+    pub fn cgroup(mut self, level: u32, id: u64) -> Self {
+        self.add_expr(Socket::cgroupv2(level));
+        // the cgroup id is loaded in host byte order
+        self.add_expr(Cmp::new(CmpOp::Eq, id.to_ne_bytes()));
+        self
+    }
+    /// Matches packets whose local socket is transparent (i.e. has the `IP_TRANSPARENT` option),
+    /// like `socket transparent 1` in nftables. In prerouting, this finds the established flows
+    /// of a transparent proxy so that they can be marked and routed locally. The packets without
+    /// a local socket never match.
+    pub fn socket_transparent(mut self) -> Self {
+        self.add_expr(Socket::new(SocketKey::Transparent));
+        self.add_expr(Cmp::new(CmpOp::Eq, [1u8]));
+        self
+    }
+    /// Matches packets whose local socket is bound to a wildcard address, like
+    /// `socket wildcard 1` in nftables.
+    pub fn socket_wildcard(mut self) -> Self {
+        self.add_expr(Socket::new(SocketKey::Wildcard));
+        self.add_expr(Cmp::new(CmpOp::Eq, [1u8]));
+        self
+    }
+    /// Matches packets whose local socket carries the `SO_MARK` `mark`, like
+    /// `socket mark mark` in nftables.
+    pub fn socket_mark(mut self, mark: u32) -> Self {
+        self.add_expr(Socket::new(SocketKey::Mark));
+        // the mark is loaded in host byte order
+        self.add_expr(Cmp::new(CmpOp::Eq, mark.to_ne_bytes()));
+        self
+    }
+    /// Matches packets of the given type, e.g. to drop broadcast traffic.
+    pub fn pkttype(mut self, pkt_type: PktType) -> Self {
+        self.add_expr(Meta::new(MetaType::PktType));
+        self.add_expr(Cmp::new(CmpOp::Eq, [pkt_type as u8]));
+        self
+    }
+    /// Matches packets whose mark is `mark`, like `meta mark mark` in nftables.
+    pub fn mark(mut self, mark: u32) -> Self {
+        self.add_expr(Meta::new(MetaType::Mark));
+        // the mark is loaded in host byte order
+        self.add_expr(Cmp::new(CmpOp::Eq, mark.to_ne_bytes()));
+        self
+    }
+    /// Matches packets whose mark has the bits of `mask` set as in `value`, like
+    /// `meta mark and mask == value` in nftables, for the setups where several subsystems each
+    /// own some of the bits of the mark. The bits of `value` outside of `mask` are ignored.
+    pub fn mark_masked(mut self, mask: u32, value: u32) -> Self {
+        self.add_expr(Meta::new(MetaType::Mark));
+        // the mark is loaded in host byte order, so the mask and the value are in the same order
+        self.add_expr(Bitwise::and(mask.to_ne_bytes()));
+        self.add_expr(Cmp::new(CmpOp::Eq, (value & mask).to_ne_bytes()));
+        self
+    }
+    /// Matches packets whose security mark is `secmark`. The marks are set by the security
+    /// subsystem, e.g. by SELinux according to the labels of the sockets, or by the rules
+    /// applying a secmark object, and are only available when the kernel is built with
+    /// `CONFIG_NETWORK_SECMARK`. The value of a mark is the security id the subsystem assigned
+    /// to a label, not the label itself.
+    pub fn secmark(mut self, secmark: u32) -> Self {
+        self.add_expr(Meta::new(MetaType::SecMark));
+        // the mark is loaded in host byte order
+        self.add_expr(Cmp::new(CmpOp::Eq, secmark.to_ne_bytes()));
+        self
+    }
+    /// Matches packets of a connection whose security mark is `secmark`. See [`Rule::secmark`]
+    /// and [`Rule::save_ct_secmark`].
+    pub fn ct_secmark(mut self, secmark: u32) -> Self {
+        self.add_expr(Conntrack::new(ConntrackKey::SecMark));
+        self.add_expr(Cmp::new(CmpOp::Eq, secmark.to_ne_bytes()));
+        self
+    }
+    /// Matches packets of a connection whose number of bytes in both directions compares to
+    /// `bytes` with `op`, e.g. `ct_bytes(CmpOp::Gt, 1 << 30)` for `ct bytes > 1g` in nftables, to
+    /// single out the long-lived flows. The bytes are only counted when conntrack accounting is
+    /// enabled (the `net.netfilter.nf_conntrack_acct` sysctl), otherwise they stay at zero.
+    pub fn ct_bytes(self, op: CmpOp, bytes: u64) -> Self {
+        self.match_ct_counter(ConntrackKey::Bytes, op, bytes)
+    }
+    /// Matches packets of a connection whose number of packets in both directions compares to
+    /// `packets` with `op`. See [`Rule::ct_bytes`].
+    pub fn ct_packets(self, op: CmpOp, packets: u64) -> Self {
+        self.match_ct_counter(ConntrackKey::Packets, op, packets)
+    }
+    /// Copies the security mark of the packets to their connection, like
+    /// `ct secmark set meta secmark` in nftables, so that the following packets of the
+    /// connection can get it back with [`Rule::restore_ct_secmark`].
+    pub fn save_ct_secmark(mut self) -> Self {
+        self.add_expr(Meta::new(MetaType::SecMark));
+        self.add_expr(
+            Conntrack::default()
+                .with_key(ConntrackKey::SecMark)
+                .with_sreg(Register::Reg1),
+        );
+        self
+    }
+    /// Copies the security mark of the connection to the packets, like
+    /// `meta secmark set ct secmark` in nftables.
+    pub fn restore_ct_secmark(mut self) -> Self {
+        self.add_expr(Conntrack::new(ConntrackKey::SecMark));
+        self.add_expr(
+            Meta::default()
+                .with_key(MetaType::SecMark)
+                .with_sreg(Register::Reg1),
+        );
+        self
+    }
+    /// Matches packets whose priority is the tc class `major:minor`, like
+    /// `meta priority major:minor` in nftables.
+    pub fn priority(mut self, major: u16, minor: u16) -> Self {
+        self.add_expr(Meta::new(MetaType::Priority));
+        // the priority is loaded in host byte order
+        self.add_expr(Cmp::new(CmpOp::Eq, tc_classid(major, minor).to_ne_bytes()));
+        self
+    }
+    /// Sets the priority of the packets to the tc class `major:minor`, so that the classful
+    /// qdiscs of the output interface, such as HTB, put them in that class.
+    pub fn set_priority(mut self, major: u16, minor: u16) -> Self {
+        self.add_expr(Immediate::new_data(
+            tc_classid(major, minor).to_ne_bytes().to_vec(),
+            Register::Reg1,
+        ));
+        self.add_expr(
+            Meta::default()
+                .with_key(MetaType::Priority)
+                .with_sreg(Register::Reg1),
+        );
+        self
+    }
+    /// Matches packets processed by the CPU numbered `cpu`, which is mostly useful to count the
+    /// packets per CPU when investigating how the receive queues spread the load. The kernel
+    /// does not expose the checksum or offload status of the packets to nftables.
+    pub fn cpu(mut self, cpu: u32) -> Self {
+        self.add_expr(Meta::new(MetaType::Cpu));
+        // the CPU number is loaded in host byte order
+        self.add_expr(Cmp::new(CmpOp::Eq, cpu.to_ne_bytes()));
+        self
+    }
+    /// Matches packets received on an interface of the given `group`. Groups are assigned with
+    /// `ip link set dev <iface> group <group>`, and unlike the interface names they don't change
+    /// when an interface is renamed, so a group can stand for e.g. all the WAN interfaces.
+    pub fn iifgroup(mut self, group: u32) -> Self {
+        self.add_expr(Meta::new(MetaType::IifGroup));
+        // the group is loaded in host byte order
+        self.add_expr(Cmp::new(CmpOp::Eq, group.to_ne_bytes()));
+        self
+    }
+    /// Matches packets sent on an interface of the given `group`. See [`Rule::iifgroup`].
+    pub fn oifgroup(mut self, group: u32) -> Self {
+        self.add_expr(Meta::new(MetaType::OifGroup));
+        self.add_expr(Cmp::new(CmpOp::Eq, group.to_ne_bytes()));
+        self
+    }
     /// Matches packets going through `iface_index`. Interface indexes can be queried with
     /// `iface_index()`.
     pub fn iface_id(mut self, iface_index: libc::c_uint) -> Self {
@@ -176,18 +985,32 @@ impl Rule {
         self.add_expr(Cmp::new(CmpOp::Eq, iface_index.to_be_bytes()));
         self
     }
+    /// Matches packets going through the interface named `iface_name`, by looking up its index
+    /// once, when building the rule. This is faster than matching on the name with `iface()`,
+    /// but the rule will not match any more if the interface is recreated.
+    pub fn iface_by_name(self, iface_name: &str) -> Result<Self, BuilderError> {
+        let index = iface_index(iface_name).map_err(BuilderError::InterfaceLookupFailed)?;
+        Ok(self.iface_id(index))
+    }
     /// Matches packets going through `iface_name`, an interface name, as in "wlan0" or "lo"
-    pub fn iface(mut self, iface_name: &str) -> Result<Self, BuilderError> {
-        if iface_name.len() >= libc::IFNAMSIZ {
-            return Err(BuilderError::InterfaceNameTooLong);
-        }
-        let mut iface_vec = iface_name.as_bytes().to_vec();
-        // null terminator
-        iface_vec.push(0u8);
-
-        self.add_expr(Meta::new(MetaType::IifName));
-        self.add_expr(Cmp::new(CmpOp::Eq, iface_vec));
-        Ok(self)
+    pub fn iface(self, iface_name: &str) -> Result<Self, BuilderError> {
+        self.match_iface_name(MetaType::IifName, iface_name)
+    }
+    /// Matches packets coming in through a loopback interface, by the type of the interface
+    /// rather than by its name or index, like `meta iiftype loopback` in nftables. As there is
+    /// no incoming interface in the output hook, use [`Rule::oif_is_loopback`] there.
+    pub fn is_loopback(mut self) -> Self {
+        self.add_expr(Meta::new(MetaType::IifType));
+        // the type of the interface is loaded in host byte order
+        self.add_expr(Cmp::new(CmpOp::Eq, libc::ARPHRD_LOOPBACK.to_ne_bytes()));
+        self
+    }
+    /// Matches packets leaving through a loopback interface, like `meta oiftype loopback` in
+    /// nftables.
+    pub fn oif_is_loopback(mut self) -> Self {
+        self.add_expr(Meta::new(MetaType::OifType));
+        self.add_expr(Cmp::new(CmpOp::Eq, libc::ARPHRD_LOOPBACK.to_ne_bytes()));
+        self
     }
     /// Matches packets leaving through `oface_index`. Interface indexes can be queried with
     /// `iface_index()`.
@@ -196,17 +1019,241 @@ impl Rule {
         self.add_expr(Cmp::new(CmpOp::Eq, oface_index.to_be_bytes()));
         self
     }
+    /// Matches packets leaving through the interface named `oface_name`, by looking up its index
+    /// once, when building the rule. This is faster than matching on the name with `oface()`,
+    /// but the rule will not match any more if the interface is recreated.
+    pub fn oface_by_name(self, oface_name: &str) -> Result<Self, BuilderError> {
+        let index = iface_index(oface_name).map_err(BuilderError::InterfaceLookupFailed)?;
+        Ok(self.oface_id(index))
+    }
     /// Matches packets leaving through `oface_name`, an interface name, as in "wlan0" or "lo"
-    pub fn oface(mut self, oface_name: &str) -> Result<Self, BuilderError> {
-        if oface_name.len() >= libc::IFNAMSIZ {
+    pub fn oface(self, oface_name: &str) -> Result<Self, BuilderError> {
+        self.match_iface_name(MetaType::OifName, oface_name)
+    }
+    /// Matches packets received on a port of the bridge named `bridge_name`, like `meta ibrname`
+    /// in nftables. This is only useful in bridge tables, where [`Rule::iface`] matches the port
+    /// the packets were received on, rather than the bridge, so that the ports of a bridge can be
+    /// told apart.
+    pub fn ibrname(self, bridge_name: &str) -> Result<Self, BuilderError> {
+        self.match_iface_name(MetaType::BriIifName, bridge_name)
+    }
+    /// Matches packets sent on a port of the bridge named `bridge_name`, like `meta obrname` in
+    /// nftables. See [`Rule::ibrname`].
+    pub fn obrname(self, bridge_name: &str) -> Result<Self, BuilderError> {
+        self.match_iface_name(MetaType::BriOifName, bridge_name)
+    }
+    /// Matches packets whose input interface is one of `ifaces`, like `iifname { "eth0", "wg0" }`
+    /// in nftables. The names are stored in an anonymous set, which is added to `batch`: the rule
+    /// must be added to that same batch afterwards. Returns `BuilderError::EmptySet` if `ifaces`
+    /// is empty, as the rule could never match.
+    pub fn ifaces(mut self, ifaces: &[&str], batch: &mut Batch) -> Result<Self, BuilderError> {
+        if ifaces.is_empty() {
+            return Err(BuilderError::EmptySet);
+        }
+        let table = Table::new(self.get_family()).with_name(
+            self.get_table()
+                .ok_or(BuilderError::MissingChainInformationError)?,
+        );
+        let mut set_builder = SetBuilder::<[u8; libc::IFNAMSIZ]>::anonymous(&table)?;
+        for iface in ifaces {
+            if iface.len() >= libc::IFNAMSIZ {
+                return Err(BuilderError::InterfaceNameTooLong);
+            }
+            // set keys have a fixed size, so the name is null-padded up to IFNAMSIZ
+            let mut key = [0u8; libc::IFNAMSIZ];
+            key[..iface.len()].copy_from_slice(iface.as_bytes());
+            set_builder.add(&key);
+        }
+        let (set, elements) = set_builder.finish();
+
+        self.add_expr(Meta::new(MetaType::IifName));
+        self.add_expr(Lookup::new(&set)?);
+        batch.add(&set, MsgType::Add);
+        batch.add(&elements, MsgType::Add);
+        Ok(self)
+    }
+    /// Matches packets whose input interface name starts with `prefix`, like the `iifname "eth*"`
+    /// wildcard of nftables. Returns `BuilderError::EmptyPrefix` if `prefix` is empty.
+    pub fn iface_prefix(self, prefix: &str) -> Result<Self, BuilderError> {
+        self.match_iface_prefix(MetaType::IifName, prefix)
+    }
+    /// Matches packets whose output interface name starts with `prefix`, like the
+    /// `oifname "eth*"` wildcard of nftables.
+    pub fn oface_prefix(self, prefix: &str) -> Result<Self, BuilderError> {
+        self.match_iface_prefix(MetaType::OifName, prefix)
+    }
+    fn match_iface_prefix(mut self, key: MetaType, prefix: &str) -> Result<Self, BuilderError> {
+        if prefix.len() >= libc::IFNAMSIZ {
             return Err(BuilderError::InterfaceNameTooLong);
         }
-        let mut oface_vec = oface_name.as_bytes().to_vec();
-        // null terminator
-        oface_vec.push(0u8);
+        // the kernel rejects comparisons of zero bytes
+        if prefix.is_empty() {
+            return Err(BuilderError::EmptyPrefix);
+        }
 
-        self.add_expr(Meta::new(MetaType::OifName));
-        self.add_expr(Cmp::new(CmpOp::Eq, oface_vec));
+        self.add_expr(Meta::new(key));
+        // without a null terminator, the kernel only compares the first bytes of the name
+        self.add_expr(Cmp::new(CmpOp::Eq, prefix.as_bytes()));
+        Ok(self)
+    }
+    /// Matches packets whose `len` bytes at `offset` from the start of the `base` header compare
+    /// to `data` according to `op`.
+    pub fn match_raw(
+        mut self,
+        base: PayloadBase,
+        offset: u32,
+        len: u32,
+        op: CmpOp,
+        data: &[u8],
+    ) -> Result<Self, BuilderError> {
+        if data.len() != len as usize {
+            return Err(BuilderError::IncompatibleLength);
+        }
+        self.add_expr(Payload::raw(base, offset, len, Register::Reg1)?);
+        self.add_expr(Cmp::new(op, data));
+        Ok(self)
+    }
+    /// Matches TCP packets whose header `field` compares to `value` according to `op`. Returns
+    /// `BuilderError::IncompatibleLength` if `value` does not fit in the field.
+    pub fn tcp_field(
+        mut self,
+        field: TCPHeaderField,
+        op: CmpOp,
+        value: u32,
+    ) -> Result<Self, BuilderError> {
+        let len = field.len() as usize;
+        let bytes = value.to_be_bytes();
+        if bytes[..4 - len].iter().any(|b| *b != 0) {
+            return Err(BuilderError::IncompatibleLength);
+        }
+        self = self.protocol(Protocol::TCP);
+        self.add_expr(HighLevelPayload::Transport(TransportHeaderField::Tcp(field)).build());
+        self.add_expr(Cmp::new(op, &bytes[4 - len..]));
+        Ok(self)
+    }
+    /// Matches TCP packets carrying a maximum segment size option whose value compares to `mss`
+    /// according to `op`, e.g. `tcp option maxseg size < 1400`. Packets without this option
+    /// never match.
+    pub fn tcp_mss(mut self, op: CmpOp, mss: u16) -> Self {
+        self = self.protocol(Protocol::TCP);
+        self.add_expr(
+            ExtHdr::default()
+                .with_dreg(Register::Reg1)
+                .with_typ(2u8)
+                .with_offset(2u32)
+                .with_len(2u32)
+                .with_op(ExtHdrOp::TCPOpt),
+        );
+        self.add_expr(Cmp::new(op, mss.to_be_bytes()));
+        self
+    }
+    fn tcp_has_option(mut self, kind: u8) -> Self {
+        self = self.protocol(Protocol::TCP);
+        // with the presence flag, the expression loads a single byte, set to 1 if the packet
+        // carries the option
+        self.add_expr(
+            ExtHdr::default()
+                .with_dreg(Register::Reg1)
+                .with_typ(kind)
+                .with_offset(0u32)
+                .with_len(1u32)
+                .with_flags(NFT_EXTHDR_F_PRESENT)
+                .with_op(ExtHdrOp::TCPOpt),
+        );
+        self.add_expr(Cmp::new(CmpOp::Eq, [1u8]));
+        self
+    }
+    /// Matches TCP packets carrying a window scale option, like
+    /// `tcp option window exists` in nftables. The option may only be advertised in the SYN
+    /// packets.
+    pub fn tcp_has_wscale(self) -> Self {
+        self.tcp_has_option(3)
+    }
+    /// Matches TCP packets carrying a SACK-permitted option, like
+    /// `tcp option sack-perm exists` in nftables. The option may only be advertised in the SYN
+    /// packets.
+    pub fn tcp_has_sack_perm(self) -> Self {
+        self.tcp_has_option(4)
+    }
+    /// Matches UDP packets sent without a checksum, i.e. whose checksum field is zero, which is
+    /// only valid over IPv4.
+    pub fn udp_checksum_zero(mut self) -> Self {
+        self = self.protocol(Protocol::UDP);
+        self.add_expr(
+            HighLevelPayload::Transport(TransportHeaderField::Udp(UDPHeaderField::Checksum))
+                .build(),
+        );
+        self.add_expr(Cmp::new(CmpOp::Eq, [0u8, 0u8]));
+        self
+    }
+    /// Matches GRE packets carrying the given `key` (RFC 2890), e.g. to tell apart the tunnels
+    /// sharing a pair of endpoints. The packets without the key present flag don't match.
+    ///
+    /// The key follows the checksum when the checksum present flag is set, which would move it
+    /// to a different offset, so only the packets without a checksum match. Tunnels with
+    /// checksums need a second rule matching the key with [`Rule::match_raw`] at offset 8.
+    ///
+    /// [`Rule::match_raw`]: struct.Rule.html#method.match_raw
+    pub fn gre_key(mut self, key: u32) -> Result<Self, BuilderError> {
+        self.add_expr(Meta::new(MetaType::L4Proto));
+        self.add_expr(Cmp::new(CmpOp::Eq, [libc::IPPROTO_GRE as u8]));
+        // the checksum present and key present flags are the first and third bits of the header
+        self.add_expr(Payload::raw(PayloadBase::Transport, 0, 2, Register::Reg1)?);
+        self.add_expr(Bitwise::new(0xa000u16.to_be_bytes(), 0u16.to_be_bytes())?);
+        self.add_expr(Cmp::new(CmpOp::Eq, 0x2000u16.to_be_bytes()));
+        self.add_expr(Payload::raw(PayloadBase::Transport, 4, 4, Register::Reg1)?);
+        self.add_expr(Cmp::new(CmpOp::Eq, key.to_be_bytes()));
+        Ok(self)
+    }
+    /// Applies the verdict associated in the verdict `map` (see [`VerdictMapBuilder`]) to the
+    /// concatenation of the header `fields`, as in `ip saddr . tcp dport vmap { ... }`. The
+    /// packets whose key is not in the map go on to the next expression.
+    ///
+    /// Each field is loaded in its own 4 bytes registers: returns
+    /// `BuilderError::TooManyConcatFields` if they don't fit in the registers, and
+    /// `BuilderError::IncompatibleLength` if they don't match the length of the keys of the map.
+    /// Note that the fields are read blindly, so in inet tables the rule should first match the
+    /// protocols of the headers (e.g. with [`Rule::if_ipv4`]).
+    ///
+    /// [`VerdictMapBuilder`]: set/struct.VerdictMapBuilder.html
+    pub fn concat_vmap(
+        mut self,
+        fields: &[HighLevelPayload],
+        map: &Set,
+    ) -> Result<Self, BuilderError> {
+        if map.get_data_type() != Some(&NFT_DATA_VERDICT) {
+            return Err(BuilderError::NotAMap);
+        }
+        let mut register_index = 0;
+        for field in fields {
+            let mut payload = field.build();
+            let len = *payload.get_len().unwrap_or(&0);
+            payload.set_dreg(
+                Register::reg32(register_index).ok_or(BuilderError::TooManyConcatFields)?,
+            );
+            register_index += len.div_ceil(4);
+            if register_index > 16 {
+                return Err(BuilderError::TooManyConcatFields);
+            }
+            self.add_expr(payload);
+        }
+        if fields.is_empty() || map.get_key_len() != Some(&(register_index * 4)) {
+            return Err(BuilderError::IncompatibleLength);
+        }
+        self.add_expr(Lookup::new_map(map, Register::Verdict)?.with_sreg(Register::Reg32_00));
+        Ok(self)
+    }
+    /// Loads `len` zero bytes into `register`, e.g. to clear the bytes of the registers that the
+    /// fields of a concatenated key don't fill, which would otherwise hold leftovers of the
+    /// previous expressions. The data may span several registers from `register` onwards, but
+    /// `BuilderError::IncompatibleLength` is returned if it is empty or overflows the last data
+    /// register, or if `register` is the verdict register.
+    pub fn zero_register(mut self, register: Register, len: u32) -> Result<Self, BuilderError> {
+        match register.available_len() {
+            Some(available) if len > 0 && len <= available => {}
+            _ => return Err(BuilderError::IncompatibleLength),
+        }
+        self.add_expr(Immediate::new_data(vec![0; len as usize], register));
         Ok(self)
     }
     /// Matches packets whose source IP address is `saddr`.
@@ -217,6 +1264,14 @@ impl Rule {
     pub fn daddr(self, ip: IpAddr) -> Self {
         self.match_ip(ip, false)
     }
+    /// Matches packets whose destination address is of the given type according to the routing
+    /// table, e.g. `AddrType::Local` for the traffic destined to this host.
+    pub fn daddr_type(mut self, addr_type: AddrType) -> Self {
+        self.add_expr(Fib::new(FibResult::AddrType, NFTA_FIB_F_DADDR));
+        // the address type is loaded as a 32 bits integer in host byte order
+        self.add_expr(Cmp::new(CmpOp::Eq, (addr_type as u32).to_ne_bytes()));
+        self
+    }
     /// Matches packets whose source network is `net`.
     pub fn snetwork(self, net: IpNetwork) -> Result<Self, BuilderError> {
         self.match_network(net, true)
@@ -225,6 +1280,29 @@ impl Rule {
     pub fn dnetwork(self, net: IpNetwork) -> Result<Self, BuilderError> {
         self.match_network(net, false)
     }
+    /// Matches packets whose source address is in one of `nets`, like
+    /// `ip saddr { 10.0.0.0/8, 192.168.0.0/16 }` in nftables. The networks are stored in an
+    /// anonymous interval set, which is added to `batch`: the rule must be added to that same
+    /// batch afterwards. As a set only holds addresses of one family, the networks must be either
+    /// all IPv4 or all IPv6, otherwise `BuilderError::MixedAddressFamilies` is returned.
+    pub fn saddr_in(self, nets: &[IpNetwork], batch: &mut Batch) -> Result<Self, BuilderError> {
+        self.match_networks(nets, true, batch)
+    }
+    /// Matches packets whose destination address is in one of `nets`. See [`Rule::saddr_in`].
+    pub fn daddr_in(self, nets: &[IpNetwork], batch: &mut Batch) -> Result<Self, BuilderError> {
+        self.match_networks(nets, false, batch)
+    }
+    /// Counts the packets matching the rule and their bytes, without issuing a verdict, so that
+    /// the packets go on to the next rules. Placed without any match at the end of a chain, such
+    /// a rule counts all the packets that fell through the previous rules before the policy of
+    /// the chain applies to them. The counts can be read back from the rules listed with
+    /// [`list_rules_for_chain`].
+    ///
+    /// [`list_rules_for_chain`]: fn.list_rules_for_chain.html
+    pub fn count_only(mut self) -> Self {
+        self.add_expr(Counter::default());
+        self
+    }
     /// Adds the `Accept` verdict to the rule. The packet will be sent to destination.
     pub fn accept(mut self) -> Self {
         self.add_expr(Immediate::new_verdict(VerdictKind::Accept));
@@ -235,28 +1313,154 @@ impl Rule {
         self.add_expr(Immediate::new_verdict(VerdictKind::Drop));
         self
     }
+    /// Counts, logs with the given `prefix` and drops the packets, a shorthand for the usual
+    /// `counter log prefix "..." drop` sequence.
+    pub fn log_and_drop(mut self, prefix: &str) -> Result<Self, BuilderError> {
+        self.add_expr(Counter::default());
+        self.add_expr(Log::new(None, Some(prefix))?);
+        Ok(self.drop())
+    }
+    /// Rejects the packets, answering them that they are administratively prohibited, with the
+    /// code suited to the family of the rule (see [`RejectCode::admin_prohibited`]): in an
+    /// `inet` table, the IPv4 packets get an ICMP answer and the IPv6 packets an ICMPv6 one.
+    /// Use [`Rule::reject_with`] to answer with another code. Returns
+    /// `BuilderError::InvalidRejectCode` in the families that can't reject packets, such as
+    /// `arp`.
+    pub fn reject(self) -> Result<Self, BuilderError> {
+        let code = RejectCode::admin_prohibited(self.get_family())
+            .ok_or(BuilderError::InvalidRejectCode)?;
+        self.reject_with(code)
+    }
+    /// Rejects the packets, answering them with `code`. ICMP (resp. ICMPv6) codes are only legal
+    /// in `ip` (resp. `ip6`) and `inet` tables; in the latter, the rule is restricted to the IPv4
+    /// (resp. IPv6) packets. Family-agnostic ICMP codes are only legal in `inet`, `bridge` and
+    /// `netdev` tables, and TCP resets restrict the rule to TCP packets.
+    pub fn reject_with(mut self, code: RejectCode) -> Result<Self, BuilderError> {
+        let family = self.get_family();
+        let code_family = match code {
+            RejectCode::TcpRst => return Ok(self.reject_tcp_reset()),
+            RejectCode::Icmpx(_) => {
+                if !matches!(
+                    family,
+                    ProtocolFamily::Inet | ProtocolFamily::Bridge | ProtocolFamily::NetDev
+                ) {
+                    return Err(BuilderError::InvalidRejectCode);
+                }
+                None
+            }
+            RejectCode::Icmp(_) => Some(ProtocolFamily::Ipv4),
+            RejectCode::Icmpv6(_) => Some(ProtocolFamily::Ipv6),
+        };
+        match (family, code_family) {
+            (_, None) => {}
+            (ProtocolFamily::Inet, Some(code_family)) => {
+                self.add_expr(Meta::new(MetaType::NfProto));
+                self.add_expr(Cmp::new(CmpOp::Eq, [code_family as u8]));
+            }
+            (family, Some(code_family)) if family == code_family => {}
+            _ => return Err(BuilderError::InvalidRejectCode),
+        }
+        self.add_expr(Reject::new(code));
+        Ok(self)
+    }
+    /// Rejects the packets by answering them with a TCP reset, which tears down the connection
+    /// immediately instead of letting the peer time out, e.g. to make a port scan see closed
+    /// ports. A reset only makes sense in answer to a TCP packet, so this restricts the rule to
+    /// TCP packets, like `reject with tcp reset` in nftables.
+    pub fn reject_tcp_reset(mut self) -> Self {
+        self = self.protocol(Protocol::TCP);
+        self.add_expr(Reject::new(RejectCode::TcpRst));
+        self
+    }
     /// Adds the `Masquerade` verdict to the rule. The packet will have its
     /// source address rewritten.
-    pub fn masquerade(mut self) -> Self {
+    ///
+    /// Returns `BuilderError::InvalidChainForNat` if the base chain of the rule cannot perform
+    /// it, see [`Rule::check_nat_chain`].
+    ///
+    /// [`Rule::check_nat_chain`]: struct.Rule.html#method.check_nat_chain
+    pub fn masquerade(mut self) -> Result<Self, BuilderError> {
+        self.check_nat_expr(&ExpressionVariant::from(Masquerade {}))?;
         self.add_expr(Masquerade {});
-        self
+        Ok(self)
     }
     /// Adds the `Nat` verdict to the rule, with type `DNat`. The packet
     /// will have its destination address and optionally port rewritten.
-    pub fn dnat(mut self, dst: IpAddr, port: Option<u16>) -> Self {
-        self.add_expr(Immediate::new_data(ip_to_vec(dst), Register::Reg1));
-        if let Some(port) = port {
-            self.add_expr(Immediate::new_data(
-                port.to_be_bytes().to_vec(),
-                Register::Reg2,
-            ));
-        }
-        self.add_expr(Nat {
-            nat_type: Some(NatType::DNat),
-            family: Some(ProtocolFamily::Ipv4),
-            ip_register: Some(Register::Reg1),
-            port_register: port.map(|_| Register::Reg2),
-        });
+    ///
+    /// Returns `BuilderError::InvalidChainForNat` if the base chain of the rule cannot perform
+    /// it, see [`Rule::check_nat_chain`].
+    ///
+    /// [`Rule::check_nat_chain`]: struct.Rule.html#method.check_nat_chain
+    pub fn dnat(self, dst: IpAddr, port: Option<u16>) -> Result<Self, BuilderError> {
+        self.nat(NatType::DNat, dst, port.map(|port| (port, port)))
+    }
+    /// Adds the `Nat` verdict to the rule, with type `DNat`. The packet will have its
+    /// destination address rewritten, and its destination port rewritten to a port in the
+    /// range `port_min`..=`port_max`.
+    ///
+    /// Returns `BuilderError::InvalidChainForNat` if the base chain of the rule cannot perform
+    /// it, see [`Rule::check_nat_chain`].
+    ///
+    /// [`Rule::check_nat_chain`]: struct.Rule.html#method.check_nat_chain
+    pub fn dnat_range(
+        self,
+        dst: IpAddr,
+        port_min: u16,
+        port_max: u16,
+    ) -> Result<Self, BuilderError> {
+        self.nat(NatType::DNat, dst, Some((port_min, port_max)))
+    }
+    /// Adds the `Nat` verdict to the rule, with type `SNat`. The packet
+    /// will have its source address and optionally port rewritten.
+    ///
+    /// Returns `BuilderError::InvalidChainForNat` if the base chain of the rule cannot perform
+    /// it, see [`Rule::check_nat_chain`].
+    ///
+    /// [`Rule::check_nat_chain`]: struct.Rule.html#method.check_nat_chain
+    pub fn snat(self, src: IpAddr, port: Option<u16>) -> Result<Self, BuilderError> {
+        self.nat(NatType::SNat, src, port.map(|port| (port, port)))
+    }
+    /// Adds the `Nat` verdict to the rule, with type `SNat`. The packet will have its source
+    /// address rewritten, and its source port rewritten to a port in the range
+    /// `port_min`..=`port_max`.
+    ///
+    /// Returns `BuilderError::InvalidChainForNat` if the base chain of the rule cannot perform
+    /// it, see [`Rule::check_nat_chain`].
+    ///
+    /// [`Rule::check_nat_chain`]: struct.Rule.html#method.check_nat_chain
+    pub fn snat_range(
+        self,
+        src: IpAddr,
+        port_min: u16,
+        port_max: u16,
+    ) -> Result<Self, BuilderError> {
+        self.nat(NatType::SNat, src, Some((port_min, port_max)))
+    }
+    /// Assigns the conntrack helper object `name` to the connection of the packet, as in
+    /// `ct helper set "ftp-standard"`. The object must be declared in the table of the rule (see
+    /// [`CtHelper`]).
+    ///
+    /// [`CtHelper`]: struct.CtHelper.html
+    pub fn ct_helper(mut self, name: impl Into<String>) -> Self {
+        self.add_expr(ObjRef::new(ObjectType::CtHelper, name));
+        self
+    }
+    /// Matches the packets as long as the rate of the named limit object `name` isn't exceeded,
+    /// as in `limit name "ssh-rate"`. The object must be declared in the table of the rule (see
+    /// [`Object::limit`]), and all the rules referencing it share its rate.
+    ///
+    /// [`Object::limit`]: struct.Object.html#method.limit
+    pub fn limit_ref(mut self, name: impl Into<String>) -> Self {
+        self.add_expr(ObjRef::new(ObjectType::Limit, name));
+        self
+    }
+    /// Offloads the flow of the packet to the flowtable `name`, as in `flow add @fastpath`. The
+    /// flowtable must be declared in the table of the rule (see [`Flowtable`]). This is usually
+    /// done in a forward chain, for the established connections.
+    ///
+    /// [`Flowtable`]: struct.Flowtable.html
+    pub fn flow_offload(mut self, name: impl Into<String>) -> Self {
+        self.add_expr(FlowOffload::new(name));
         self
     }
     /// Adds the `ExtHdr` expression to the rule. The packet will have
@@ -276,6 +1480,56 @@ impl Rule {
         );
         self
     }
+    /// Matches packets that arrived over IPsec, i.e. that were decrypted by an IPsec security
+    /// association, like `meta secpath exists` in nftables. This allows e.g. only VPN clients to
+    /// reach a service. The decrypted packets go through the input hooks a second time, so the
+    /// rule sees both the encrypted packet, which doesn't match, and the decrypted one.
+    pub fn ipsec_in(mut self) -> Self {
+        self.add_expr(Meta::new(MetaType::SecPath));
+        self.add_expr(Cmp::new(CmpOp::Eq, [1u8]));
+        self
+    }
+    /// Matches packets that will be sent over IPsec, i.e. whose route goes through an IPsec
+    /// transformation, like `rt ipsec exists` in nftables. The route is only known once the
+    /// packet was routed, so this should be used in the forward, output and postrouting chains.
+    pub fn ipsec_out(mut self) -> Self {
+        self.add_expr(
+            Rt::default()
+                .with_dreg(Register::Reg1)
+                .with_key(RtKey::Xfrm),
+        );
+        self.add_expr(Cmp::new(CmpOp::Eq, [1u8]));
+        self
+    }
+    /// Matches packets seen between the times of the day `start` and `end` included, given as
+    /// durations since midnight UTC: the kernel doesn't know about the local time zone. When
+    /// `start` is after `end`, the range wraps around midnight, e.g. from 22:00 to 06:00.
+    ///
+    /// Requires Linux 5.4 or later.
+    pub fn hour_range(self, start: Duration, end: Duration) -> Result<Self, BuilderError> {
+        const SECS_PER_DAY: u64 = 24 * 60 * 60;
+        if start.as_secs() >= SECS_PER_DAY || end.as_secs() >= SECS_PER_DAY {
+            return Err(BuilderError::InvalidTimeOfDay);
+        }
+        let (start, end) = (start.as_secs() as u32, end.as_secs() as u32);
+        if start <= end {
+            Ok(self.match_meta_range(MetaType::TimeHour, RangeOp::Eq, start, end))
+        } else if start == end + 1 {
+            // the range covers the whole day
+            Ok(self)
+        } else {
+            // match the times that are not in the gap between `end` and `start`
+            Ok(self.match_meta_range(MetaType::TimeHour, RangeOp::Neq, end + 1, start - 1))
+        }
+    }
+    /// Matches packets seen on the given `day` of the week, in UTC.
+    ///
+    /// Requires Linux 5.4 or later.
+    pub fn weekday(mut self, day: Weekday) -> Self {
+        self.add_expr(Meta::new(MetaType::TimeDay));
+        self.add_expr(Cmp::new(CmpOp::Eq, [day as u8]));
+        self
+    }
     /// Sets the TCP MSS to the path MTU observed by the routing cache.
     pub fn clamp_mss_to_pmtu(mut self) -> Self {
         self.add_expr(
@@ -316,6 +1570,25 @@ impl Rule {
     }
 }
 
+/// Returns the 32 bits tc class id `major:minor`, as stored in the priority of the packets.
+fn tc_classid(major: u16, minor: u16) -> u32 {
+    (major as u32) << 16 | minor as u32
+}
+
+/// Sorts the inclusive ranges and merges the overlapping or nested ones, as `nft` does, since the
+/// kernel refuses overlapping intervals in a set.
+fn merge_ranges<T: Ord + Copy>(mut ranges: Vec<(T, T)>) -> Vec<(T, T)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(T, T)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
 /// Looks up the interface index for a given interface name.
 pub fn iface_index(name: &str) -> Result<libc::c_uint, std::io::Error> {
     let c_name = CString::new(name)?;
@@ -1,19 +1,31 @@
 use rustables_macros::nfnetlink_struct;
 
 use crate::data_type::DataType;
-use crate::error::BuilderError;
-use crate::nlmsg::NfNetlinkObject;
+use crate::error::{BuilderError, QueryError};
+use crate::expr::{Verdict, VerdictKind};
+use crate::nlmsg::{pad_netlink_object, NfNetlinkAttribute, NfNetlinkObject};
+use crate::parser::{decode_comment_userdata, encode_comment_userdata};
 use crate::parser_impls::{NfNetlinkData, NfNetlinkList};
+use crate::query::get_object;
 use crate::sys::{
-    NFTA_SET_ELEM_KEY, NFTA_SET_ELEM_LIST_ELEMENTS, NFTA_SET_ELEM_LIST_SET,
-    NFTA_SET_ELEM_LIST_TABLE, NFTA_SET_FLAGS, NFTA_SET_ID, NFTA_SET_KEY_LEN, NFTA_SET_KEY_TYPE,
-    NFTA_SET_NAME, NFTA_SET_TABLE, NFTA_SET_USERDATA, NFT_MSG_DELSET, NFT_MSG_DELSETELEM,
-    NFT_MSG_NEWSET, NFT_MSG_NEWSETELEM,
+    nlattr, NFTA_SET_DATA_LEN, NFTA_SET_DATA_TYPE, NFTA_SET_DESC, NFTA_SET_DESC_SIZE,
+    NFTA_SET_ELEM_DATA, NFTA_SET_ELEM_EXPIRATION, NFTA_SET_ELEM_FLAGS, NFTA_SET_ELEM_KEY,
+    NFTA_SET_ELEM_LIST_ELEMENTS, NFTA_SET_ELEM_LIST_SET, NFTA_SET_ELEM_LIST_SET_ID,
+    NFTA_SET_ELEM_LIST_TABLE, NFTA_SET_ELEM_TIMEOUT, NFTA_SET_ELEM_USERDATA, NFTA_SET_FLAGS,
+    NFTA_SET_ID, NFTA_SET_KEY_LEN, NFTA_SET_KEY_TYPE, NFTA_SET_NAME, NFTA_SET_TABLE,
+    NFTA_SET_USERDATA, NFT_DATA_VERDICT, NFT_MSG_DELSET, NFT_MSG_DELSETELEM, NFT_MSG_GETSETELEM,
+    NFT_MSG_NEWSET, NFT_MSG_NEWSETELEM, NFT_SET_ANONYMOUS, NFT_SET_CONSTANT,
+    NFT_SET_ELEM_INTERVAL_END, NFT_SET_INTERVAL, NFT_SET_MAP,
 };
-use crate::table::Table;
+use crate::table::{validate_name, Table};
 use crate::ProtocolFamily;
 use std::fmt::Debug;
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Identifiers of the anonymous sets, which are referenced by their id rather than by their name
+/// inside a batch.
+static ANONYMOUS_SET_ID: AtomicU32 = AtomicU32::new(0);
 
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 #[nfnetlink_struct(derive_deserialize = false)]
@@ -29,12 +41,68 @@ pub struct Set {
     pub key_type: u32,
     #[field(NFTA_SET_KEY_LEN)]
     pub key_len: u32,
+    #[field(NFTA_SET_DATA_TYPE)]
+    pub data_type: u32,
+    #[field(NFTA_SET_DATA_LEN)]
+    pub data_len: u32,
     #[field(NFTA_SET_ID)]
     pub id: u32,
+    /// The description of the set, which the kernel uses to pick the implementation of the set
+    /// best suited to its expected size.
+    #[field(NFTA_SET_DESC)]
+    pub desc: SetDescription,
     #[field(NFTA_SET_USERDATA)]
     pub userdata: String,
 }
 
+/// The nested description of a [`Set`].
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[nfnetlink_struct(nested = true)]
+pub struct SetDescription {
+    /// The maximum number of elements expected in the set.
+    #[field(NFTA_SET_DESC_SIZE, name_in_functions = "max_elements")]
+    pub size: u32,
+}
+
+impl Set {
+    /// Retrieves the element of this set with the same key as `element` from the kernel, e.g. to
+    /// check whether an address is already in a blocklist without dumping the whole set, along
+    /// with its timeout and expiration. Returns `None` if the set holds no such element.
+    pub fn get_element(&self, element: &SetElement) -> Result<Option<SetElement>, QueryError> {
+        let mut filter = SetElementList {
+            family: self.family,
+            table: Some(
+                self.get_table()
+                    .ok_or(BuilderError::MissingTableName)?
+                    .clone(),
+            ),
+            set: Some(self.get_name().ok_or(BuilderError::MissingSetName)?.clone()),
+            ..Default::default()
+        };
+        // only the key identifies the element
+        let mut key = SetElement::default();
+        if let Some(k) = element.get_key() {
+            key.set_key(k.clone());
+        }
+        filter.set_elements(SetElementListElements::default().with_value(key));
+
+        match get_object(NFT_MSG_GETSETELEM as u16, &filter) {
+            Ok(list) => Ok(list
+                .get_elements()
+                .and_then(|elements| elements.get(0))
+                .cloned()),
+            Err(QueryError::NetlinkError(e, _)) if e.error == libc::ENOENT => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns whether this set holds an element with the same key as `element`. See
+    /// [`Set::get_element`].
+    pub fn contains(&self, element: &SetElement) -> Result<bool, QueryError> {
+        Ok(self.get_element(element)?.is_some())
+    }
+}
+
 impl NfNetlinkObject for Set {
     const MSG_TYPE_ADD: u32 = NFT_MSG_NEWSET;
     const MSG_TYPE_DEL: u32 = NFT_MSG_DELSET;
@@ -55,30 +123,118 @@ pub struct SetBuilder<K: DataType> {
 }
 
 impl<K: DataType> SetBuilder<K> {
+    /// Creates a new set named `name` inside `table`. Returns `BuilderError::NameTooLong` if
+    /// the name doesn't fit in the limits of nftables.
     pub fn new(name: impl Into<String>, table: &Table) -> Result<Self, BuilderError> {
         let table_name = table.get_name().ok_or(BuilderError::MissingTableName)?;
         let set_name = name.into();
-        let set = Set::default()
+        validate_name(&set_name)?;
+        let mut set = Set::default()
             .with_key_type(K::TYPE)
             .with_key_len(K::LEN)
             .with_table(table_name)
             .with_name(&set_name);
+        set.family = table.get_family();
 
         Ok(SetBuilder {
             inner: set,
             list: SetElementList {
+                family: table.get_family(),
                 table: Some(table_name.clone()),
                 set: Some(set_name),
+                set_id: None,
                 elements: Some(SetElementListElements::default()),
             },
             _phantom: PhantomData,
         })
     }
 
+    /// Creates a new anonymous set, a constant set that only lives as long as the rule that
+    /// references it (through a [`Lookup`] expression). The set, its elements and that rule must
+    /// be sent in the same batch.
+    ///
+    /// [`Lookup`]: expr/struct.Lookup.html
+    pub fn anonymous(table: &Table) -> Result<Self, BuilderError> {
+        // the kernel replaces the '%d' with the first free index in the table
+        let mut res = Self::new("__set%d", table)?;
+        let id = ANONYMOUS_SET_ID.fetch_add(1, Ordering::Relaxed);
+        res.inner.set_id(id);
+        res.inner.set_flags(NFT_SET_ANONYMOUS | NFT_SET_CONSTANT);
+        res.list.set_set_id(id);
+        Ok(res)
+    }
+
+    /// Makes this set an interval set, whose elements are ranges of keys (see
+    /// [`SetBuilder::add_range`]). This must be called before adding any element.
+    ///
+    /// [`SetBuilder::add_range`]: struct.SetBuilder.html#method.add_range
+    pub fn with_intervals(mut self) -> Self {
+        let flags = self.inner.get_flags().copied().unwrap_or(0);
+        self.inner.set_flags(flags | NFT_SET_INTERVAL);
+        self
+    }
+
+    fn is_interval(&self) -> bool {
+        self.inner.get_flags().copied().unwrap_or(0) & NFT_SET_INTERVAL != 0
+    }
+
+    fn new_element(key: Vec<u8>) -> SetElement {
+        SetElement::default().with_key(NfNetlinkData::default().with_value(key))
+    }
+
     pub fn add(&mut self, key: &K) {
-        self.list.elements.as_mut().unwrap().add_value(SetElement {
-            key: Some(NfNetlinkData::default().with_value(key.data())),
-        });
+        if self.is_interval() {
+            self.push_range(Self::new_element(key.data()), key.data());
+            return;
+        }
+        self.list
+            .elements
+            .as_mut()
+            .unwrap()
+            .add_value(Self::new_element(key.data()));
+    }
+
+    /// Adds all the keys from `start` to `end` (inclusive) to the set, which must be an interval
+    /// set (see [`SetBuilder::with_intervals`]). The keys are compared as big-endian numbers,
+    /// which is the byte order of addresses and ports, but not of the 32 bits integers loaded in
+    /// host byte order. Returns `BuilderError::InvertedRange` if `start` comes after `end`.
+    ///
+    /// The kernel refuses overlapping ranges when the batch is committed, so the ranges added to
+    /// a set must be disjoint.
+    ///
+    /// [`SetBuilder::with_intervals`]: struct.SetBuilder.html#method.with_intervals
+    pub fn add_range(&mut self, start: &K, end: &K) -> Result<(), BuilderError> {
+        let (start, end) = (start.data(), end.data());
+        if start > end {
+            return Err(BuilderError::InvertedRange);
+        }
+        self.push_range(Self::new_element(start), end);
+        Ok(())
+    }
+
+    /// Adds the range beginning with the element `start` and ending with the key `end`.
+    fn push_range(&mut self, start: SetElement, end: Vec<u8>) {
+        let elements = self.list.elements.as_mut().unwrap();
+        elements.add_value(start);
+        // the kernel stores the first key after the range, as in `nft --debug=netlink`, except
+        // when the range extends up to the largest key
+        if let Some(end) = next_key(end) {
+            elements.add_value(Self::new_element(end).with_flags(NFT_SET_ELEM_INTERVAL_END));
+        }
+    }
+
+    /// Adds `key` to the set, annotated with `comment` (see [`SetElement::set_comment`]). In an
+    /// interval set, the key is added as a range of a single key, whose start holds the comment.
+    ///
+    /// [`SetElement::set_comment`]: struct.SetElement.html#method.set_comment
+    pub fn add_with_comment(&mut self, key: &K, comment: &str) -> Result<(), BuilderError> {
+        let elem = Self::new_element(key.data()).with_comment(comment)?;
+        if self.is_interval() {
+            self.push_range(elem, key.data());
+        } else {
+            self.list.elements.as_mut().unwrap().add_value(elem);
+        }
+        Ok(())
     }
 
     pub fn finish(self) -> (Set, SetElementList) {
@@ -86,23 +242,166 @@ impl<K: DataType> SetBuilder<K> {
     }
 }
 
+/// Builds a map, a set whose elements associate a value of type `V` to their key. A [`Lookup`]
+/// expression with a destination register loads the value associated to the key it looks up.
+///
+/// [`Lookup`]: expr/struct.Lookup.html
+pub struct MapBuilder<K: DataType, V: DataType> {
+    inner: SetBuilder<K>,
+    _phantom: PhantomData<V>,
+}
+
+impl<K: DataType, V: DataType> MapBuilder<K, V> {
+    pub fn new(name: impl Into<String>, table: &Table) -> Result<Self, BuilderError> {
+        Ok(Self::from_set_builder(SetBuilder::new(name, table)?))
+    }
+
+    /// Creates a new anonymous map. As for anonymous sets (see [`SetBuilder::anonymous`]), the
+    /// map must be sent in the same batch as the rule that references it.
+    ///
+    /// [`SetBuilder::anonymous`]: struct.SetBuilder.html#method.anonymous
+    pub fn anonymous(table: &Table) -> Result<Self, BuilderError> {
+        Ok(Self::from_set_builder(SetBuilder::anonymous(table)?))
+    }
+
+    fn from_set_builder(mut inner: SetBuilder<K>) -> Self {
+        let flags = inner.inner.get_flags().copied().unwrap_or(0);
+        inner.inner.set_flags(flags | NFT_SET_MAP);
+        inner.inner.set_data_type(V::TYPE);
+        inner.inner.set_data_len(V::LEN);
+        MapBuilder {
+            inner,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn add(&mut self, key: &K, value: &V) {
+        self.inner.list.elements.as_mut().unwrap().add_value(
+            SetElement::default()
+                .with_key(NfNetlinkData::default().with_value(key.data()))
+                .with_data(NfNetlinkData::default().with_value(value.data())),
+        );
+    }
+
+    pub fn finish(self) -> (Set, SetElementList) {
+        self.inner.finish()
+    }
+}
+
+/// Builds a verdict map, a map associating a verdict to each of its keys, as in
+/// `ip saddr vmap { 10.0.0.1 : accept }`. See [`Rule::concat_vmap`] for a rule applying the
+/// verdicts of such a map.
+///
+/// [`Rule::concat_vmap`]: ../struct.Rule.html#method.concat_vmap
+pub struct VerdictMapBuilder<K: DataType> {
+    inner: SetBuilder<K>,
+}
+
+impl<K: DataType> VerdictMapBuilder<K> {
+    pub fn new(name: impl Into<String>, table: &Table) -> Result<Self, BuilderError> {
+        Ok(Self::from_set_builder(SetBuilder::new(name, table)?))
+    }
+
+    /// Creates a new anonymous verdict map. As for anonymous sets (see
+    /// [`SetBuilder::anonymous`]), the map must be sent in the same batch as the rule that
+    /// references it.
+    ///
+    /// [`SetBuilder::anonymous`]: struct.SetBuilder.html#method.anonymous
+    pub fn anonymous(table: &Table) -> Result<Self, BuilderError> {
+        Ok(Self::from_set_builder(SetBuilder::anonymous(table)?))
+    }
+
+    fn from_set_builder(mut inner: SetBuilder<K>) -> Self {
+        let flags = inner.inner.get_flags().copied().unwrap_or(0);
+        inner.inner.set_flags(flags | NFT_SET_MAP);
+        // the kernel knows the length of the verdicts
+        inner.inner.set_data_type(NFT_DATA_VERDICT);
+        VerdictMapBuilder { inner }
+    }
+
+    pub fn add(&mut self, key: &K, verdict: VerdictKind) {
+        self.inner.list.elements.as_mut().unwrap().add_value(
+            SetElement::default()
+                .with_key(NfNetlinkData::default().with_value(key.data()))
+                .with_data(NfNetlinkData::default().with_verdict(Verdict::from(verdict))),
+        );
+    }
+
+    pub fn finish(self) -> (Set, SetElementList) {
+        self.inner.finish()
+    }
+}
+
+/// Returns the key following `key`, when keys are compared as big-endian numbers, or `None` if
+/// `key` is the largest one.
+fn next_key(mut key: Vec<u8>) -> Option<Vec<u8>> {
+    for byte in key.iter_mut().rev() {
+        let (res, overflow) = byte.overflowing_add(1);
+        *byte = res;
+        if !overflow {
+            return Some(key);
+        }
+    }
+    None
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 #[nfnetlink_struct(nested = true, derive_deserialize = false)]
 pub struct SetElementList {
+    pub family: ProtocolFamily,
     #[field(NFTA_SET_ELEM_LIST_TABLE)]
     pub table: String,
     #[field(NFTA_SET_ELEM_LIST_SET)]
     pub set: String,
+    #[field(NFTA_SET_ELEM_LIST_SET_ID)]
+    pub set_id: u32,
     #[field(NFTA_SET_ELEM_LIST_ELEMENTS)]
     pub elements: SetElementListElements,
 }
 
+impl SetElementList {
+    /// Splits `elements` of `set` into as few lists as possible, so that the
+    /// NFTA_SET_ELEM_LIST_ELEMENTS attribute of each of them fits within the 16 bits of its
+    /// nla_len.
+    pub(crate) fn split(set: &Set, elements: &[SetElement]) -> Vec<SetElementList> {
+        let new_list = || SetElementList {
+            family: set.family,
+            table: set.table.clone(),
+            set: set.name.clone(),
+            set_id: set.id,
+            elements: Some(SetElementListElements::default()),
+        };
+        let max_size = u16::MAX as usize - pad_netlink_object::<nlattr>();
+
+        let mut res = Vec::new();
+        let mut list = new_list();
+        let mut size = 0;
+        for elem in elements {
+            let elem_size = elem.get_size() + pad_netlink_object::<nlattr>();
+            if size + elem_size > max_size && size != 0 {
+                res.push(std::mem::replace(&mut list, new_list()));
+                size = 0;
+            }
+            list.elements.as_mut().unwrap().add_value(elem.clone());
+            size += elem_size;
+        }
+        if size != 0 {
+            res.push(list);
+        }
+        res
+    }
+}
+
 impl NfNetlinkObject for SetElementList {
     const MSG_TYPE_ADD: u32 = NFT_MSG_NEWSETELEM;
     const MSG_TYPE_DEL: u32 = NFT_MSG_DELSETELEM;
 
     fn get_family(&self) -> ProtocolFamily {
-        ProtocolFamily::Unspec
+        self.family
+    }
+
+    fn set_family(&mut self, family: ProtocolFamily) {
+        self.family = family;
     }
 }
 
@@ -111,6 +410,42 @@ impl NfNetlinkObject for SetElementList {
 pub struct SetElement {
     #[field(NFTA_SET_ELEM_KEY)]
     pub key: NfNetlinkData,
+    /// The value associated to the key, for the elements of a map.
+    #[field(NFTA_SET_ELEM_DATA)]
+    pub data: NfNetlinkData,
+    /// `NFT_SET_ELEM_INTERVAL_END` for the elements ending a range of an interval set.
+    #[field(NFTA_SET_ELEM_FLAGS)]
+    pub flags: u32,
+    /// The time in milliseconds after which the element is removed, for the sets with timeouts.
+    #[field(NFTA_SET_ELEM_TIMEOUT)]
+    pub timeout: u64,
+    /// The time in milliseconds left before the element is removed, only available on elements
+    /// retrieved from the kernel.
+    #[field(NFTA_SET_ELEM_EXPIRATION)]
+    pub expiration: u64,
+    #[field(NFTA_SET_ELEM_USERDATA)]
+    pub userdata: Vec<u8>,
+}
+
+impl SetElement {
+    /// Annotates the element with `comment`, which is stored in its userdata in the same
+    /// format as `nft add element ... { 10.0.0.1 comment "..." }`, so both tools can read it
+    /// back. Returns `BuilderError::InvalidComment` if the comment is more than 127 bytes long
+    /// or contains a null byte.
+    pub fn set_comment(&mut self, comment: &str) -> Result<(), BuilderError> {
+        self.set_userdata(encode_comment_userdata(comment)?);
+        Ok(())
+    }
+
+    pub fn with_comment(mut self, comment: &str) -> Result<Self, BuilderError> {
+        self.set_comment(comment)?;
+        Ok(self)
+    }
+
+    /// Returns the comment of the element, if its userdata holds one.
+    pub fn get_comment(&self) -> Option<String> {
+        decode_comment_userdata(self.get_userdata()?)
+    }
 }
 
 type SetElementListElements = NfNetlinkList<SetElement>;
@@ -2,10 +2,11 @@ use std::fmt::Debug;
 
 use rustables_macros::nfnetlink_struct;
 
-use crate::error::QueryError;
+use crate::error::{BuilderError, QueryError};
 use crate::nlmsg::NfNetlinkObject;
 use crate::sys::{
-    NFTA_TABLE_FLAGS, NFTA_TABLE_NAME, NFT_MSG_DELTABLE, NFT_MSG_GETTABLE, NFT_MSG_NEWTABLE,
+    NFTA_TABLE_FLAGS, NFTA_TABLE_HANDLE, NFTA_TABLE_NAME, NFT_MSG_DELTABLE, NFT_MSG_GETTABLE,
+    NFT_MSG_NEWTABLE, NFT_NAME_MAXLEN,
 };
 use crate::{Batch, ProtocolFamily};
 
@@ -21,6 +22,9 @@ pub struct Table {
     name: String,
     #[field(NFTA_TABLE_FLAGS)]
     flags: u32,
+    /// Identifier assigned by the kernel, only available on tables retrieved from the kernel.
+    #[field(NFTA_TABLE_HANDLE)]
+    handle: u64,
     #[field(optional = true, crate::sys::NFTA_TABLE_USERDATA)]
     userdata: Vec<u8>,
 }
@@ -32,6 +36,16 @@ impl Table {
         res
     }
 
+    /// Creates a new table named `name`. Unlike [`Table::with_name`], this checks that the name
+    /// fits in the limits of nftables, and returns `BuilderError::NameTooLong` otherwise.
+    ///
+    /// [`Table::with_name`]: struct.Table.html#method.with_name
+    pub fn named(family: ProtocolFamily, name: impl Into<String>) -> Result<Table, BuilderError> {
+        let name = name.into();
+        validate_name(&name)?;
+        Ok(Table::new(family).with_name(name))
+    }
+
     /// Appends this rule to `batch`
     pub fn add_to_batch(self, batch: &mut Batch) -> Self {
         batch.add(&self, crate::MsgType::Add);
@@ -39,6 +53,15 @@ impl Table {
     }
 }
 
+/// Checks that `name` fits in the names of the tables, chains, sets and objects, which the kernel
+/// rejects when they are longer than `NFT_NAME_MAXLEN` bytes, null terminator included.
+pub(crate) fn validate_name(name: &str) -> Result<(), BuilderError> {
+    if name.len() >= NFT_NAME_MAXLEN as usize {
+        return Err(BuilderError::NameTooLong);
+    }
+    Ok(())
+}
+
 impl NfNetlinkObject for Table {
     const MSG_TYPE_ADD: u32 = NFT_MSG_NEWTABLE;
     const MSG_TYPE_DEL: u32 = NFT_MSG_DELTABLE;
@@ -1,14 +1,30 @@
 use std::mem::size_of;
+use std::net::Ipv4Addr;
 
 use libc::{AF_UNSPEC, NFNL_MSG_BATCH_BEGIN, NLM_F_REQUEST};
 use nix::libc::NFNL_MSG_BATCH_END;
 
-use crate::nlmsg::{pad_netlink_object_with_variable_size, NfNetlinkDeserializable};
-use crate::parser::{parse_nlmsg, NlMsg};
-use crate::sys::{nfgenmsg, nlmsghdr, NFNETLINK_V0, NFNL_SUBSYS_NFTABLES};
-use crate::{Batch, MsgType, Table};
+use crate::batch::{get_echoed_handle, get_echoed_rule};
+use crate::error::BuilderError;
+use crate::expr::ConnTrackState;
+use crate::nlmsg::{
+    pad_netlink_object, pad_netlink_object_with_variable_size, NfNetlinkDeserializable,
+    NfNetlinkObject, NfNetlinkWriter,
+};
+use crate::parser::{get_nlmsghdr, parse_nlmsg, write_attribute, NlMsg};
+use crate::parser_impls::NfNetlinkData;
+use crate::query::SocketOptions;
+use crate::set::SetElement;
+use crate::sys::{
+    nfgenmsg, nlattr, nlmsgerr, nlmsghdr, NFNETLINK_V0, NFNL_SUBSYS_NFTABLES, NLMSGERR_ATTR_MSG,
+    NLMSG_ERROR, NLM_F_ACK_TLVS, NLM_F_CAPPED, NLM_F_ECHO,
+};
+use crate::{
+    Batch, BatchObjectKind, Chain, ChainType, Hook, HookClass, MsgType, Protocol, ProtocolFamily,
+    Rule, Table,
+};
 
-use super::get_test_table;
+use super::{get_test_chain, get_test_rule, get_test_set, get_test_table};
 
 const HEADER_SIZE: u32 =
     pad_netlink_object_with_variable_size(size_of::<nlmsghdr>() + size_of::<nfgenmsg>()) as u32;
@@ -94,3 +110,413 @@ fn batch_with_objects() {
     assert_eq!(hdr, end_hdr);
     assert_eq!(msg, DEFAULT_BATCH_MSG);
 }
+
+#[test]
+fn batch_operations() {
+    let mut batch = Batch::new();
+    batch.add(&get_test_table(), MsgType::Add);
+    batch.add(&get_test_chain(), MsgType::Add);
+    batch.add(&get_test_rule().accept(), MsgType::Add);
+    batch.add(&get_test_rule().accept(), MsgType::Create);
+    batch.replace(&get_test_rule().drop(), 42);
+    batch.add(&get_test_table(), MsgType::Del);
+
+    let operations = batch.operations().expect("Couldn't parse the batch");
+    assert_eq!(
+        operations
+            .iter()
+            .map(|op| (op.msg_type, op.kind))
+            .collect::<Vec<_>>(),
+        vec![
+            (MsgType::Add, BatchObjectKind::Table),
+            (MsgType::Add, BatchObjectKind::Chain),
+            (MsgType::Add, BatchObjectKind::Rule),
+            (MsgType::Create, BatchObjectKind::Rule),
+            (MsgType::Replace, BatchObjectKind::Rule),
+            (MsgType::Del, BatchObjectKind::Table),
+        ]
+    );
+    assert_eq!(
+        operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "add table mocktable",
+            "add chain mocktable mockchain",
+            "add rule mocktable mockchain",
+            "create rule mocktable mockchain",
+            "replace rule mocktable mockchain handle 42",
+            "delete table mocktable",
+        ]
+    );
+}
+
+#[test]
+fn delete_table_batch() {
+    let mut batch = Batch::new();
+    batch.delete_table(&get_test_table());
+
+    let operations = batch.operations().expect("Couldn't parse the batch");
+    assert_eq!(
+        operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect::<Vec<_>>(),
+        vec!["add table mocktable", "delete table mocktable"]
+    );
+}
+
+#[test]
+fn finalized_batch_size() {
+    let finalized = Batch::new().finalize();
+    assert_eq!(finalized.message_count(), 2);
+    assert_eq!(
+        finalized.total_len(),
+        2 * pad_netlink_object_with_variable_size(HEADER_SIZE as usize)
+    );
+
+    let mut batch = Batch::new();
+    for _ in 0..10 {
+        batch.add(&get_test_table(), MsgType::Add);
+    }
+    let finalized = batch.finalize();
+    assert_eq!(finalized.message_count(), 12);
+    assert_eq!(finalized.total_len(), finalized.as_bytes().len());
+}
+
+#[test]
+fn finalized_batch_messages() {
+    let mut batch = Batch::new();
+    batch.add(&get_test_table(), MsgType::Add);
+    let finalized = batch.finalize();
+
+    let messages: Vec<&[u8]> = finalized.iter().collect();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(
+        messages.iter().map(|msg| msg.len()).sum::<usize>(),
+        finalized.as_bytes().len()
+    );
+    assert_eq!(get_nlmsghdr(messages[0]).unwrap(), DEFAULT_BATCH_BEGIN_HDR);
+    let (table, _) = Table::deserialize(messages[1]).expect("could not deserialize a table");
+    assert_eq!(table, get_test_table());
+    assert_eq!(
+        get_nlmsghdr(messages[2]).unwrap(),
+        nlmsghdr {
+            nlmsg_seq: 2,
+            ..DEFAULT_BATCH_END_HDR
+        }
+    );
+}
+
+#[test]
+fn owned_table() {
+    let mut batch = Batch::new();
+    batch
+        .add_owned_table(
+            &get_test_table(),
+            &[get_test_chain()],
+            &[get_test_rule().accept(), get_test_rule().drop()],
+        )
+        .unwrap();
+
+    assert_eq!(
+        batch
+            .operations()
+            .expect("Couldn't parse the batch")
+            .iter()
+            .map(|op| op.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "add table mocktable",
+            "delete rule mocktable <unnamed>",
+            "add chain mocktable mockchain",
+            "add rule mocktable mockchain",
+            "add rule mocktable mockchain",
+        ]
+    );
+
+    let other_table = Table::new(ProtocolFamily::Ipv4).with_name("othertable");
+    assert!(matches!(
+        Batch::new().add_owned_table(&get_test_table(), &[Chain::new(&other_table)], &[]),
+        Err(BuilderError::ObjectOutsideOfTable)
+    ));
+
+    // masquerading does nothing in a filter chain
+    let filter = get_test_chain()
+        .with_type(ChainType::Filter)
+        .with_hook(Hook::new(HookClass::PostRouting, 0));
+    // the rule does not know its chain, which the batch checks instead
+    let masquerade = get_test_rule().masquerade().unwrap();
+    assert!(matches!(
+        Batch::new().add_owned_table(&get_test_table(), &[filter], &[masquerade]),
+        Err(BuilderError::InvalidChainForNat)
+    ));
+}
+
+#[test]
+fn error_with_ext_ack_message() {
+    let message = "Could not process rule: No such file or directory";
+    let attr_len = pad_netlink_object::<nlattr>() + message.len() + 1;
+    let len = size_of::<nlmsghdr>()
+        + size_of::<nlmsgerr>()
+        + pad_netlink_object_with_variable_size(attr_len);
+    let mut buf = vec![0u8; len];
+
+    let hdr = nlmsghdr {
+        nlmsg_len: len as u32,
+        nlmsg_type: NLMSG_ERROR as u16,
+        nlmsg_flags: (NLM_F_CAPPED | NLM_F_ACK_TLVS) as u16,
+        nlmsg_seq: 0,
+        nlmsg_pid: 0,
+    };
+    let err = nlmsgerr {
+        error: -libc::ENOENT,
+        msg: nlmsghdr {
+            nlmsg_len: 100,
+            ..hdr
+        },
+    };
+    unsafe {
+        *(buf.as_mut_ptr() as *mut nlmsghdr) = hdr;
+        *(buf[size_of::<nlmsghdr>()..].as_mut_ptr() as *mut nlmsgerr) = err;
+    }
+    let mut value = message.as_bytes().to_vec();
+    value.push(0);
+    write_attribute(
+        NLMSGERR_ATTR_MSG as u16,
+        &value,
+        &mut buf[size_of::<nlmsghdr>() + size_of::<nlmsgerr>()..],
+    );
+
+    let (_, msg) = parse_nlmsg(&buf).expect("Invalid nlmsg message");
+    match msg {
+        NlMsg::Error(err, msg) => {
+            assert_eq!(err.error, libc::ENOENT);
+            assert_eq!(msg.as_deref(), Some(message));
+        }
+        _ => panic!("Expected an error message"),
+    }
+}
+
+#[test]
+fn zero_errno_is_an_ack() {
+    let len = size_of::<nlmsghdr>() + size_of::<nlmsgerr>();
+    let mut buf = vec![0u8; len];
+
+    let hdr = nlmsghdr {
+        nlmsg_len: len as u32,
+        nlmsg_type: NLMSG_ERROR as u16,
+        nlmsg_flags: NLM_F_CAPPED as u16,
+        nlmsg_seq: 3,
+        nlmsg_pid: 0,
+    };
+    let err = nlmsgerr {
+        error: 0,
+        msg: nlmsghdr {
+            nlmsg_len: 100,
+            ..hdr
+        },
+    };
+    unsafe {
+        *(buf.as_mut_ptr() as *mut nlmsghdr) = hdr;
+        *(buf[size_of::<nlmsghdr>()..].as_mut_ptr() as *mut nlmsgerr) = err;
+    }
+
+    let (hdr, msg) = parse_nlmsg(&buf).expect("Invalid nlmsg message");
+    assert_eq!(hdr.nlmsg_seq, 3);
+    match msg {
+        NlMsg::Ack(err) => assert_eq!(err.error, 0),
+        msg => panic!("Expected an acknowledgement, got {:?}", msg),
+    }
+}
+
+#[test]
+fn echoed_handles() {
+    let mut batch = Batch::new();
+    batch.add(&get_test_table(), MsgType::Add);
+    let seq = batch.add_with_echo(&get_test_rule().accept(), MsgType::Add);
+    assert_eq!(seq, 2);
+    let finalized = batch.finalize();
+
+    let messages: Vec<&[u8]> = finalized.iter().collect();
+    assert_eq!(
+        get_nlmsghdr(messages[1]).unwrap().nlmsg_flags & NLM_F_ECHO as u16,
+        0
+    );
+    let hdr = get_nlmsghdr(messages[2]).unwrap();
+    assert_eq!(hdr.nlmsg_seq, seq);
+    assert_ne!(hdr.nlmsg_flags & NLM_F_ECHO as u16, 0);
+    // the object is left untouched
+    let (rule, _) = Rule::deserialize(messages[2]).expect("could not deserialize a rule");
+    assert_eq!(rule, get_test_rule().accept());
+
+    // the kernel echoes the rule with its handle, and the same sequence number
+    let mut buf = Vec::new();
+    let mut writer = NfNetlinkWriter::new(&mut buf);
+    get_test_rule()
+        .accept()
+        .with_handle(42u64)
+        .add_or_remove(&mut writer, MsgType::Add, seq);
+    assert_eq!(get_echoed_handle(&buf).unwrap(), (seq, Some(42)));
+
+    let mut buf = Vec::new();
+    let mut writer = NfNetlinkWriter::new(&mut buf);
+    get_test_table().add_or_remove(&mut writer, MsgType::Add, 1);
+    assert_eq!(get_echoed_handle(&buf).unwrap(), (1, None));
+    assert_eq!(get_echoed_rule(&buf).unwrap(), None);
+}
+
+#[test]
+fn echoed_rules() {
+    let rule = get_test_rule().accept().with_handle(42u64);
+    let mut buf = Vec::new();
+    let mut writer = NfNetlinkWriter::new(&mut buf);
+    rule.add_or_remove(&mut writer, MsgType::Add, 3);
+
+    let echoed = get_echoed_rule(&buf)
+        .unwrap()
+        .expect("Expected an echoed rule");
+    assert_eq!(echoed.get_handle(), Some(&42));
+    assert_eq!(echoed, rule);
+}
+
+#[test]
+fn bulk_set_elements() {
+    let set = get_test_set::<Ipv4Addr>();
+    let elements: Vec<SetElement> = (0..10000u32)
+        .map(|i| {
+            SetElement::default().with_key(NfNetlinkData::default().with_value(i.to_be_bytes()))
+        })
+        .collect();
+
+    let mut batch = Batch::new();
+    batch.add_elements(&set, &elements);
+    batch.remove_elements(&set, &elements[..10]);
+    batch.add_elements(&set, &[]);
+
+    // each element takes 16 bytes, so 4095 of them fit in the 64 KBytes of an attribute
+    let operations = batch.operations().expect("Couldn't parse the batch");
+    assert_eq!(
+        operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "add 4095 elements of set mocktable mockset",
+            "add 4095 elements of set mocktable mockset",
+            "add 1810 elements of set mocktable mockset",
+            "delete 10 elements of set mocktable mockset",
+        ]
+    );
+}
+
+#[test]
+fn replace_set_elements() {
+    let set = get_test_set::<Ipv4Addr>();
+    let elements: Vec<SetElement> = (0..5000u32)
+        .map(|i| {
+            SetElement::default().with_key(NfNetlinkData::default().with_value(i.to_be_bytes()))
+        })
+        .collect();
+
+    let mut batch = Batch::new();
+    batch.replace_elements(&set, &elements);
+
+    let operations = batch.operations().expect("Couldn't parse the batch");
+    assert_eq!(
+        operations
+            .iter()
+            .map(|op| op.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "delete all elements of set mocktable mockset",
+            "add 4095 elements of set mocktable mockset",
+            "add 905 elements of set mocktable mockset",
+        ]
+    );
+}
+
+#[test]
+fn socket_buffer_sizes() {
+    use nix::sys::socket::{self, sockopt, AddressFamily, SockFlag, SockType};
+
+    let (sock, other) = socket::socketpair(
+        AddressFamily::Unix,
+        SockType::Datagram,
+        None,
+        SockFlag::empty(),
+    )
+    .unwrap();
+    SocketOptions::new()
+        .with_recv_buffer_size(65536)
+        .with_send_buffer_size(65536)
+        .apply(sock)
+        .expect("Couldn't set the buffer sizes");
+
+    // the kernel doubles the requested sizes to account for its bookkeeping overhead
+    assert_eq!(
+        socket::getsockopt(sock, sockopt::RcvBuf).unwrap(),
+        2 * 65536
+    );
+    assert_eq!(
+        socket::getsockopt(sock, sockopt::SndBuf).unwrap(),
+        2 * 65536
+    );
+
+    nix::unistd::close(sock).unwrap();
+    nix::unistd::close(other).unwrap();
+}
+
+#[test]
+fn conntrack_preamble() {
+    let mut batch = Batch::new();
+    batch.add_conntrack_preamble(&get_test_chain()).unwrap();
+
+    let mut expected = Batch::new();
+    expected.add(
+        &get_test_rule()
+            .ct_states(ConnTrackState::ESTABLISHED | ConnTrackState::RELATED)
+            .unwrap()
+            .accept(),
+        MsgType::Add,
+    );
+    expected.add(
+        &get_test_rule()
+            .ct_states(ConnTrackState::INVALID)
+            .unwrap()
+            .drop(),
+        MsgType::Add,
+    );
+    assert_eq!(batch.finalize().as_bytes(), expected.finalize().as_bytes());
+}
+
+#[test]
+fn allow_ports_then_drop_rules() {
+    let mut batch = Batch::new();
+    batch
+        .allow_ports_then_drop(
+            &get_test_chain(),
+            &[(22, Protocol::TCP), (53, Protocol::UDP)],
+        )
+        .unwrap();
+
+    let mut expected = Batch::new();
+    expected.add(
+        &get_test_rule().dport(22, Protocol::TCP).accept(),
+        MsgType::Add,
+    );
+    expected.add(
+        &get_test_rule().dport(53, Protocol::UDP).accept(),
+        MsgType::Add,
+    );
+    expected.add(&get_test_rule().drop(), MsgType::Add);
+    assert_eq!(batch.finalize().as_bytes(), expected.finalize().as_bytes());
+
+    let mut batch = Batch::new();
+    assert!(matches!(
+        batch.allow_ports_then_drop(&get_test_chain(), &[]),
+        Err(BuilderError::EmptyServiceList)
+    ));
+    assert!(batch.operations().unwrap().is_empty());
+}
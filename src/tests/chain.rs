@@ -1,10 +1,13 @@
 use crate::{
-    nlmsg::get_operation_from_nlmsghdr_type,
+    error::BuilderError,
+    nlmsg::{get_operation_from_nlmsghdr_type, NfNetlinkDeserializable},
     sys::{
-        NFTA_CHAIN_HOOK, NFTA_CHAIN_NAME, NFTA_CHAIN_TABLE, NFTA_CHAIN_TYPE, NFTA_CHAIN_USERDATA,
-        NFTA_HOOK_HOOKNUM, NFTA_HOOK_PRIORITY, NFT_MSG_DELCHAIN, NFT_MSG_NEWCHAIN,
+        NFTA_CHAIN_HANDLE, NFTA_CHAIN_HOOK, NFTA_CHAIN_NAME, NFTA_CHAIN_TABLE, NFTA_CHAIN_TYPE,
+        NFTA_CHAIN_USERDATA, NFTA_DEVICE_NAME, NFTA_HOOK_DEVS, NFTA_HOOK_HOOKNUM,
+        NFTA_HOOK_PRIORITY, NFT_MSG_DELCHAIN, NFT_MSG_NEWCHAIN, NF_NETDEV_INGRESS,
     },
-    ChainType, Hook, HookClass, MsgType,
+    Chain, ChainPolicy, ChainType, Hook, HookClass, HookDevices, MsgType, NetDevHookClass,
+    ProtocolFamily, Table,
 };
 
 use super::{
@@ -118,3 +121,254 @@ fn delete_empty_chain() {
         .to_raw()
     );
 }
+
+#[test]
+fn rename_chain() {
+    let handle: u64 = 42;
+    assert!(matches!(
+        get_test_chain().rename("newchain"),
+        Err(BuilderError::MissingChainHandle)
+    ));
+
+    let mut chain = get_test_chain()
+        .with_handle(handle)
+        .rename("newchain")
+        .expect("Couldn't rename the chain");
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut chain);
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(nlmsghdr.nlmsg_type),
+        NFT_MSG_NEWCHAIN as u8
+    );
+    assert_eq!(nlmsghdr.nlmsg_len, 60);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_CHAIN_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_CHAIN_HANDLE, handle.to_be_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_CHAIN_NAME, b"newchain".to_vec()),
+        ])
+        .to_raw()
+    );
+}
+
+#[test]
+fn parse_chain_with_handle() {
+    let mut chain = get_test_chain().with_handle(42u64);
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut chain);
+
+    let (deserialized_chain, _) =
+        Chain::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(deserialized_chain, chain);
+    assert_eq!(deserialized_chain.get_handle(), Some(&42));
+}
+
+#[test]
+fn parse_base_chain() {
+    // the kernel also sends the number of references to the chain
+    let mut chain = get_test_chain()
+        .with_handle(42u64)
+        .with_hook(Hook::new(HookClass::Forward, -100))
+        .with_policy(ChainPolicy::Drop)
+        .with_type(ChainType::Nat)
+        .with_use_count(2u32);
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut chain);
+
+    let (deserialized_chain, _) =
+        Chain::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(deserialized_chain, chain);
+    assert!(deserialized_chain.is_base_chain());
+    assert_eq!(
+        deserialized_chain.get_hook().unwrap().get_class(),
+        Some(&(HookClass::Forward as u32))
+    );
+    assert_eq!(deserialized_chain.get_priority(), Some(-100));
+    assert_eq!(deserialized_chain.get_policy(), Some(&ChainPolicy::Drop));
+    assert_eq!(deserialized_chain.get_type(), Some(&ChainType::Nat));
+
+    let chain = get_test_chain();
+    assert!(!chain.is_base_chain());
+    assert_eq!(chain.get_priority(), None);
+}
+
+#[test]
+fn new_netdev_chain_with_devices() {
+    let mut chain = get_test_chain()
+        .with_hook_device(NetDevHookClass::Ingress, 0, &["eth0", "eth1"])
+        .unwrap();
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut chain);
+    assert_eq!(nlmsghdr.nlmsg_len, 92);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_CHAIN_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_CHAIN_NAME, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_CHAIN_HOOK,
+                vec![
+                    NetlinkExpr::Final(NFTA_HOOK_HOOKNUM, NF_NETDEV_INGRESS.to_be_bytes().to_vec()),
+                    NetlinkExpr::Final(NFTA_HOOK_PRIORITY, vec![0, 0, 0, 0]),
+                    NetlinkExpr::Nested(
+                        NFTA_HOOK_DEVS,
+                        vec![
+                            NetlinkExpr::Final(NFTA_DEVICE_NAME, b"eth0".to_vec()),
+                            NetlinkExpr::Final(NFTA_DEVICE_NAME, b"eth1".to_vec()),
+                        ]
+                    ),
+                ]
+            ),
+        ])
+        .to_raw()
+    );
+
+    let (deserialized_chain, _) = Chain::deserialize(&buf).unwrap();
+    assert_eq!(
+        deserialized_chain
+            .get_hook()
+            .and_then(|hook| hook.get_devices()),
+        Some(&HookDevices(vec!["eth0".to_string(), "eth1".to_string()]))
+    );
+}
+
+#[test]
+fn netdev_chain_device_validation() {
+    let hook = Hook::new_netdev(NetDevHookClass::Ingress, 0, &["eth0"]).unwrap();
+    assert_eq!(hook.get_device(), Some(&"eth0".to_string()));
+    assert_eq!(hook.get_devices(), None);
+
+    assert!(matches!(
+        Hook::new_netdev(NetDevHookClass::Ingress, 0, &[]),
+        Err(BuilderError::MissingHookDevice)
+    ));
+    assert!(matches!(
+        get_test_chain().with_hook_device(NetDevHookClass::Egress, 0, &["a_very_long_device_name"]),
+        Err(BuilderError::InterfaceNameTooLong)
+    ));
+}
+
+#[test]
+fn base_hooks_per_family() {
+    let table = Table::new(ProtocolFamily::Bridge).with_name(TABLE_NAME);
+    let chain = Chain::new(&table)
+        .with_name(CHAIN_NAME)
+        .with_base_hook(HookClass::PreRouting, -300)
+        .unwrap();
+    assert_eq!(
+        chain.get_hook(),
+        Some(&Hook::new(HookClass::PreRouting, -300))
+    );
+
+    let table = Table::new(ProtocolFamily::Arp).with_name(TABLE_NAME);
+    let chain = Chain::new(&table)
+        .with_name(CHAIN_NAME)
+        .with_base_hook(HookClass::Out, 0)
+        .unwrap();
+    assert_eq!(
+        chain.get_hook().unwrap().get_class(),
+        Some(&(libc::NF_ARP_OUT as u32))
+    );
+    assert!(matches!(
+        Chain::new(&table).with_base_hook(HookClass::Forward, 0),
+        Err(BuilderError::InvalidHook)
+    ));
+
+    let table = Table::new(ProtocolFamily::NetDev).with_name(TABLE_NAME);
+    assert!(matches!(
+        Chain::new(&table).with_base_hook(HookClass::In, 0),
+        Err(BuilderError::InvalidHook)
+    ));
+
+    for class in [
+        HookClass::PreRouting,
+        HookClass::In,
+        HookClass::Forward,
+        HookClass::Out,
+        HookClass::PostRouting,
+    ] {
+        assert_eq!(class.hook_num(ProtocolFamily::Inet), Some(class as u32));
+        assert_eq!(class.hook_num(ProtocolFamily::Bridge), Some(class as u32));
+    }
+}
+
+#[test]
+fn new_base_chain() {
+    let table = Table::new(ProtocolFamily::Inet).with_name(TABLE_NAME);
+    let chain = Chain::new_base(
+        &table,
+        CHAIN_NAME,
+        HookClass::PostRouting,
+        100,
+        ChainType::Nat,
+        ChainPolicy::Accept,
+    )
+    .expect("Couldn't create a base chain");
+    assert_eq!(
+        chain,
+        Chain::new(&table)
+            .with_name(CHAIN_NAME)
+            .with_hook(Hook::new(HookClass::PostRouting, 100))
+            .with_type(ChainType::Nat)
+            .with_policy(ChainPolicy::Accept)
+    );
+
+    assert!(matches!(
+        Chain::new_base(
+            &table,
+            CHAIN_NAME,
+            HookClass::Forward,
+            0,
+            ChainType::Nat,
+            ChainPolicy::Accept
+        ),
+        Err(BuilderError::InvalidHookForChainType)
+    ));
+    assert!(matches!(
+        Chain::new_base(
+            &table,
+            CHAIN_NAME,
+            HookClass::In,
+            0,
+            ChainType::Route,
+            ChainPolicy::Accept
+        ),
+        Err(BuilderError::InvalidHookForChainType)
+    ));
+    let bridge = Table::new(ProtocolFamily::Bridge).with_name(TABLE_NAME);
+    assert!(matches!(
+        Chain::new_base(
+            &bridge,
+            CHAIN_NAME,
+            HookClass::PreRouting,
+            0,
+            ChainType::Nat,
+            ChainPolicy::Accept
+        ),
+        Err(BuilderError::InvalidHookForChainType)
+    ));
+    assert!(Chain::new_base(
+        &bridge,
+        CHAIN_NAME,
+        HookClass::Forward,
+        0,
+        ChainType::Filter,
+        ChainPolicy::Drop
+    )
+    .is_ok());
+
+    // a regular chain has no policy
+    assert!(matches!(
+        Chain::new(&table)
+            .with_name(CHAIN_NAME)
+            .with_policy(ChainPolicy::Drop)
+            .validate(),
+        Err(BuilderError::MissingHookForChain)
+    ));
+    assert!(Chain::new(&table).with_name(CHAIN_NAME).validate().is_ok());
+}
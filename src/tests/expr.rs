@@ -1,28 +1,41 @@
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
 
 use libc::NF_DROP;
 
 use crate::{
+    error::BuilderError,
     expr::{
-        Bitwise, Cmp, CmpOp, Conntrack, ConntrackKey, Counter, ExpressionList, HeaderField,
-        HighLevelPayload, IcmpCode, Immediate, Log, Lookup, Masquerade, Meta, MetaType, Nat,
-        NatType, Register, Reject, RejectType, TCPHeaderField, TransportHeaderField, VerdictKind,
+        Bitwise, Cmp, CmpOp, ConnLimit, Conntrack, ConntrackDirection, ConntrackKey, Counter,
+        ExprKind, ExpressionList, Fib, FibResult, HeaderField, HighLevelPayload, IcmpCode,
+        Immediate, Last, Log, Lookup, Masquerade, Meta, MetaType, Nat, NatType, ObjRef, Range,
+        RangeOp, RawExpression, Register, Reject, RejectType, Socket, TCPHeaderField,
+        TransportHeaderField, VerdictKind, VerdictType,
     },
+    nlmsg::{AttributeDecoder, NfNetlinkAttribute, NfNetlinkDeserializable},
     set::SetBuilder,
     sys::{
-        NFTA_BITWISE_DREG, NFTA_BITWISE_LEN, NFTA_BITWISE_MASK, NFTA_BITWISE_SREG,
-        NFTA_BITWISE_XOR, NFTA_CMP_DATA, NFTA_CMP_OP, NFTA_CMP_SREG, NFTA_COUNTER_BYTES,
-        NFTA_COUNTER_PACKETS, NFTA_CT_DREG, NFTA_CT_KEY, NFTA_DATA_VALUE, NFTA_DATA_VERDICT,
-        NFTA_EXPR_DATA, NFTA_EXPR_NAME, NFTA_IMMEDIATE_DATA, NFTA_IMMEDIATE_DREG, NFTA_LIST_ELEM,
+        IP_CT_DIR_REPLY, NFTA_BITWISE_DREG, NFTA_BITWISE_LEN, NFTA_BITWISE_MASK, NFTA_BITWISE_SREG,
+        NFTA_BITWISE_XOR, NFTA_CMP_DATA, NFTA_CMP_OP, NFTA_CMP_SREG, NFTA_CONNLIMIT_COUNT,
+        NFTA_CONNLIMIT_FLAGS, NFTA_COUNTER_BYTES, NFTA_COUNTER_PACKETS, NFTA_CT_DIRECTION,
+        NFTA_CT_DREG, NFTA_CT_KEY, NFTA_DATA_VALUE, NFTA_DATA_VERDICT, NFTA_EXPR_DATA,
+        NFTA_EXPR_NAME, NFTA_FIB_DREG, NFTA_FIB_FLAGS, NFTA_FIB_F_DADDR, NFTA_FIB_RESULT,
+        NFTA_IMMEDIATE_DATA, NFTA_IMMEDIATE_DREG, NFTA_LAST_MSECS, NFTA_LAST_SET, NFTA_LIST_ELEM,
         NFTA_LOG_GROUP, NFTA_LOG_PREFIX, NFTA_LOOKUP_SET, NFTA_LOOKUP_SREG, NFTA_META_DREG,
-        NFTA_META_KEY, NFTA_NAT_FAMILY, NFTA_NAT_REG_ADDR_MIN, NFTA_NAT_TYPE, NFTA_PAYLOAD_BASE,
-        NFTA_PAYLOAD_DREG, NFTA_PAYLOAD_LEN, NFTA_PAYLOAD_OFFSET, NFTA_REJECT_ICMP_CODE,
-        NFTA_REJECT_TYPE, NFTA_RULE_CHAIN, NFTA_RULE_EXPRESSIONS, NFTA_RULE_TABLE,
-        NFTA_VERDICT_CODE, NFT_CMP_EQ, NFT_CT_STATE, NFT_META_PROTOCOL, NFT_NAT_SNAT,
-        NFT_PAYLOAD_TRANSPORT_HEADER, NFT_REG_1, NFT_REG_VERDICT, NFT_REJECT_ICMPX_UNREACH,
+        NFTA_META_KEY, NFTA_NAT_FAMILY, NFTA_NAT_FLAGS, NFTA_NAT_REG_ADDR_MAX,
+        NFTA_NAT_REG_ADDR_MIN, NFTA_NAT_REG_PROTO_MAX, NFTA_NAT_REG_PROTO_MIN, NFTA_NAT_TYPE,
+        NFTA_OBJREF_IMM_NAME, NFTA_OBJREF_IMM_TYPE, NFTA_PAYLOAD_BASE, NFTA_PAYLOAD_DREG,
+        NFTA_PAYLOAD_LEN, NFTA_PAYLOAD_OFFSET, NFTA_RANGE_FROM_DATA, NFTA_RANGE_OP,
+        NFTA_RANGE_SREG, NFTA_RANGE_TO_DATA, NFTA_REJECT_ICMP_CODE, NFTA_REJECT_TYPE,
+        NFTA_RULE_CHAIN, NFTA_RULE_EXPRESSIONS, NFTA_RULE_TABLE, NFTA_SOCKET_DREG, NFTA_SOCKET_KEY,
+        NFTA_SOCKET_LEVEL, NFTA_VERDICT_CHAIN, NFTA_VERDICT_CODE, NFT_CMP_EQ, NFT_CONNLIMIT_F_INV,
+        NFT_CT_DST_IP, NFT_CT_STATE, NFT_FIB_RESULT_ADDRTYPE, NFT_JUMP, NFT_META_PROTOCOL,
+        NFT_NAT_DNAT, NFT_NAT_SNAT, NFT_OBJECT_CT_HELPER, NFT_PAYLOAD_TRANSPORT_HEADER,
+        NFT_RANGE_NEQ, NFT_REG_1, NFT_REG_2, NFT_REG_3, NFT_REG_4, NFT_REG_VERDICT,
+        NFT_REJECT_ICMPX_UNREACH, NFT_SOCKET_CGROUPV2, NF_NAT_RANGE_PROTO_SPECIFIED,
     },
     tests::{get_test_table, SET_NAME},
-    ProtocolFamily,
+    ObjectType, ProtocolFamily, Rule,
 };
 
 use super::{get_test_nlmsg, get_test_rule, NetlinkExpr, CHAIN_NAME, TABLE_NAME};
@@ -84,6 +97,33 @@ fn bitwise_expr_is_valid() {
     );
 }
 
+#[test]
+fn bitwise_boolean_constructors() {
+    fn mask_and_xor(bitwise: &Bitwise) -> (Vec<u8>, Vec<u8>) {
+        (
+            bitwise.get_mask().unwrap().get_value().unwrap().clone(),
+            bitwise.get_xor().unwrap().get_value().unwrap().clone(),
+        )
+    }
+
+    let and = Bitwise::and([0xf0u8, 0x0f]);
+    assert_eq!(and.get_len(), Some(&2));
+    assert_eq!(mask_and_xor(&and), (vec![0xf0, 0x0f], vec![0x00, 0x00]));
+
+    let or = Bitwise::or([0xf0u8, 0x01]);
+    assert_eq!(or.get_len(), Some(&2));
+    assert_eq!(mask_and_xor(&or), (vec![0x0f, 0xfe], vec![0xf0, 0x01]));
+
+    let xor = Bitwise::xor([0xf0u8, 0x01]);
+    assert_eq!(xor.get_len(), Some(&2));
+    assert_eq!(mask_and_xor(&xor), (vec![0xff, 0xff], vec![0xf0, 0x01]));
+
+    assert_eq!(
+        Bitwise::and([0xf0u8, 0x0f]),
+        Bitwise::new([0xf0u8, 0x0f], [0u8, 0]).unwrap()
+    );
+}
+
 #[test]
 fn cmp_expr_is_valid() {
     let val = [1u8, 2, 3, 4];
@@ -124,6 +164,23 @@ fn cmp_expr_is_valid() {
     );
 }
 
+#[test]
+fn cmp_network_order_constructors() {
+    assert_eq!(Cmp::eq_port(22), Cmp::new(CmpOp::Eq, [0u8, 22]));
+    assert_eq!(
+        Cmp::eq_be(0x01020304u32),
+        Cmp::new(CmpOp::Eq, [1u8, 2, 3, 4])
+    );
+    assert_eq!(
+        Cmp::eq_ip(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))),
+        Cmp::new(CmpOp::Eq, [10u8, 0, 0, 1])
+    );
+    assert_eq!(
+        Cmp::eq_ip(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+        Cmp::new(CmpOp::Eq, Ipv6Addr::LOCALHOST.octets())
+    );
+}
+
 #[test]
 fn counter_expr_is_valid() {
     let nb_bytes = 123456u64;
@@ -170,6 +227,114 @@ fn counter_expr_is_valid() {
     );
 }
 
+#[test]
+fn last_expr_is_valid() {
+    let last = Last::default().with_set(1u32).with_msecs(1500u64);
+    let mut rule = get_test_rule().with_expressions(vec![last]);
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(nlmsghdr.nlmsg_len, 92);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![NetlinkExpr::Nested(
+                    NFTA_LIST_ELEM,
+                    vec![
+                        NetlinkExpr::Final(NFTA_EXPR_NAME, b"last".to_vec()),
+                        NetlinkExpr::Nested(
+                            NFTA_EXPR_DATA,
+                            vec![
+                                NetlinkExpr::Final(NFTA_LAST_SET, 1u32.to_be_bytes().to_vec()),
+                                NetlinkExpr::Final(NFTA_LAST_MSECS, 1500u64.to_be_bytes().to_vec()),
+                            ]
+                        )
+                    ]
+                )]
+            )
+        ])
+        .to_raw()
+    );
+
+    // the elapsed time is read back from the rules returned by the kernel
+    let (rule, _) = Rule::deserialize(&buf).expect("Couldn't parse the rule");
+    let last = rule.get_expr(0).unwrap().decode_expr::<Last>().unwrap();
+    assert_eq!(last.elapsed(), Some(Duration::from_millis(1500)));
+    assert_eq!(Last::default().elapsed(), None);
+    // the rule never matched
+    assert_eq!(Last::default().with_set(0u32).elapsed(), None);
+}
+
+#[test]
+fn connlimit_expr_is_valid() {
+    let connlimit = ConnLimit::new(100, true);
+    let mut rule = get_test_rule().with_expressions(vec![connlimit]);
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(nlmsghdr.nlmsg_len, 96);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![NetlinkExpr::Nested(
+                    NFTA_LIST_ELEM,
+                    vec![
+                        NetlinkExpr::Final(NFTA_EXPR_NAME, b"connlimit".to_vec()),
+                        NetlinkExpr::Nested(
+                            NFTA_EXPR_DATA,
+                            vec![
+                                NetlinkExpr::Final(
+                                    NFTA_CONNLIMIT_COUNT,
+                                    100u32.to_be_bytes().to_vec()
+                                ),
+                                NetlinkExpr::Final(
+                                    NFTA_CONNLIMIT_FLAGS,
+                                    NFT_CONNLIMIT_F_INV.to_be_bytes().to_vec()
+                                ),
+                            ]
+                        )
+                    ]
+                )]
+            )
+        ])
+        .to_raw()
+    );
+}
+
+#[test]
+fn counter_expr_is_decoded() {
+    // counter expression as returned by the kernel when listing rules
+    let raw_expr = NetlinkExpr::List(vec![
+        NetlinkExpr::Final(NFTA_EXPR_NAME, b"counter".to_vec()),
+        NetlinkExpr::Nested(
+            NFTA_EXPR_DATA,
+            vec![
+                NetlinkExpr::Final(NFTA_COUNTER_BYTES, 123456u64.to_be_bytes().to_vec()),
+                NetlinkExpr::Final(NFTA_COUNTER_PACKETS, 987u64.to_be_bytes().to_vec()),
+            ],
+        ),
+    ])
+    .to_raw();
+
+    let expr = RawExpression::from_bytes(&raw_expr).expect("Couldn't parse the expression");
+    let counter = expr
+        .decode_expr::<Counter>()
+        .expect("The expression is not a counter");
+    assert_eq!(counter.get_nb_bytes(), Some(&123456));
+    assert_eq!(counter.get_nb_packets(), Some(&987));
+    assert!(expr.decode_expr::<Log>().is_none());
+}
+
 #[test]
 fn ct_expr_is_valid() {
     let ct = Conntrack::default().with_retrieve_value(ConntrackKey::State);
@@ -208,6 +373,102 @@ fn ct_expr_is_valid() {
     )
 }
 
+#[test]
+fn ct_tuple_expr_is_valid() {
+    let ct = Conntrack::tuple(ConntrackKey::DstIp, ConntrackDirection::Reply);
+    let mut rule = get_test_rule().with_expressions(vec![ct]);
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(nlmsghdr.nlmsg_len, 96);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![NetlinkExpr::Nested(
+                    NFTA_LIST_ELEM,
+                    vec![
+                        NetlinkExpr::Final(NFTA_EXPR_NAME, b"ct".to_vec()),
+                        NetlinkExpr::Nested(
+                            NFTA_EXPR_DATA,
+                            vec![
+                                NetlinkExpr::Final(NFTA_CT_DREG, NFT_REG_1.to_be_bytes().to_vec()),
+                                NetlinkExpr::Final(
+                                    NFTA_CT_KEY,
+                                    NFT_CT_DST_IP.to_be_bytes().to_vec()
+                                ),
+                                NetlinkExpr::Final(NFTA_CT_DIRECTION, vec![IP_CT_DIR_REPLY as u8]),
+                            ]
+                        )
+                    ]
+                )]
+            )
+        ])
+        .to_raw()
+    )
+}
+
+#[test]
+fn fib_expr_is_valid() {
+    let fib = Fib::new(FibResult::AddrType, NFTA_FIB_F_DADDR);
+    let mut rule = get_test_rule().with_expressions(vec![fib]);
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(nlmsghdr.nlmsg_len, 96);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![NetlinkExpr::Nested(
+                    NFTA_LIST_ELEM,
+                    vec![
+                        NetlinkExpr::Final(NFTA_EXPR_NAME, b"fib".to_vec()),
+                        NetlinkExpr::Nested(
+                            NFTA_EXPR_DATA,
+                            vec![
+                                NetlinkExpr::Final(NFTA_FIB_DREG, NFT_REG_1.to_be_bytes().to_vec()),
+                                NetlinkExpr::Final(
+                                    NFTA_FIB_RESULT,
+                                    NFT_FIB_RESULT_ADDRTYPE.to_be_bytes().to_vec()
+                                ),
+                                NetlinkExpr::Final(
+                                    NFTA_FIB_FLAGS,
+                                    NFTA_FIB_F_DADDR.to_be_bytes().to_vec()
+                                ),
+                            ]
+                        )
+                    ]
+                )]
+            )
+        ])
+        .to_raw()
+    );
+}
+
+#[test]
+fn expr_kinds() {
+    let counter = RawExpression::from(Counter::default());
+    assert_eq!(counter.kind(), Some(ExprKind::Counter));
+    assert_eq!(RawExpression::default().kind(), None);
+
+    assert_eq!(ExprKind::from_name("payload"), ExprKind::Payload);
+    assert_eq!(ExprKind::from_name("ct"), ExprKind::Conntrack);
+    assert_eq!(
+        ExprKind::from_name("tproxy"),
+        ExprKind::Other("tproxy".to_string())
+    );
+    assert_eq!(ExprKind::Conntrack.name(), "ct");
+}
+
 #[test]
 fn immediate_expr_is_valid() {
     let immediate = Immediate::new_data(vec![42u8], Register::Reg1);
@@ -250,6 +511,49 @@ fn immediate_expr_is_valid() {
     );
 }
 
+#[test]
+fn immediate_ip_expr_is_valid() {
+    let ip = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+    let immediate = Immediate::new_ip(IpAddr::V6(ip), Register::Reg2);
+    let mut rule =
+        get_test_rule().with_expressions(ExpressionList::default().with_value(immediate));
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(nlmsghdr.nlmsg_len, 112);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![NetlinkExpr::Nested(
+                    NFTA_LIST_ELEM,
+                    vec![
+                        NetlinkExpr::Final(NFTA_EXPR_NAME, b"immediate".to_vec()),
+                        NetlinkExpr::Nested(
+                            NFTA_EXPR_DATA,
+                            vec![
+                                NetlinkExpr::Final(
+                                    NFTA_IMMEDIATE_DREG,
+                                    NFT_REG_2.to_be_bytes().to_vec()
+                                ),
+                                NetlinkExpr::Nested(
+                                    NFTA_IMMEDIATE_DATA,
+                                    vec![NetlinkExpr::Final(NFTA_DATA_VALUE, ip.octets().to_vec())]
+                                )
+                            ]
+                        )
+                    ]
+                )]
+            )
+        ])
+        .to_raw()
+    );
+}
+
 #[test]
 fn log_expr_is_valid() {
     let log = Log::new(Some(1337), Some("mockprefix")).expect("Could not build a log expression");
@@ -401,6 +705,35 @@ fn meta_expr_is_valid() {
     );
 }
 
+#[test]
+fn raw_expression_to_bytes() {
+    let meta = Meta::default()
+        .with_key(MetaType::Protocol)
+        .with_dreg(Register::Reg1);
+    let expr = RawExpression::from(meta.clone());
+
+    // the name and the content of the expression, without any rule around them
+    let bytes = expr.to_bytes();
+    assert_eq!(
+        bytes,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_EXPR_NAME, b"meta".to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_EXPR_DATA,
+                vec![
+                    NetlinkExpr::Final(NFTA_META_KEY, NFT_META_PROTOCOL.to_be_bytes().to_vec()),
+                    NetlinkExpr::Final(NFTA_META_DREG, NFT_REG_1.to_be_bytes().to_vec())
+                ]
+            )
+        ])
+        .to_raw()
+    );
+
+    let decoded = RawExpression::from_bytes(&bytes).expect("Couldn't parse the expression");
+    assert_eq!(decoded, expr);
+    assert_eq!(decoded.decode_expr::<Meta>(), Some(&meta));
+}
+
 #[test]
 fn nat_expr_is_valid() {
     let nat = Nat::default()
@@ -449,6 +782,95 @@ fn nat_expr_is_valid() {
     );
 }
 
+#[test]
+fn nat_expr_round_trip() {
+    let nat = Nat::default()
+        .with_nat_type(NatType::DNat)
+        .with_family(ProtocolFamily::Ipv6)
+        .with_ip_register(Register::Reg1)
+        .with_ip_register_max(Register::Reg2)
+        .with_port_register(Register::Reg3)
+        .with_port_register_max(Register::Reg4)
+        .with_flags(NF_NAT_RANGE_PROTO_SPECIFIED);
+
+    let bytes = nat.to_bytes();
+    assert_eq!(
+        bytes,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_NAT_TYPE, NFT_NAT_DNAT.to_be_bytes().to_vec()),
+            NetlinkExpr::Final(
+                NFTA_NAT_FAMILY,
+                (ProtocolFamily::Ipv6 as u32).to_be_bytes().to_vec(),
+            ),
+            NetlinkExpr::Final(NFTA_NAT_REG_ADDR_MIN, NFT_REG_1.to_be_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_NAT_REG_ADDR_MAX, NFT_REG_2.to_be_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_NAT_REG_PROTO_MIN, NFT_REG_3.to_be_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_NAT_REG_PROTO_MAX, NFT_REG_4.to_be_bytes().to_vec()),
+            NetlinkExpr::Final(
+                NFTA_NAT_FLAGS,
+                NF_NAT_RANGE_PROTO_SPECIFIED.to_be_bytes().to_vec()
+            ),
+        ])
+        .to_raw()
+    );
+    assert_eq!(
+        Nat::from_bytes(&bytes).expect("Couldn't parse the nat"),
+        nat
+    );
+
+    // and through a whole rule, as retrieved from the kernel
+    let mut rule = get_test_rule().with_expressions(vec![nat.clone()]);
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut rule);
+    let (deserialized, _) = Rule::deserialize(&buf).expect("Couldn't parse the rule");
+    assert_eq!(
+        deserialized
+            .get_expressions()
+            .and_then(|exprs| exprs.get(0))
+            .and_then(|expr| expr.decode_expr::<Nat>()),
+        Some(&nat)
+    );
+    assert_eq!(deserialized, rule);
+}
+
+#[test]
+fn objref_expr_is_valid() {
+    let objref = ObjRef::new(ObjectType::CtHelper, "mockhelper");
+    let mut rule = get_test_rule().with_expressions(vec![objref]);
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(nlmsghdr.nlmsg_len, 100);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![NetlinkExpr::Nested(
+                    NFTA_LIST_ELEM,
+                    vec![
+                        NetlinkExpr::Final(NFTA_EXPR_NAME, b"objref".to_vec()),
+                        NetlinkExpr::Nested(
+                            NFTA_EXPR_DATA,
+                            vec![
+                                NetlinkExpr::Final(
+                                    NFTA_OBJREF_IMM_TYPE,
+                                    NFT_OBJECT_CT_HELPER.to_be_bytes().to_vec()
+                                ),
+                                NetlinkExpr::Final(NFTA_OBJREF_IMM_NAME, b"mockhelper".to_vec()),
+                            ]
+                        )
+                    ]
+                )]
+            )
+        ])
+        .to_raw()
+    );
+}
+
 #[test]
 fn payload_expr_is_valid() {
     let tcp_header_field = TCPHeaderField::Sport;
@@ -500,12 +922,68 @@ fn payload_expr_is_valid() {
     );
 }
 
+#[test]
+fn range_expr_is_valid() {
+    let from = 1000u32.to_be_bytes();
+    let to = 1005u32.to_be_bytes();
+    let range = Range::new(RangeOp::Neq, from, to);
+    let mut rule = get_test_rule().with_expressions(vec![range]);
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(nlmsghdr.nlmsg_len, 116);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![NetlinkExpr::Nested(
+                    NFTA_LIST_ELEM,
+                    vec![
+                        NetlinkExpr::Final(NFTA_EXPR_NAME, b"range".to_vec()),
+                        NetlinkExpr::Nested(
+                            NFTA_EXPR_DATA,
+                            vec![
+                                NetlinkExpr::Final(
+                                    NFTA_RANGE_SREG,
+                                    NFT_REG_1.to_be_bytes().to_vec()
+                                ),
+                                NetlinkExpr::Final(
+                                    NFTA_RANGE_OP,
+                                    NFT_RANGE_NEQ.to_be_bytes().to_vec()
+                                ),
+                                NetlinkExpr::Nested(
+                                    NFTA_RANGE_FROM_DATA,
+                                    vec![NetlinkExpr::Final(NFTA_DATA_VALUE, from.to_vec())]
+                                ),
+                                NetlinkExpr::Nested(
+                                    NFTA_RANGE_TO_DATA,
+                                    vec![NetlinkExpr::Final(NFTA_DATA_VALUE, to.to_vec())]
+                                ),
+                            ]
+                        )
+                    ]
+                )]
+            )
+        ])
+        .to_raw()
+    );
+}
+
 #[test]
 fn reject_expr_is_valid() {
     let code = IcmpCode::NoRoute;
     let reject = Reject::default()
         .with_type(RejectType::IcmpxUnreach)
         .with_icmp_code(code);
+    // the code is stored as a raw u8, which converts back to an IcmpCode
+    assert!(matches!(
+        reject.get_icmp_code().map(|c| IcmpCode::try_from(*c)),
+        Some(Ok(IcmpCode::NoRoute))
+    ));
     let mut rule = get_test_rule().with_expressions(vec![reject]);
     let mut buf = Vec::new();
     let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
@@ -543,6 +1021,48 @@ fn reject_expr_is_valid() {
     );
 }
 
+#[test]
+fn socket_expr_is_valid() {
+    let socket = Socket::cgroupv2(2);
+    let mut rule = get_test_rule().with_expressions(vec![socket]);
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(nlmsghdr.nlmsg_len, 100);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_RULE_EXPRESSIONS,
+                vec![NetlinkExpr::Nested(
+                    NFTA_LIST_ELEM,
+                    vec![
+                        NetlinkExpr::Final(NFTA_EXPR_NAME, b"socket".to_vec()),
+                        NetlinkExpr::Nested(
+                            NFTA_EXPR_DATA,
+                            vec![
+                                NetlinkExpr::Final(
+                                    NFTA_SOCKET_KEY,
+                                    NFT_SOCKET_CGROUPV2.to_be_bytes().to_vec()
+                                ),
+                                NetlinkExpr::Final(
+                                    NFTA_SOCKET_DREG,
+                                    NFT_REG_1.to_be_bytes().to_vec()
+                                ),
+                                NetlinkExpr::Final(NFTA_SOCKET_LEVEL, 2u32.to_be_bytes().to_vec()),
+                            ]
+                        )
+                    ]
+                )]
+            )
+        ])
+        .to_raw()
+    );
+}
+
 #[test]
 fn verdict_expr_is_valid() {
     let verdict = Immediate::new_verdict(VerdictKind::Drop);
@@ -589,3 +1109,46 @@ fn verdict_expr_is_valid() {
         .to_raw()
     );
 }
+
+#[test]
+fn verdict_to_chain_expr_is_valid() {
+    let jump = Immediate::new_verdict_to_chain(VerdictType::Jump, "other-chain")
+        .expect("Couldn't create a jump");
+    assert_eq!(
+        jump,
+        Immediate::new_verdict(VerdictKind::Jump {
+            chain: "other-chain".to_string()
+        })
+    );
+    assert_eq!(
+        jump.to_bytes(),
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_IMMEDIATE_DREG, NFT_REG_VERDICT.to_be_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_IMMEDIATE_DATA,
+                vec![NetlinkExpr::Nested(
+                    NFTA_DATA_VERDICT,
+                    vec![
+                        NetlinkExpr::Final(NFTA_VERDICT_CODE, NFT_JUMP.to_be_bytes().to_vec()),
+                        NetlinkExpr::Final(NFTA_VERDICT_CHAIN, b"other-chain".to_vec()),
+                    ]
+                )]
+            ),
+        ])
+        .to_raw()
+    );
+
+    let goto = Immediate::new_verdict_to_chain(VerdictType::Goto, "other-chain")
+        .expect("Couldn't create a goto");
+    assert_eq!(
+        goto.get_data()
+            .and_then(|data| data.get_verdict())
+            .and_then(|verdict| verdict.get_code()),
+        Some(&VerdictType::Goto)
+    );
+
+    assert!(matches!(
+        Immediate::new_verdict_to_chain(VerdictType::Accept, "other-chain"),
+        Err(BuilderError::InvalidVerdictForChain)
+    ));
+}
@@ -0,0 +1,90 @@
+use crate::{
+    error::BuilderError,
+    expr::{ExpressionVariant, FlowOffload},
+    nlmsg::{get_operation_from_nlmsghdr_type, NfNetlinkDeserializable},
+    sys::{
+        NFTA_DEVICE_NAME, NFTA_FLOWTABLE_HOOK, NFTA_FLOWTABLE_HOOK_DEVS, NFTA_FLOWTABLE_HOOK_NUM,
+        NFTA_FLOWTABLE_HOOK_PRIORITY, NFTA_FLOWTABLE_NAME, NFTA_FLOWTABLE_TABLE,
+        NFT_MSG_NEWFLOWTABLE, NF_NETDEV_INGRESS,
+    },
+    Batch, BatchObjectKind, Flowtable, MsgType,
+};
+
+use super::{
+    get_test_nlmsg, get_test_rule, get_test_rule_expressions, get_test_table, NetlinkExpr,
+    TABLE_NAME,
+};
+
+const FLOWTABLE_NAME: &str = "mockflowtable";
+
+fn get_test_flowtable() -> Flowtable {
+    Flowtable::new(&get_test_table(), FLOWTABLE_NAME, 0, &["eth0", "eth1"])
+        .expect("Couldn't create a flowtable")
+}
+
+#[test]
+fn new_flowtable() {
+    let mut flowtable = get_test_flowtable();
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut flowtable);
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(nlmsghdr.nlmsg_type),
+        NFT_MSG_NEWFLOWTABLE as u8
+    );
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_FLOWTABLE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_FLOWTABLE_NAME, FLOWTABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_FLOWTABLE_HOOK,
+                vec![
+                    NetlinkExpr::Final(
+                        NFTA_FLOWTABLE_HOOK_NUM,
+                        NF_NETDEV_INGRESS.to_be_bytes().to_vec()
+                    ),
+                    NetlinkExpr::Final(NFTA_FLOWTABLE_HOOK_PRIORITY, 0u32.to_be_bytes().to_vec()),
+                    NetlinkExpr::Nested(
+                        NFTA_FLOWTABLE_HOOK_DEVS,
+                        vec![
+                            NetlinkExpr::Final(NFTA_DEVICE_NAME, b"eth0".to_vec()),
+                            NetlinkExpr::Final(NFTA_DEVICE_NAME, b"eth1".to_vec()),
+                        ]
+                    ),
+                ]
+            ),
+        ])
+        .to_raw()
+    );
+
+    let (deserialized, remaining) =
+        Flowtable::deserialize(&buf).expect("Couldn't deserialize the flowtable");
+    assert_eq!(deserialized, flowtable);
+    assert_eq!(remaining.len(), 0);
+    assert_eq!(deserialized.devices(), ["eth0", "eth1"]);
+
+    assert!(matches!(
+        Flowtable::new(&get_test_table(), FLOWTABLE_NAME, 0, &[]),
+        Err(BuilderError::MissingHookDevice)
+    ));
+}
+
+#[test]
+fn flowtable_in_batch() {
+    let mut batch = Batch::new();
+    get_test_flowtable().add_to_batch(&mut batch);
+    let rule = get_test_rule().flow_offload(FLOWTABLE_NAME);
+    batch.add(&rule, MsgType::Add);
+
+    let operations = batch.operations().expect("Couldn't parse the batch");
+    assert_eq!(operations[0].kind, BatchObjectKind::Flowtable);
+    assert_eq!(
+        operations[0].to_string(),
+        "add flowtable mocktable mockflowtable"
+    );
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        [ExpressionVariant::from(FlowOffload::new(FLOWTABLE_NAME))]
+    );
+}
@@ -1,12 +1,15 @@
 use crate::data_type::DataType;
-use crate::nlmsg::{NfNetlinkObject, NfNetlinkWriter};
+use crate::expr::ExpressionVariant;
+use crate::nlmsg::{NfNetlinkDeserializable, NfNetlinkObject, NfNetlinkWriter};
 use crate::parser::{parse_nlmsg, NlMsg};
-use crate::set::{Set, SetBuilder};
-use crate::{sys::*, Chain, MsgType, ProtocolFamily, Rule, Table};
+use crate::set::{Set, SetBuilder, SetElementList};
+use crate::{sys::*, Batch, Chain, MsgType, ProtocolFamily, Rule, Table};
 
 mod batch;
 mod chain;
 mod expr;
+mod flowtable;
+mod object;
 mod rule;
 mod set;
 mod table;
@@ -154,6 +157,14 @@ pub fn get_test_rule() -> Rule {
     Rule::new(&get_test_chain()).unwrap()
 }
 
+pub fn get_test_rule_expressions(rule: &Rule) -> Vec<ExpressionVariant> {
+    rule.get_expressions()
+        .expect("The rule has no expressions")
+        .iter()
+        .map(|e| e.get_data().expect("Expression without data").clone())
+        .collect()
+}
+
 pub fn get_test_set<K: DataType>() -> Set {
     SetBuilder::<K>::new(SET_NAME, &get_test_table())
         .expect("Couldn't create a set")
@@ -162,6 +173,25 @@ pub fn get_test_set<K: DataType>() -> Set {
         .with_userdata(SET_USERDATA)
 }
 
+/// Returns the messages of `batch`, without the messages beginning and ending the batch.
+pub fn get_batch_messages(batch: Batch) -> Vec<Vec<u8>> {
+    let batch = batch.finalize();
+    let mut messages: Vec<Vec<u8>> = batch.iter().map(|msg| msg.to_vec()).collect();
+    messages.pop();
+    messages.remove(0);
+    messages
+}
+
+/// Returns the set and its elements, which must be the first messages of `batch`, e.g. when a
+/// rule added them to match against an anonymous set.
+pub fn get_batch_set_and_elements(batch: Batch) -> (Set, SetElementList) {
+    let messages = get_batch_messages(batch);
+    let (set, _) = Set::deserialize(&messages[0]).expect("Couldn't parse the set");
+    let (elements, _) =
+        SetElementList::deserialize(&messages[1]).expect("Couldn't parse the set elements");
+    (set, elements)
+}
+
 pub fn get_test_nlmsg_with_msg_type<'a>(
     buf: &'a mut Vec<u8>,
     obj: &mut impl NfNetlinkObject,
@@ -0,0 +1,164 @@
+use crate::{
+    error::BuilderError,
+    expr::{Counter, ExpressionVariant, LimitUnit, ObjRef},
+    nlmsg::{get_operation_from_nlmsghdr_type, NfNetlinkDeserializable},
+    sys::{
+        NFTA_COUNTER_BYTES, NFTA_COUNTER_PACKETS, NFTA_CT_HELPER_L4PROTO, NFTA_CT_HELPER_NAME,
+        NFTA_LIMIT_BURST, NFTA_LIMIT_RATE, NFTA_LIMIT_TYPE, NFTA_LIMIT_UNIT, NFTA_OBJ_DATA,
+        NFTA_OBJ_NAME, NFTA_OBJ_TABLE, NFTA_OBJ_TYPE, NFT_LIMIT_PKTS, NFT_MSG_NEWOBJ,
+        NFT_OBJECT_COUNTER, NFT_OBJECT_CT_HELPER, NFT_OBJECT_LIMIT,
+    },
+    CtHelper, Object, ObjectData, ObjectType, Protocol,
+};
+
+use super::{
+    get_test_nlmsg, get_test_rule, get_test_rule_expressions, get_test_table, NetlinkExpr,
+    TABLE_NAME,
+};
+
+const HELPER_NAME: &str = "mockhelper";
+
+fn get_test_ct_helper() -> Object {
+    Object::new(
+        &get_test_table(),
+        HELPER_NAME,
+        CtHelper::new("ftp", Protocol::TCP),
+    )
+    .expect("Couldn't create an object")
+}
+
+#[test]
+fn new_ct_helper_object() {
+    let mut obj = get_test_ct_helper();
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut obj);
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(nlmsghdr.nlmsg_type),
+        NFT_MSG_NEWOBJ as u8
+    );
+    assert_eq!(nlmsghdr.nlmsg_len, 80);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_OBJ_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_NAME, HELPER_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_TYPE, NFT_OBJECT_CT_HELPER.to_be_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_OBJ_DATA,
+                vec![
+                    NetlinkExpr::Final(NFTA_CT_HELPER_NAME, b"ftp".to_vec()),
+                    NetlinkExpr::Final(NFTA_CT_HELPER_L4PROTO, vec![libc::IPPROTO_TCP as u8]),
+                ]
+            ),
+        ])
+        .to_raw()
+    );
+}
+
+#[test]
+fn parse_ct_helper_object() {
+    let mut obj = get_test_ct_helper();
+
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut obj);
+
+    let (deserialized_obj, remaining) =
+        Object::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(obj, deserialized_obj);
+    assert_eq!(remaining.len(), 0);
+}
+
+#[test]
+fn parse_counter_object() {
+    // counter object as returned by the kernel when reading (and resetting) it
+    let mut obj = Object::new(
+        &get_test_table(),
+        "mockcounter",
+        Counter::default()
+            .with_nb_bytes(1500u64)
+            .with_nb_packets(1u64),
+    )
+    .expect("Couldn't create an object");
+
+    let mut buf = Vec::new();
+    let (_nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut obj);
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_OBJ_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_NAME, b"mockcounter".to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_TYPE, NFT_OBJECT_COUNTER.to_be_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_OBJ_DATA,
+                vec![
+                    NetlinkExpr::Final(NFTA_COUNTER_BYTES, 1500u64.to_be_bytes().to_vec()),
+                    NetlinkExpr::Final(NFTA_COUNTER_PACKETS, 1u64.to_be_bytes().to_vec()),
+                ]
+            ),
+        ])
+        .to_raw()
+    );
+
+    let (deserialized_obj, _) = Object::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(obj, deserialized_obj);
+}
+
+#[test]
+fn set_counter_object_totals() {
+    let obj = Object::new(&get_test_table(), "mockcounter", Counter::default())
+        .expect("Couldn't create an object")
+        .with_counter(1500, 1)
+        .expect("Couldn't set the totals of a counter");
+    assert_eq!(
+        obj.get_data(),
+        Some(&ObjectData::Counter(
+            Counter::default()
+                .with_nb_bytes(1500u64)
+                .with_nb_packets(1u64)
+        ))
+    );
+
+    assert!(matches!(
+        get_test_ct_helper().set_counter(1500, 1),
+        Err(BuilderError::NotACounter)
+    ));
+}
+
+#[test]
+fn new_limit_object() {
+    let mut obj = Object::limit(&get_test_table(), "mocklimit", 10, LimitUnit::Minute, 5)
+        .expect("Couldn't create an object");
+    assert_eq!(obj.get_type(), Some(&ObjectType::Limit));
+
+    let mut buf = Vec::new();
+    let (_nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut obj);
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_OBJ_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_NAME, b"mocklimit".to_vec()),
+            NetlinkExpr::Final(NFTA_OBJ_TYPE, NFT_OBJECT_LIMIT.to_be_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_OBJ_DATA,
+                vec![
+                    NetlinkExpr::Final(NFTA_LIMIT_RATE, 10u64.to_be_bytes().to_vec()),
+                    NetlinkExpr::Final(NFTA_LIMIT_UNIT, 60u64.to_be_bytes().to_vec()),
+                    NetlinkExpr::Final(NFTA_LIMIT_BURST, 5u32.to_be_bytes().to_vec()),
+                    NetlinkExpr::Final(NFTA_LIMIT_TYPE, NFT_LIMIT_PKTS.to_be_bytes().to_vec()),
+                ]
+            ),
+        ])
+        .to_raw()
+    );
+
+    let (deserialized_obj, _) = Object::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(deserialized_obj, obj);
+
+    let rule = get_test_rule().limit_ref("mocklimit").accept();
+    assert_eq!(
+        get_test_rule_expressions(&rule)[0],
+        ExpressionVariant::from(ObjRef::new(ObjectType::Limit, "mocklimit"))
+    );
+}
@@ -1,15 +1,41 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use ipnetwork::IpNetwork;
+
 use crate::{
-    nlmsg::get_operation_from_nlmsghdr_type,
+    data_type::{ip_to_vec, vec_to_ip},
+    error::{BuilderError, DecodeError, QueryError},
+    expr::{
+        AddrType, Bitwise, Byteorder, ByteorderOp, Cmp, CmpOp, ConnTrackState, ConnTrackStatus,
+        Conntrack, ConntrackDirection, ConntrackKey, Counter, ExpressionVariant, ExtHdr, ExtHdrOp,
+        Fib, FibResult, HighLevelPayload, IPv4HeaderField, IPv6HeaderField, IcmpCode, Icmpv4Code,
+        Icmpv6Code, Immediate, Log, Lookup, Meta, MetaType, Nat, NatType, NetworkHeaderField,
+        Payload, PayloadBase, Range, RangeOp, Register, Reject, RejectCode, RejectType, Rt, RtKey,
+        Socket, SocketKey, TCPHeaderField, TransportHeaderField, UDPHeaderField, VerdictKind,
+    },
+    nlmsg::{
+        get_operation_from_nlmsghdr_type, pad_netlink_object, NfNetlinkDeserializable,
+        NfNetlinkObject, NfNetlinkWriter,
+    },
+    parser::get_nlmsghdr,
+    query::{count_object_message, deserialize_object},
+    set::{SetBuilder, VerdictMapBuilder},
     sys::{
-        NFTA_RULE_CHAIN, NFTA_RULE_HANDLE, NFTA_RULE_POSITION, NFTA_RULE_TABLE, NFTA_RULE_USERDATA,
-        NFT_MSG_DELRULE, NFT_MSG_NEWRULE,
+        nfgenmsg, nlmsghdr, NFTA_FIB_F_DADDR, NFTA_RULE_CHAIN, NFTA_RULE_HANDLE,
+        NFTA_RULE_POSITION, NFTA_RULE_TABLE, NFTA_RULE_USERDATA, NFT_EXTHDR_F_PRESENT,
+        NFT_MSG_DELRULE, NFT_MSG_GETRULE, NFT_MSG_NEWRULE, NFT_PAYLOAD_TRANSPORT_HEADER,
+        NFT_SET_ANONYMOUS, NFT_SET_CONSTANT, NFT_SET_ELEM_INTERVAL_END, NFT_SET_INTERVAL,
+        NF_NAT_RANGE_PROTO_SPECIFIED, NLM_F_CREATE, NLM_F_REPLACE,
     },
-    MsgType,
+    Batch, Chain, ChainType, EcnCodepoint, EtherType, Hook, HookClass, IcmpType, MsgType, PktType,
+    Protocol, ProtocolFamily, Rule, RuleDiff, RuleGroup, Table, Weekday,
 };
 
 use super::{
-    get_test_nlmsg, get_test_nlmsg_with_msg_type, get_test_rule, NetlinkExpr, CHAIN_NAME,
-    RULE_USERDATA, TABLE_NAME,
+    get_batch_messages, get_batch_set_and_elements, get_test_chain, get_test_nlmsg,
+    get_test_nlmsg_with_msg_type, get_test_rule, get_test_rule_expressions, get_test_table,
+    NetlinkExpr, CHAIN_NAME, RULE_USERDATA, TABLE_NAME,
 };
 
 #[test]
@@ -130,3 +156,1912 @@ fn delete_empty_rule_with_handle() {
         .to_raw()
     );
 }
+
+#[test]
+fn replace_rule() {
+    let handle: u64 = 42;
+    let rule = get_test_rule().accept();
+
+    let mut batch = Batch::new();
+    batch.replace(&rule, handle);
+    let messages = get_batch_messages(batch);
+    let nlmsghdr = get_nlmsghdr(&messages[0]).unwrap();
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(nlmsghdr.nlmsg_type),
+        NFT_MSG_NEWRULE as u8
+    );
+    assert_eq!(
+        nlmsghdr.nlmsg_flags & NLM_F_REPLACE as u16,
+        NLM_F_REPLACE as u16
+    );
+    assert_eq!(nlmsghdr.nlmsg_flags & NLM_F_CREATE as u16, 0);
+
+    let (replacement, _) = Rule::deserialize(&messages[0]).expect("Couldn't parse the rule");
+    assert_eq!(replacement, rule.with_handle(handle));
+}
+
+#[test]
+fn dnat_with_port_range() {
+    let dst = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let rule = get_test_rule().dnat_range(dst, 8000, 8100).unwrap();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Immediate::new_ip(dst, Register::Reg1)),
+            ExpressionVariant::from(Immediate::new_data(
+                8000u16.to_be_bytes().to_vec(),
+                Register::Reg2
+            )),
+            ExpressionVariant::from(Immediate::new_data(
+                8100u16.to_be_bytes().to_vec(),
+                Register::Reg3
+            )),
+            ExpressionVariant::from(
+                Nat::default()
+                    .with_nat_type(NatType::DNat)
+                    .with_family(ProtocolFamily::Ipv4)
+                    .with_ip_register(Register::Reg1)
+                    .with_port_register(Register::Reg2)
+                    .with_port_register_max(Register::Reg3)
+                    .with_flags(NF_NAT_RANGE_PROTO_SPECIFIED)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn dport_range_in_a_single_rule() {
+    let rule = get_test_rule().dport_range(1000, 2000, Protocol::TCP);
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::L4Proto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::IPPROTO_TCP as u8])),
+            ExpressionVariant::from(
+                HighLevelPayload::Transport(TransportHeaderField::Tcp(TCPHeaderField::Dport))
+                    .build()
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Gte, 1000u16.to_be_bytes())),
+            ExpressionVariant::from(Cmp::new(CmpOp::Lte, 2000u16.to_be_bytes())),
+        ]
+    );
+}
+
+#[test]
+fn reject_with_icmp_code_in_inet_table() {
+    let rule = get_test_rule()
+        .reject_with(RejectCode::Icmpv6(Icmpv6Code::AdminProhibited))
+        .unwrap();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::NfProto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8])),
+            ExpressionVariant::from(
+                Reject::default()
+                    .with_type(RejectType::IcmpUnreach)
+                    .with_icmp_code(1u8)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn reject_with_tcp_reset() {
+    let rule = get_test_rule().syn().unwrap().reject_tcp_reset();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[3..],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::L4Proto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::IPPROTO_TCP as u8])),
+            ExpressionVariant::from(Reject::default().with_type(RejectType::TcpRst)),
+        ]
+    );
+    assert_eq!(
+        get_test_rule().reject_with(RejectCode::TcpRst).unwrap(),
+        get_test_rule().reject_tcp_reset()
+    );
+}
+
+#[test]
+fn reject_with_code_of_another_family() {
+    let table = Table::new(ProtocolFamily::Ipv4).with_name(TABLE_NAME);
+    let chain = Chain::new(&table).with_name(CHAIN_NAME);
+    let rule = || Rule::new(&chain).unwrap();
+
+    let valid = rule()
+        .reject_with(RejectCode::Icmp(Icmpv4Code::AdminProhibited))
+        .unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&valid),
+        vec![ExpressionVariant::from(
+            Reject::default()
+                .with_type(RejectType::IcmpUnreach)
+                .with_icmp_code(13u8)
+        )]
+    );
+
+    assert!(matches!(
+        rule().reject_with(RejectCode::Icmpv6(Icmpv6Code::AdminProhibited)),
+        Err(BuilderError::InvalidRejectCode)
+    ));
+    assert!(matches!(
+        rule().reject_with(RejectCode::Icmpx(IcmpCode::AdminProhibited)),
+        Err(BuilderError::InvalidRejectCode)
+    ));
+}
+
+#[test]
+fn iface_prefix_is_not_null_terminated() {
+    let rule = get_test_rule().iface_prefix("eth").unwrap();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::IifName)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, b"eth".to_vec())),
+        ]
+    );
+
+    assert!(matches!(
+        get_test_rule().oface_prefix("averyveryverylongname"),
+        Err(BuilderError::InterfaceNameTooLong)
+    ));
+    assert!(matches!(
+        get_test_rule().iface_prefix(""),
+        Err(BuilderError::EmptyPrefix)
+    ));
+}
+
+#[test]
+fn ifaces_in_an_anonymous_set() {
+    let mut batch = Batch::new();
+    let rule = get_test_rule()
+        .ifaces(&["eth0", "wg0"], &mut batch)
+        .unwrap();
+
+    let exprs = get_test_rule_expressions(&rule);
+    assert_eq!(
+        exprs[0],
+        ExpressionVariant::from(Meta::new(MetaType::IifName))
+    );
+    let lookup = match &exprs[1] {
+        ExpressionVariant::Lookup(lookup) => lookup,
+        expr => panic!("Unexpected expression {:?}", expr),
+    };
+
+    let (set, elements) = get_batch_set_and_elements(batch);
+
+    assert_eq!(set.get_name(), Some(&"__set%d".to_string()));
+    assert_eq!(
+        set.get_flags(),
+        Some(&(NFT_SET_ANONYMOUS | NFT_SET_CONSTANT))
+    );
+    assert_eq!(set.get_key_len(), Some(&(libc::IFNAMSIZ as u32)));
+    assert_eq!(lookup.get_set(), set.get_name());
+    assert_eq!(lookup.get_set_id(), set.get_id());
+    assert_eq!(elements.get_set_id(), set.get_id());
+
+    let keys: Vec<_> = elements
+        .get_elements()
+        .unwrap()
+        .iter()
+        .map(|elem| elem.get_key().unwrap().get_value().unwrap().clone())
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            b"eth0\0\0\0\0\0\0\0\0\0\0\0\0".to_vec(),
+            b"wg0\0\0\0\0\0\0\0\0\0\0\0\0\0".to_vec(),
+        ]
+    );
+
+    // nothing is added to the batch for an empty list
+    let mut batch = Batch::new();
+    assert!(matches!(
+        get_test_rule().ifaces(&[], &mut batch),
+        Err(BuilderError::EmptySet)
+    ));
+    assert!(batch.operations().unwrap().is_empty());
+}
+
+#[test]
+fn match_raw_payload() {
+    let rule = get_test_rule()
+        .match_raw(PayloadBase::Transport, 20, 4, CmpOp::Eq, b"GET ")
+        .unwrap();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(
+                Payload::default()
+                    .with_base(NFT_PAYLOAD_TRANSPORT_HEADER)
+                    .with_offset(20u32)
+                    .with_len(4u32)
+                    .with_dreg(Register::Reg1)
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, b"GET ".to_vec())),
+        ]
+    );
+
+    assert!(matches!(
+        get_test_rule().match_raw(PayloadBase::Transport, 20, 2, CmpOp::Eq, b"GET "),
+        Err(BuilderError::IncompatibleLength)
+    ));
+    assert!(matches!(
+        Payload::raw(PayloadBase::Network, 256, 1, Register::Reg1),
+        Err(BuilderError::PayloadOffsetTooLarge)
+    ));
+    assert!(matches!(
+        Payload::raw(PayloadBase::Network, 0, 65, Register::Reg1),
+        Err(BuilderError::InvalidPayloadLength)
+    ));
+}
+
+#[test]
+fn check_register_lengths() {
+    let rule = get_test_rule()
+        .dport(80, Protocol::TCP)
+        .saddr(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+        .iface("eth0")
+        .unwrap()
+        .iface_prefix("wg")
+        .unwrap()
+        .established()
+        .unwrap()
+        .syn()
+        .unwrap()
+        .accept();
+    assert!(rule.check_register_lengths().is_ok());
+
+    // the port is 2 bytes long
+    let rule = get_test_rule()
+        .with_expr(
+            HighLevelPayload::Transport(TransportHeaderField::Tcp(TCPHeaderField::Dport)).build(),
+        )
+        .with_expr(Cmp::new(CmpOp::Eq, 80u32.to_be_bytes()));
+    assert!(matches!(
+        rule.check_register_lengths(),
+        Err(BuilderError::RegisterLengthMismatch)
+    ));
+
+    let rule = get_test_rule()
+        .with_expr(Meta::new(MetaType::Mark))
+        .with_expr(Cmp::new(CmpOp::Eq, [1u8]));
+    assert!(matches!(
+        rule.check_register_lengths(),
+        Err(BuilderError::RegisterLengthMismatch)
+    ));
+}
+
+#[test]
+fn log_and_drop() {
+    let rule = get_test_rule().log_and_drop("dropped: ").unwrap();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Counter::default()),
+            ExpressionVariant::from(Log::new(None, Some("dropped: ")).unwrap()),
+            ExpressionVariant::from(Immediate::new_verdict(VerdictKind::Drop)),
+        ]
+    );
+    assert!(matches!(
+        get_test_rule().log_and_drop(&"a".repeat(128)),
+        Err(BuilderError::TooLongLogPrefix)
+    ));
+}
+
+#[test]
+fn match_ecn() {
+    let rule = get_test_rule().ecn6(EcnCodepoint::Ce).unwrap();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::NfProto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8])),
+            ExpressionVariant::from(
+                Payload::raw(PayloadBase::Network, 1, 1, Register::Reg1).unwrap()
+            ),
+            ExpressionVariant::from(Bitwise::new([0x30u8], [0u8]).unwrap()),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [0x30u8])),
+        ]
+    );
+
+    let rule = get_test_rule().ecn(EcnCodepoint::Ect0).unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule)[3..],
+        [
+            ExpressionVariant::from(Bitwise::new([0x03u8], [0u8]).unwrap()),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [0x02u8])),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_ip_options() {
+    let rule = get_test_rule().has_ip_options().unwrap().drop();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..5],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::NfProto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV4 as u8])),
+            ExpressionVariant::from(
+                Payload::raw(PayloadBase::Network, 0, 1, Register::Reg1).unwrap()
+            ),
+            ExpressionVariant::from(Bitwise::new([0x0fu8], [0u8]).unwrap()),
+            ExpressionVariant::from(Cmp::new(CmpOp::Gt, [5u8])),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn skuid_range() {
+    let rule = get_test_rule().skuid_range(1000, 1005);
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::SkUid)),
+            ExpressionVariant::from(
+                Byteorder::default()
+                    .with_sreg(Register::Reg1)
+                    .with_dreg(Register::Reg1)
+                    .with_op(ByteorderOp::HtoN)
+                    .with_len(4u32)
+                    .with_siz(4u32)
+            ),
+            ExpressionVariant::from(Range::new(
+                RangeOp::Eq,
+                1000u32.to_be_bytes(),
+                1005u32.to_be_bytes()
+            )),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_length_range() {
+    let rule = get_test_rule().length_range(9001, u32::MAX).unwrap().drop();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..3],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::Len)),
+            ExpressionVariant::from(
+                Byteorder::default()
+                    .with_sreg(Register::Reg1)
+                    .with_dreg(Register::Reg1)
+                    .with_op(ByteorderOp::HtoN)
+                    .with_len(4u32)
+                    .with_siz(4u32)
+            ),
+            ExpressionVariant::from(Range::new(
+                RangeOp::Eq,
+                9001u32.to_be_bytes(),
+                u32::MAX.to_be_bytes()
+            )),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    assert!(matches!(
+        get_test_rule().length_range(100, 10),
+        Err(BuilderError::InvertedRange)
+    ));
+}
+
+#[test]
+fn match_ct_bytes() {
+    let rule = get_test_rule().ct_bytes(CmpOp::Gt, 1 << 30).drop();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..3],
+        [
+            ExpressionVariant::from(Conntrack::new(ConntrackKey::Bytes)),
+            ExpressionVariant::from(
+                Byteorder::default()
+                    .with_sreg(Register::Reg1)
+                    .with_dreg(Register::Reg1)
+                    .with_op(ByteorderOp::HtoN)
+                    .with_len(8u32)
+                    .with_siz(8u32)
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Gt, (1u64 << 30).to_be_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    let rule = get_test_rule().ct_packets(CmpOp::Lte, 10);
+    assert_eq!(
+        get_test_rule_expressions(&rule)[0],
+        ExpressionVariant::from(Conntrack::new(ConntrackKey::Packets))
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_masked_payload() {
+    // the DSCP bits of the IPv4 ToS field
+    let tos = Payload::raw(PayloadBase::Network, 1, 1, Register::Reg1).unwrap();
+    let rule = get_test_rule()
+        .match_masked(tos, &[0xfc], &[0xb8])
+        .unwrap()
+        .accept();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..3],
+        [
+            ExpressionVariant::from(tos),
+            ExpressionVariant::from(Bitwise::new([0xfcu8], [0u8]).unwrap()),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [0xb8u8])),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    assert!(matches!(
+        get_test_rule().match_masked(tos, &[0xff, 0xff], &[0, 0]),
+        Err(BuilderError::IncompatibleLength)
+    ));
+    assert!(matches!(
+        get_test_rule().match_masked(tos, &[0xff], &[0, 0]),
+        Err(BuilderError::IncompatibleLength)
+    ));
+}
+
+#[test]
+fn match_mark() {
+    let rule = get_test_rule().mark(0x42);
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::Mark)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 0x42u32.to_ne_bytes())),
+        ]
+    );
+
+    let rule = get_test_rule().mark_masked(0xff00, 0x1234);
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::Mark)),
+            ExpressionVariant::from(
+                Bitwise::new(0xff00u32.to_ne_bytes(), 0u32.to_ne_bytes()).unwrap()
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 0x1200u32.to_ne_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_cgroup() {
+    let rule = get_test_rule().cgroup(1, 4242);
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(
+                Socket::new(SocketKey::CgroupV2)
+                    .with_dreg(Register::Reg1)
+                    .with_level(1u32)
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 4242u64.to_ne_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_pkttype() {
+    let rule = get_test_rule().pkttype(PktType::Broadcast).drop();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..2],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::PktType)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::PACKET_BROADCAST])),
+        ]
+    );
+    assert_eq!(MetaType::PktType as u32, 19);
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_iface_groups() {
+    let rule = get_test_rule().iifgroup(1).oifgroup(2).accept();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..4],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::IifGroup)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 1u32.to_ne_bytes())),
+            ExpressionVariant::from(Meta::new(MetaType::OifGroup)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 2u32.to_ne_bytes())),
+        ]
+    );
+    assert_eq!(MetaType::IifGroup as u32, 21);
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_daddr_type() {
+    let rule = get_test_rule().daddr_type(AddrType::Local).accept();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..2],
+        [
+            ExpressionVariant::from(Fib::new(FibResult::AddrType, NFTA_FIB_F_DADDR)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, (libc::RTN_LOCAL as u32).to_ne_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_flow_label() {
+    let rule = get_test_rule().flow_label(0x12345).unwrap();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::NfProto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8])),
+            ExpressionVariant::from(
+                Payload::raw(PayloadBase::Network, 1, 3, Register::Reg1).unwrap()
+            ),
+            ExpressionVariant::from(Bitwise::new([0x0fu8, 0xff, 0xff], [0u8; 3]).unwrap()),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [0x01u8, 0x23, 0x45])),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    assert!(matches!(
+        get_test_rule().flow_label(0x100000),
+        Err(BuilderError::InvalidFlowLabel)
+    ));
+}
+
+#[test]
+fn match_tcp_field() {
+    let rule = get_test_rule()
+        .tcp_field(TCPHeaderField::Window, CmpOp::Lt, 1024)
+        .unwrap();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[2..],
+        [
+            ExpressionVariant::from(
+                HighLevelPayload::Transport(TransportHeaderField::Tcp(TCPHeaderField::Window))
+                    .build()
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Lt, 1024u16.to_be_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    let rule = get_test_rule()
+        .tcp_field(TCPHeaderField::Sequence, CmpOp::Eq, 0xdeadbeef)
+        .unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule)[3],
+        ExpressionVariant::from(Cmp::new(CmpOp::Eq, 0xdeadbeefu32.to_be_bytes()))
+    );
+
+    assert!(matches!(
+        get_test_rule().tcp_field(TCPHeaderField::UrgPtr, CmpOp::Eq, 0x10000),
+        Err(BuilderError::IncompatibleLength)
+    ));
+}
+
+#[test]
+fn rule_group() {
+    let group = RuleGroup::new(&get_test_chain()).unwrap();
+    assert_eq!(group.rule(), get_test_rule());
+
+    let mut batch = Batch::new();
+    RuleGroup::add_all(&mut batch, vec![group.rule().accept(), group.rule().drop()]);
+    assert_eq!(
+        batch
+            .operations()
+            .unwrap()
+            .iter()
+            .map(|op| op.to_string())
+            .collect::<Vec<_>>(),
+        vec![
+            "add rule mocktable mockchain",
+            "add rule mocktable mockchain",
+        ]
+    );
+}
+
+#[test]
+fn match_nfproto() {
+    let rule = get_test_rule().if_ipv4().with_expr(Counter::default());
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..2],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::NfProto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV4 as u8])),
+        ]
+    );
+
+    let rule = get_test_rule().if_ipv6();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::NfProto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8])),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_ct_original_daddr() {
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10));
+    let rule = get_test_rule().ct_original_daddr(ip).accept();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..2],
+        [
+            ExpressionVariant::from(
+                Conntrack::new(ConntrackKey::DstIp)
+                    .with_direction(ConntrackDirection::Original as u8)
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [192u8, 168, 1, 10])),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    let rule = get_test_rule().ct_addr(
+        ConntrackDirection::Reply,
+        true,
+        IpAddr::V6(Ipv6Addr::LOCALHOST),
+    );
+    assert_eq!(
+        get_test_rule_expressions(&rule)[0],
+        ExpressionVariant::from(Conntrack::tuple(
+            ConntrackKey::SrcIp6,
+            ConntrackDirection::Reply
+        ))
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+/// Makes the length of the first attribute of the message in `buf` (the table name of a rule)
+/// overflow the message, and returns the offset of the attribute.
+fn overflow_first_attribute(buf: &mut [u8]) -> usize {
+    let attr_offset = pad_netlink_object::<nlmsghdr>() + pad_netlink_object::<nfgenmsg>();
+    buf[attr_offset..attr_offset + 2].copy_from_slice(&u16::MAX.to_ne_bytes());
+    attr_offset
+}
+
+#[test]
+fn malformed_rule_message() {
+    let rule = get_test_rule().accept();
+    let mut buf = Vec::new();
+    let mut writer = NfNetlinkWriter::new(&mut buf);
+    rule.add_or_remove(&mut writer, MsgType::Add, 0);
+
+    let rule_again = deserialize_object::<Rule>(NFT_MSG_GETRULE as u16, &buf)
+        .expect("Couldn't parse a valid rule");
+    assert_eq!(rule_again, rule);
+
+    overflow_first_attribute(&mut buf);
+
+    match deserialize_object::<Rule>(NFT_MSG_GETRULE as u16, &buf) {
+        Err(QueryError::InvalidObject(msg_type, _)) => {
+            assert_eq!(msg_type, NFT_MSG_GETRULE as u16)
+        }
+        res => panic!("Expected an invalid object, got {:?}", res),
+    }
+}
+
+#[test]
+fn count_rule_messages() {
+    let mut batch = Batch::new();
+    batch.add(&get_test_table(), MsgType::Add);
+    batch.add(&get_test_chain(), MsgType::Add);
+    for _ in 0..3 {
+        batch.add(&get_test_rule().accept(), MsgType::Add);
+    }
+    let batch = batch.finalize();
+
+    // only the rules are counted, not the table, the chain or the messages beginning and ending
+    // the batch
+    let mut count = 0;
+    for msg in batch.iter() {
+        count_object_message::<Rule>(msg, &mut count).expect("Couldn't count the message");
+    }
+    assert_eq!(count, 3);
+}
+
+#[test]
+fn decode_error_context() {
+    let mut buf = Vec::new();
+    let mut writer = NfNetlinkWriter::new(&mut buf);
+    get_test_rule()
+        .accept()
+        .add_or_remove(&mut writer, MsgType::Add, 0);
+
+    let attr_offset = overflow_first_attribute(&mut buf);
+    let available = buf.len() - attr_offset;
+    match Rule::deserialize(&buf) {
+        Err(e @ DecodeError::InvalidAttributeLength { .. }) => {
+            assert!(matches!(
+                e,
+                DecodeError::InvalidAttributeLength { offset: 0, len: 65535, available: a }
+                    if a == available
+            ));
+            assert_eq!(
+                e.to_string(),
+                format!(
+                    "The attribute at offset 0 claims a length of 65535 bytes, with {} bytes left",
+                    available
+                )
+            );
+        }
+        res => panic!("Expected an invalid attribute length, got {:?}", res),
+    }
+
+    assert!(matches!(
+        get_nlmsghdr(&buf[..4]),
+        Err(DecodeError::BufTooSmall {
+            expected: 16,
+            available: 4
+        })
+    ));
+}
+
+#[test]
+fn match_tcp_mss() {
+    let rule = get_test_rule().tcp_mss(CmpOp::Lt, 1400).accept();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..4],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::L4Proto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::IPPROTO_TCP as u8])),
+            ExpressionVariant::from(
+                ExtHdr::default()
+                    .with_dreg(Register::Reg1)
+                    .with_typ(2u8)
+                    .with_offset(2u32)
+                    .with_len(2u32)
+                    .with_op(ExtHdrOp::TCPOpt)
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Lt, 1400u16.to_be_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_tcp_options_presence() {
+    let rule = get_test_rule().tcp_has_wscale().accept();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..4],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::L4Proto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::IPPROTO_TCP as u8])),
+            ExpressionVariant::from(
+                ExtHdr::default()
+                    .with_dreg(Register::Reg1)
+                    .with_typ(3u8)
+                    .with_offset(0u32)
+                    .with_len(1u32)
+                    .with_flags(NFT_EXTHDR_F_PRESENT)
+                    .with_op(ExtHdrOp::TCPOpt)
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [1u8])),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    let rule = get_test_rule().tcp_has_sack_perm();
+    match &get_test_rule_expressions(&rule)[2] {
+        ExpressionVariant::ExtHdr(exthdr) => assert_eq!(exthdr.get_typ(), Some(&4)),
+        expr => panic!("Unexpected expression {:?}", expr),
+    }
+}
+
+#[test]
+fn match_udp_checksum_zero() {
+    let rule = get_test_rule().udp_checksum_zero();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        [
+            ExpressionVariant::from(Meta::new(MetaType::L4Proto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::IPPROTO_UDP as u8])),
+            ExpressionVariant::from(
+                Payload::raw(PayloadBase::Transport, 6, 2, Register::Reg1).unwrap()
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [0u8, 0u8])),
+        ]
+    );
+
+    let tcp_checksum =
+        HighLevelPayload::Transport(TransportHeaderField::Tcp(TCPHeaderField::Checksum)).build();
+    assert_eq!(tcp_checksum.get_offset(), Some(&16));
+    assert_eq!(tcp_checksum.get_len(), Some(&2));
+    assert_eq!(
+        TCPHeaderField::from_raw_data(16, 2).unwrap(),
+        TCPHeaderField::Checksum
+    );
+    assert_eq!(
+        UDPHeaderField::from_raw_data(6, 2).unwrap(),
+        UDPHeaderField::Checksum
+    );
+}
+
+#[test]
+fn conditional_expressions() {
+    let rule = get_test_rule()
+        .add_expr_if(true, Counter::default())
+        .add_expr_if(false, Log::default())
+        .maybe(Some(1u16), |rule, group| {
+            rule.with_expr(Log::default().with_group(group))
+        })
+        .maybe(None::<u16>, |rule, _| rule.with_expr(Counter::default()))
+        .accept();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..2],
+        [
+            ExpressionVariant::from(Counter::default()),
+            ExpressionVariant::from(Log::default().with_group(1u16)),
+        ]
+    );
+    assert_eq!(rule.expr_count(), 3);
+}
+
+#[test]
+fn match_ipv6_l4proto() {
+    let rule = get_test_rule().ipv6_l4proto(libc::IPPROTO_SCTP as u8);
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        [
+            ExpressionVariant::from(Meta::new(MetaType::NfProto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8])),
+            ExpressionVariant::from(Meta::new(MetaType::L4Proto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::IPPROTO_SCTP as u8])),
+        ]
+    );
+}
+
+#[test]
+fn match_socket_keys() {
+    let rule = get_test_rule().socket_transparent().socket_mark(0x1);
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        [
+            ExpressionVariant::from(Socket::new(SocketKey::Transparent)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [1u8])),
+            ExpressionVariant::from(Socket::new(SocketKey::Mark)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 1u32.to_ne_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    let rule = get_test_rule().socket_wildcard();
+    assert_eq!(
+        get_test_rule_expressions(&rule)[0],
+        ExpressionVariant::from(Socket::new(SocketKey::Wildcard))
+    );
+}
+
+#[test]
+fn match_ttl_expiring() {
+    let table = Table::new(ProtocolFamily::Ipv4).with_name(TABLE_NAME);
+    let chain = Chain::new(&table).with_name(CHAIN_NAME);
+    let rule = Rule::new(&chain).unwrap().ttl_expiring().unwrap().drop();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[2..4],
+        [
+            ExpressionVariant::from(
+                HighLevelPayload::Network(NetworkHeaderField::IPv4(IPv4HeaderField::Ttl)).build()
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [1u8])),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    let table = Table::new(ProtocolFamily::Ipv6).with_name(TABLE_NAME);
+    let chain = Chain::new(&table).with_name(CHAIN_NAME);
+    let hop_limit = get_test_rule_expressions(&Rule::new(&chain).unwrap().ttl_expiring().unwrap());
+    assert_eq!(
+        hop_limit[1..],
+        [
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV6 as u8])),
+            ExpressionVariant::from(
+                Payload::raw(PayloadBase::Network, 7, 1, Register::Reg1).unwrap()
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [1u8])),
+        ]
+    );
+
+    assert!(matches!(
+        get_test_rule().ttl_expiring(),
+        Err(BuilderError::AmbiguousIpVersion)
+    ));
+}
+
+#[test]
+fn match_loopback() {
+    let rule = get_test_rule().is_loopback().accept();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..2],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::IifType)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 772u16.to_ne_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+    assert_eq!(
+        get_test_rule_expressions(&get_test_rule().oif_is_loopback())[0],
+        ExpressionVariant::from(Meta::new(MetaType::OifType))
+    );
+}
+
+#[test]
+fn iface_by_name_lookup_failure() {
+    assert!(matches!(
+        get_test_rule().iface_by_name("doesnotexist0"),
+        Err(BuilderError::InterfaceLookupFailed(_))
+    ));
+    assert!(matches!(
+        get_test_rule().oface_by_name("doesnot\0exist"),
+        Err(BuilderError::InterfaceLookupFailed(_))
+    ));
+}
+
+#[test]
+fn match_dccp_dport() {
+    let rule = get_test_rule().dport(5004, Protocol::DCCP).accept();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..4],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::L4Proto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::IPPROTO_DCCP as u8])),
+            ExpressionVariant::from(
+                Payload::raw(PayloadBase::Transport, 2, 2, Register::Reg1).unwrap()
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 5004u16.to_be_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_named_services() {
+    let services = [
+        (get_test_rule().dns(), libc::IPPROTO_UDP, 53u16),
+        (get_test_rule().ssh(), libc::IPPROTO_TCP, 22),
+        (get_test_rule().http(), libc::IPPROTO_TCP, 80),
+        (get_test_rule().https(), libc::IPPROTO_TCP, 443),
+    ];
+    for (rule, proto, port) in services {
+        assert_eq!(
+            get_test_rule_expressions(&rule),
+            [
+                ExpressionVariant::from(Meta::new(MetaType::L4Proto)),
+                ExpressionVariant::from(Cmp::new(CmpOp::Eq, [proto as u8])),
+                ExpressionVariant::from(
+                    Payload::raw(PayloadBase::Transport, 2, 2, Register::Reg1).unwrap()
+                ),
+                ExpressionVariant::from(Cmp::new(CmpOp::Eq, port.to_be_bytes())),
+            ]
+        );
+    }
+}
+
+#[test]
+fn match_gre_key() {
+    let rule = get_test_rule().gre_key(0x01020304).unwrap();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        [
+            ExpressionVariant::from(Meta::new(MetaType::L4Proto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::IPPROTO_GRE as u8])),
+            ExpressionVariant::from(
+                Payload::raw(PayloadBase::Transport, 0, 2, Register::Reg1).unwrap()
+            ),
+            ExpressionVariant::from(Bitwise::new([0xa0u8, 0], [0u8, 0]).unwrap()),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [0x20u8, 0])),
+            ExpressionVariant::from(
+                Payload::raw(PayloadBase::Transport, 4, 4, Register::Reg1).unwrap()
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [1u8, 2, 3, 4])),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_router_alert() {
+    let table = Table::new(ProtocolFamily::Ipv4).with_name(TABLE_NAME);
+    let rule = Rule::new(&Chain::new(&table).with_name(CHAIN_NAME))
+        .unwrap()
+        .has_router_alert()
+        .unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        [
+            ExpressionVariant::from(
+                ExtHdr::default()
+                    .with_dreg(Register::Reg1)
+                    .with_typ(148u8)
+                    .with_offset(0u32)
+                    .with_len(1u32)
+                    .with_flags(NFT_EXTHDR_F_PRESENT)
+                    .with_op(ExtHdrOp::IPv4)
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [1u8])),
+        ]
+    );
+
+    let table = Table::new(ProtocolFamily::Ipv6).with_name(TABLE_NAME);
+    let rule = Rule::new(&Chain::new(&table).with_name(CHAIN_NAME))
+        .unwrap()
+        .has_router_alert()
+        .unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        [
+            ExpressionVariant::from(
+                ExtHdr::default()
+                    .with_dreg(Register::Reg1)
+                    .with_typ(0u8)
+                    .with_offset(2u32)
+                    .with_len(1u32)
+                    .with_op(ExtHdrOp::IPv6)
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [5u8])),
+        ]
+    );
+
+    assert!(matches!(
+        get_test_rule().has_router_alert(),
+        Err(BuilderError::AmbiguousIpVersion)
+    ));
+}
+
+#[test]
+fn concatenated_vmap() {
+    let fields = [
+        HighLevelPayload::Network(NetworkHeaderField::IPv4(IPv4HeaderField::Saddr)),
+        HighLevelPayload::Transport(TransportHeaderField::Tcp(TCPHeaderField::Dport)),
+    ];
+    let mut map_builder =
+        VerdictMapBuilder::<(Ipv4Addr, [u8; 2])>::anonymous(&get_test_table()).unwrap();
+    map_builder.add(
+        &(Ipv4Addr::new(10, 0, 0, 1), 22u16.to_be_bytes()),
+        VerdictKind::Accept,
+    );
+    let (map, _elements) = map_builder.finish();
+
+    let rule = get_test_rule().concat_vmap(&fields, &map).unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(fields[0].build().with_dreg(Register::Reg32_00)),
+            ExpressionVariant::from(fields[1].build().with_dreg(Register::Reg32_01)),
+            ExpressionVariant::from(
+                Lookup::new_map(&map, Register::Verdict)
+                    .unwrap()
+                    .with_sreg(Register::Reg32_00)
+            ),
+        ]
+    );
+
+    // the fields must match the keys of the map
+    assert!(matches!(
+        get_test_rule().concat_vmap(&fields[..1], &map),
+        Err(BuilderError::IncompatibleLength)
+    ));
+    let ipv6_saddr = HighLevelPayload::Network(NetworkHeaderField::IPv6(IPv6HeaderField::Saddr));
+    assert!(matches!(
+        get_test_rule().concat_vmap(&[ipv6_saddr; 5], &map),
+        Err(BuilderError::TooManyConcatFields)
+    ));
+    let (set, _) = SetBuilder::<(Ipv4Addr, [u8; 2])>::anonymous(&get_test_table())
+        .unwrap()
+        .finish();
+    assert!(matches!(
+        get_test_rule().concat_vmap(&fields, &set),
+        Err(BuilderError::NotAMap)
+    ));
+}
+
+#[test]
+fn rule_comment() {
+    let mut rule = get_test_rule()
+        .with_comment("allow ssh")
+        .expect("Couldn't set the comment");
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut rule);
+    assert_eq!(nlmsghdr.nlmsg_len, 68);
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_RULE_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_CHAIN, CHAIN_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_RULE_USERDATA, b"\x00\x0aallow ssh\x00".to_vec()),
+        ])
+        .to_raw()
+    );
+
+    let (deserialized, _) = Rule::deserialize(&buf).expect("Couldn't parse the rule");
+    assert_eq!(deserialized.get_comment().as_deref(), Some("allow ssh"));
+
+    // nft may store other entries before the comment
+    let rule = get_test_rule().with_userdata(b"\x01\x01\x05\x00\x04test".to_vec());
+    assert_eq!(rule.get_comment().as_deref(), Some("test"));
+    assert_eq!(get_test_rule().get_comment(), None);
+    assert!(matches!(
+        get_test_rule().with_comment(&"a".repeat(128)),
+        Err(BuilderError::InvalidComment)
+    ));
+}
+
+#[test]
+fn saddr_in_networks() {
+    let nets: Vec<IpNetwork> = vec![
+        "10.0.0.0/8".parse().unwrap(),
+        "192.168.0.0/16".parse().unwrap(),
+    ];
+    let mut batch = Batch::new();
+    let rule = get_test_rule().saddr_in(&nets, &mut batch).unwrap();
+
+    let exprs = get_test_rule_expressions(&rule);
+    assert_eq!(
+        exprs[..3],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::NfProto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV4 as u8])),
+            ExpressionVariant::from(
+                HighLevelPayload::Network(NetworkHeaderField::IPv4(IPv4HeaderField::Saddr)).build()
+            ),
+        ]
+    );
+    let lookup = match &exprs[3] {
+        ExpressionVariant::Lookup(lookup) => lookup,
+        expr => panic!("Unexpected expression {:?}", expr),
+    };
+
+    let (set, elements) = get_batch_set_and_elements(batch);
+
+    assert_eq!(
+        set.get_flags(),
+        Some(&(NFT_SET_ANONYMOUS | NFT_SET_CONSTANT | NFT_SET_INTERVAL))
+    );
+    assert_eq!(lookup.get_set_id(), set.get_id());
+
+    // each range is stored as its first address, and the address following its last one
+    let keys: Vec<_> = elements
+        .get_elements()
+        .unwrap()
+        .iter()
+        .map(|elem| {
+            (
+                elem.get_key().unwrap().get_value().unwrap().clone(),
+                elem.get_flags().copied(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            (vec![10, 0, 0, 0], None),
+            (vec![11, 0, 0, 0], Some(NFT_SET_ELEM_INTERVAL_END)),
+            (vec![192, 168, 0, 0], None),
+            (vec![192, 169, 0, 0], Some(NFT_SET_ELEM_INTERVAL_END)),
+        ]
+    );
+
+    // a set only holds addresses of one family
+    let mixed: Vec<IpNetwork> = vec!["10.0.0.0/8".parse().unwrap(), "fd00::/8".parse().unwrap()];
+    assert!(matches!(
+        get_test_rule().saddr_in(&mixed, &mut Batch::new()),
+        Err(BuilderError::MixedAddressFamilies)
+    ));
+    assert!(matches!(
+        get_test_rule().daddr_in(&[], &mut Batch::new()),
+        Err(BuilderError::EmptySet)
+    ));
+}
+
+#[test]
+fn daddr_in_nested_networks() {
+    // unsorted, with a network nested in another and two overlapping ones
+    let nets: Vec<IpNetwork> = vec![
+        "192.168.1.0/24".parse().unwrap(),
+        "10.1.0.0/16".parse().unwrap(),
+        "192.168.0.0/23".parse().unwrap(),
+        "10.0.0.0/8".parse().unwrap(),
+        "192.168.1.128/25".parse().unwrap(),
+    ];
+    let mut batch = Batch::new();
+    get_test_rule().daddr_in(&nets, &mut batch).unwrap();
+
+    let (_, elements) = get_batch_set_and_elements(batch);
+    let keys: Vec<_> = elements
+        .get_elements()
+        .unwrap()
+        .iter()
+        .map(|elem| {
+            (
+                elem.get_key().unwrap().get_value().unwrap().clone(),
+                elem.get_flags().copied(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            (vec![10, 0, 0, 0], None),
+            (vec![11, 0, 0, 0], Some(NFT_SET_ELEM_INTERVAL_END)),
+            (vec![192, 168, 0, 0], None),
+            (vec![192, 168, 2, 0], Some(NFT_SET_ELEM_INTERVAL_END)),
+        ]
+    );
+}
+
+#[test]
+fn zero_register() {
+    let rule = get_test_rule()
+        .zero_register(Register::Reg32_00, 12)
+        .unwrap()
+        .zero_register(Register::Reg4, 16)
+        .unwrap();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Immediate::new_data(vec![0; 12], Register::Reg32_00)),
+            ExpressionVariant::from(Immediate::new_data(vec![0; 16], Register::Reg4)),
+        ]
+    );
+
+    for (register, len) in [
+        (Register::Reg32_15, 5),
+        (Register::Reg2, 49),
+        (Register::Reg1, 0),
+        (Register::Verdict, 4),
+    ] {
+        assert!(matches!(
+            get_test_rule().zero_register(register, len),
+            Err(BuilderError::IncompatibleLength)
+        ));
+    }
+}
+
+#[test]
+fn expressions_keep_their_order() {
+    let mut rule = get_test_rule()
+        .dport(22, Protocol::TCP)
+        .with_expr(Counter::default())
+        .accept();
+    let mut buf = Vec::new();
+    get_test_nlmsg(&mut buf, &mut rule);
+    // the counter has no attribute, so its data attribute is empty
+    let (deserialized, _) = Rule::deserialize(&buf).expect("Couldn't parse the rule");
+
+    assert_eq!(deserialized.expr_count(), 6);
+    for (i, expr) in deserialized.get_expressions().unwrap().iter().enumerate() {
+        assert_eq!(deserialized.get_expr(i), Some(expr));
+        assert_eq!(rule.get_expr(i), Some(expr));
+    }
+    assert_eq!(
+        deserialized.get_expr(4).unwrap().get_data(),
+        Some(&ExpressionVariant::from(Counter::default()))
+    );
+    assert_eq!(deserialized.get_expr(6), None);
+    assert_eq!(get_test_rule().expr_count(), 0);
+}
+
+#[test]
+fn match_and_copy_secmark() {
+    let rule = get_test_rule().secmark(42).ct_secmark(42).accept();
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..4],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::SecMark)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 42u32.to_ne_bytes())),
+            ExpressionVariant::from(Conntrack::new(ConntrackKey::SecMark)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 42u32.to_ne_bytes())),
+        ]
+    );
+    assert_eq!(MetaType::SecMark as u32, 14);
+    assert_eq!(ConntrackKey::SecMark as u32, 4);
+    assert!(rule.check_register_lengths().is_ok());
+
+    let rule = get_test_rule().save_ct_secmark().restore_ct_secmark();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::SecMark)),
+            ExpressionVariant::from(
+                Conntrack::default()
+                    .with_key(ConntrackKey::SecMark)
+                    .with_sreg(Register::Reg1)
+            ),
+            ExpressionVariant::from(Conntrack::new(ConntrackKey::SecMark)),
+            ExpressionVariant::from(
+                Meta::default()
+                    .with_key(MetaType::SecMark)
+                    .with_sreg(Register::Reg1)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn match_ipsec() {
+    let rule = get_test_rule().ipsec_in().ipsec_out().accept();
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..4],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::SecPath)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [1u8])),
+            ExpressionVariant::from(
+                Rt::default()
+                    .with_dreg(Register::Reg1)
+                    .with_key(RtKey::Xfrm)
+            ),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [1u8])),
+        ]
+    );
+    assert_eq!(MetaType::SecPath as u32, 25);
+    assert_eq!(RtKey::Xfrm as u32, 4);
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_ct_states() {
+    let states = ConnTrackState::ESTABLISHED | ConnTrackState::RELATED;
+    let rule = get_test_rule().ct_states(states).unwrap().accept();
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..3],
+        [
+            ExpressionVariant::from(Conntrack::new(ConntrackKey::State)),
+            ExpressionVariant::from(Bitwise::new(6u32.to_ne_bytes(), 0u32.to_be_bytes()).unwrap()),
+            ExpressionVariant::from(Cmp::new(CmpOp::Neq, 0u32.to_be_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    let rule = get_test_rule()
+        .ct_states(ConnTrackState::NEW | ConnTrackState::INVALID | ConnTrackState::UNTRACKED)
+        .unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule)[1],
+        ExpressionVariant::from(Bitwise::new(73u32.to_ne_bytes(), 0u32.to_be_bytes()).unwrap())
+    );
+
+    assert_eq!(
+        get_test_rule().established().unwrap(),
+        get_test_rule()
+            .ct_states(ConnTrackState::ESTABLISHED)
+            .unwrap()
+    );
+}
+
+#[test]
+fn match_ct_status() {
+    let rule = get_test_rule().ct_expected().unwrap().accept();
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..3],
+        [
+            ExpressionVariant::from(Conntrack::new(ConntrackKey::Status)),
+            ExpressionVariant::from(Bitwise::new(1u32.to_ne_bytes(), 0u32.to_be_bytes()).unwrap()),
+            ExpressionVariant::from(Cmp::new(CmpOp::Neq, 0u32.to_be_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    let rule = get_test_rule()
+        .ct_status(ConnTrackStatus::SRC_NAT | ConnTrackStatus::DST_NAT)
+        .unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule)[1],
+        ExpressionVariant::from(Bitwise::new(48u32.to_ne_bytes(), 0u32.to_be_bytes()).unwrap())
+    );
+}
+
+#[test]
+fn match_time() {
+    let hton = Byteorder::default()
+        .with_sreg(Register::Reg1)
+        .with_dreg(Register::Reg1)
+        .with_op(ByteorderOp::HtoN)
+        .with_len(4u32)
+        .with_siz(4u32);
+
+    let rule = get_test_rule()
+        .hour_range(
+            Duration::from_secs(8 * 3600),
+            Duration::from_secs(18 * 3600),
+        )
+        .unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::TimeHour)),
+            ExpressionVariant::from(hton.clone()),
+            ExpressionVariant::from(Range::new(
+                RangeOp::Eq,
+                28800u32.to_be_bytes(),
+                64800u32.to_be_bytes()
+            )),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    // from 22:00 to 06:00, i.e. outside of 06:00:01 to 21:59:59
+    let rule = get_test_rule()
+        .hour_range(
+            Duration::from_secs(22 * 3600),
+            Duration::from_secs(6 * 3600),
+        )
+        .unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::TimeHour)),
+            ExpressionVariant::from(hton),
+            ExpressionVariant::from(Range::new(
+                RangeOp::Neq,
+                21601u32.to_be_bytes(),
+                79199u32.to_be_bytes()
+            )),
+        ]
+    );
+
+    assert!(matches!(
+        get_test_rule().hour_range(Duration::ZERO, Duration::from_secs(24 * 3600)),
+        Err(BuilderError::InvalidTimeOfDay)
+    ));
+
+    let rule = get_test_rule().weekday(Weekday::Saturday);
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::TimeDay)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [6u8])),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn match_ip_in_bridge_family() {
+    let table = Table::new(ProtocolFamily::Bridge).with_name(TABLE_NAME);
+    let chain = Chain::new(&table).with_name(CHAIN_NAME);
+    let rule = Rule::new(&chain)
+        .unwrap()
+        .saddr(Ipv4Addr::new(192, 168, 1, 1).into());
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..2],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::Protocol)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 0x0800u16.to_be_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    let rule = Rule::new(&chain)
+        .unwrap()
+        .snetwork(IpNetwork::new(Ipv6Addr::LOCALHOST.into(), 128).unwrap())
+        .unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule)[1],
+        ExpressionVariant::from(Cmp::new(CmpOp::Eq, 0x86ddu16.to_be_bytes()))
+    );
+
+    // the inet family keeps matching the netfilter protocol
+    let rule = get_test_rule().ether_type(EtherType::Arp).if_ipv4();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::Protocol)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 0x0806u16.to_be_bytes())),
+            ExpressionVariant::from(Meta::new(MetaType::NfProto)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, [libc::NFPROTO_IPV4 as u8])),
+        ]
+    );
+}
+
+#[test]
+fn match_and_set_priority() {
+    let rule = get_test_rule().priority(1, 0x10);
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::Priority)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 0x0001_0010u32.to_ne_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    let rule = get_test_rule().set_priority(0xabcd, 2);
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Immediate::new_data(
+                0xabcd_0002u32.to_ne_bytes().to_vec(),
+                Register::Reg1
+            )),
+            ExpressionVariant::from(
+                Meta::default()
+                    .with_key(MetaType::Priority)
+                    .with_sreg(Register::Reg1)
+            ),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn count_only() {
+    let rule = get_test_rule().count_only();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![ExpressionVariant::from(Counter::default())]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    // the rule has no verdict, and survives a round-trip through the kernel format
+    let mut buf = Vec::new();
+    let mut writer = NfNetlinkWriter::new(&mut buf);
+    rule.add_or_remove(&mut writer, MsgType::Add, 0);
+    assert_eq!(Rule::deserialize(&buf).unwrap().0, rule);
+}
+
+#[test]
+fn rule_diff() {
+    let rule = get_test_rule().dport(22, Protocol::TCP).accept();
+    let kernel_rule = rule.clone().with_handle(42u64).with_position(1u64);
+    assert_eq!(rule.diff(&kernel_rule), None);
+    assert!(rule.deep_eq(&kernel_rule));
+
+    let other = get_test_rule().dport(22, Protocol::TCP).drop();
+    let index = rule.expr_count() - 1;
+    match rule.diff(&other) {
+        Some(RuleDiff::Expression {
+            index: i,
+            left,
+            right,
+        }) => {
+            assert_eq!(i, index);
+            assert!(left.contains("Accept"));
+            assert!(right.contains("Drop"));
+        }
+        res => panic!("Expected a differing expression, got {:?}", res),
+    }
+    assert!(!rule.deep_eq(&other));
+
+    let longer = rule.clone().with_expr(Counter::default());
+    let diff = rule.diff(&longer).unwrap();
+    assert_eq!(diff, RuleDiff::ExpressionCount(index + 1, index + 2));
+    assert_eq!(
+        diff.to_string(),
+        format!("{} expressions != {} expressions", index + 1, index + 2)
+    );
+}
+
+#[test]
+fn reject_admin_prohibited() {
+    let rule = get_test_rule().reject().unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![ExpressionVariant::from(Reject::new(RejectCode::Icmpx(
+            IcmpCode::AdminProhibited
+        )))]
+    );
+
+    let table = Table::new(ProtocolFamily::Ipv6).with_name(TABLE_NAME);
+    let chain = Chain::new(&table).with_name(CHAIN_NAME);
+    let rule = Rule::new(&chain).unwrap().reject().unwrap();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![ExpressionVariant::from(
+            Reject::default()
+                .with_type(RejectType::IcmpUnreach)
+                .with_icmp_code(1u8)
+        )]
+    );
+
+    let table = Table::new(ProtocolFamily::Arp).with_name(TABLE_NAME);
+    let chain = Chain::new(&table).with_name(CHAIN_NAME);
+    assert!(matches!(
+        Rule::new(&chain).unwrap().reject(),
+        Err(BuilderError::InvalidRejectCode)
+    ));
+}
+
+#[test]
+fn rule_from_chain_name() {
+    let rule = Rule::in_chain(ProtocolFamily::Inet, TABLE_NAME, CHAIN_NAME).accept();
+    assert_eq!(rule, get_test_rule().accept());
+}
+
+#[test]
+fn match_cpu() {
+    let rule = get_test_rule().cpu(3).count_only();
+    assert_eq!(
+        get_test_rule_expressions(&rule),
+        vec![
+            ExpressionVariant::from(Meta::new(MetaType::Cpu)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 3u32.to_ne_bytes())),
+            ExpressionVariant::from(Counter::default()),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+}
+
+#[test]
+fn nat_chain_validation() {
+    let table = Table::new(ProtocolFamily::Ipv4).with_name(TABLE_NAME);
+    let prerouting = Chain::new(&table)
+        .with_name(CHAIN_NAME)
+        .with_type(ChainType::Nat)
+        .with_hook(Hook::new(HookClass::PreRouting, -100));
+    let postrouting = Chain::new(&table)
+        .with_name(CHAIN_NAME)
+        .with_type(ChainType::Nat)
+        .with_hook(Hook::new(HookClass::PostRouting, 100));
+    let filter = Chain::new(&table)
+        .with_name(CHAIN_NAME)
+        .with_type(ChainType::Filter)
+        .with_hook(Hook::new(HookClass::PreRouting, 0));
+    let regular = Chain::new(&table).with_name(CHAIN_NAME);
+
+    let dnat = Rule::new(&prerouting)
+        .unwrap()
+        .dnat(Ipv4Addr::new(10, 0, 0, 1).into(), Some(80))
+        .unwrap();
+    assert!(dnat.check_nat_chain(&prerouting).is_ok());
+    assert!(dnat.check_nat_chain(&regular).is_ok());
+    for chain in [&postrouting, &filter] {
+        assert!(matches!(
+            dnat.check_nat_chain(chain),
+            Err(BuilderError::InvalidChainForNat)
+        ));
+    }
+
+    let masquerade = Rule::new(&postrouting).unwrap().masquerade().unwrap();
+    assert!(masquerade.check_nat_chain(&postrouting).is_ok());
+    assert!(matches!(
+        masquerade.check_nat_chain(&prerouting),
+        Err(BuilderError::InvalidChainForNat)
+    ));
+
+    let snat = Rule::new(&postrouting)
+        .unwrap()
+        .snat(Ipv4Addr::new(10, 0, 0, 1).into(), None)
+        .unwrap();
+    assert!(snat.check_nat_chain(&postrouting).is_ok());
+    assert!(get_test_rule().accept().check_nat_chain(&filter).is_ok());
+
+    // the rules created in a base chain check their NAT helpers right away
+    assert!(matches!(
+        Rule::new(&postrouting)
+            .unwrap()
+            .dnat_range(Ipv4Addr::new(10, 0, 0, 1).into(), 8000, 8100),
+        Err(BuilderError::InvalidChainForNat)
+    ));
+    assert!(matches!(
+        Rule::new(&filter).unwrap().masquerade(),
+        Err(BuilderError::InvalidChainForNat)
+    ));
+    assert!(matches!(
+        Rule::new(&prerouting)
+            .unwrap()
+            .snat_range(Ipv4Addr::new(10, 0, 0, 1).into(), 1024, 2048),
+        Err(BuilderError::InvalidChainForNat)
+    ));
+    assert!(Rule::new(&regular).unwrap().masquerade().is_ok());
+
+    // the hooks of the NAT depend on its type
+    let untyped = get_test_rule().with_expr(Nat::default().with_family(ProtocolFamily::Ipv4));
+    assert!(matches!(
+        untyped.check_nat_chain(&postrouting),
+        Err(BuilderError::MissingNatType)
+    ));
+}
+
+#[test]
+fn match_bridge_names() {
+    let table = Table::new(ProtocolFamily::Bridge).with_name(TABLE_NAME);
+    let chain = Chain::new(&table).with_name(CHAIN_NAME);
+    let rule = Rule::new(&chain)
+        .unwrap()
+        .ibrname("br0")
+        .unwrap()
+        .iface("eth1")
+        .unwrap()
+        .obrname("br1")
+        .unwrap()
+        .accept();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..6],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::BriIifName)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, b"br0\0".to_vec())),
+            ExpressionVariant::from(Meta::new(MetaType::IifName)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, b"eth1\0".to_vec())),
+            ExpressionVariant::from(Meta::new(MetaType::BriOifName)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, b"br1\0".to_vec())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+
+    assert!(matches!(
+        get_test_rule().ibrname("a-bridge-name-too-long"),
+        Err(BuilderError::InterfaceNameTooLong)
+    ));
+}
+
+#[test]
+fn rule_matched_networks() {
+    let v4_net = IpNetwork::new(Ipv4Addr::new(192, 168, 0, 0).into(), 24).unwrap();
+    let v6_net = IpNetwork::new("2001:db8::".parse().unwrap(), 64).unwrap();
+    let mut buf = Vec::new();
+    let mut writer = NfNetlinkWriter::new(&mut buf);
+    get_test_rule()
+        .saddr(Ipv4Addr::new(10, 0, 0, 1).into())
+        .dnetwork(v4_net)
+        .unwrap()
+        .snetwork(v6_net)
+        .unwrap()
+        .accept()
+        .add_or_remove(&mut writer, MsgType::Add, 0);
+
+    let (rule, _) = Rule::deserialize(&buf).expect("Couldn't deserialize the rule");
+    assert_eq!(
+        rule.matched_networks(true),
+        vec![
+            IpNetwork::new(Ipv4Addr::new(10, 0, 0, 1).into(), 32).unwrap(),
+            v6_net
+        ]
+    );
+    assert_eq!(rule.matched_networks(false), vec![v4_net]);
+    assert_eq!(
+        get_test_rule()
+            .dport(22, Protocol::TCP)
+            .matched_networks(false),
+        vec![]
+    );
+
+    assert_eq!(
+        vec_to_ip(&ip_to_vec(Ipv6Addr::LOCALHOST.into())),
+        Some(Ipv6Addr::LOCALHOST.into())
+    );
+    assert_eq!(vec_to_ip(&[127, 0, 0, 1]), Some(Ipv4Addr::LOCALHOST.into()));
+    assert_eq!(vec_to_ip(&[0; 8]), None);
+}
+
+#[test]
+fn match_raw_ethertype() {
+    let table = Table::new(ProtocolFamily::NetDev).with_name(TABLE_NAME);
+    let chain = Chain::new(&table).with_name(CHAIN_NAME);
+    let rule = Rule::new(&chain)
+        .unwrap()
+        .ethertype(EtherType::PppoeSession as u16)
+        .unwrap()
+        .drop();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..2],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::Protocol)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, 0x8864u16.to_be_bytes())),
+        ]
+    );
+    assert!(rule.check_register_lengths().is_ok());
+    assert_eq!(
+        Rule::new(&chain).unwrap().ethertype(0x8847).unwrap(),
+        Rule::new(&chain)
+            .unwrap()
+            .ether_type(EtherType::MplsUnicast)
+    );
+
+    assert!(matches!(
+        get_test_rule().ethertype(0x88cc),
+        Err(BuilderError::MissingLinkLayerHeader)
+    ));
+}
+
+#[test]
+fn netdev_l3_protocol() {
+    let table = Table::new(ProtocolFamily::NetDev).with_name(TABLE_NAME);
+    let chain = Chain::new(&table).with_name(CHAIN_NAME);
+    let rule = Rule::new(&chain)
+        .unwrap()
+        .snetwork(IpNetwork::new([10, 0, 0, 0].into(), 8).unwrap())
+        .unwrap()
+        .drop();
+
+    assert_eq!(
+        get_test_rule_expressions(&rule)[..2],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::Protocol)),
+            ExpressionVariant::from(Cmp::new(CmpOp::Eq, (libc::ETH_P_IP as u16).to_be_bytes())),
+        ]
+    );
+    assert_eq!(
+        get_test_rule().l3_protocol(EtherType::Ipv6),
+        get_test_rule().ether_type(EtherType::Ipv6)
+    );
+}
+
+#[test]
+fn match_icmp_types_any_family() {
+    let mut batch = Batch::new();
+    let rule = get_test_rule()
+        .icmp_types_any_family(&[IcmpType::EchoRequest], &mut batch)
+        .unwrap()
+        .accept();
+
+    let exprs = get_test_rule_expressions(&rule);
+    assert_eq!(
+        exprs[..2],
+        [
+            ExpressionVariant::from(Meta::new(MetaType::L4Proto).with_dreg(Register::Reg32_00)),
+            ExpressionVariant::from(
+                Payload::raw(PayloadBase::Transport, 0, 1, Register::Reg32_01).unwrap()
+            ),
+        ]
+    );
+    let lookup = match &exprs[2] {
+        ExpressionVariant::Lookup(lookup) => lookup,
+        expr => panic!("Unexpected expression {:?}", expr),
+    };
+    assert_eq!(lookup.get_sreg(), Some(&Register::Reg32_00));
+
+    let (set, elements) = get_batch_set_and_elements(batch);
+    assert_eq!(lookup.get_set_id(), set.get_id());
+    assert_eq!(set.get_key_len(), Some(&8));
+
+    let keys: Vec<_> = elements
+        .get_elements()
+        .unwrap()
+        .iter()
+        .map(|elem| elem.get_key().unwrap().get_value().unwrap().clone())
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            vec![libc::IPPROTO_ICMP as u8, 0, 0, 0, 8, 0, 0, 0],
+            vec![libc::IPPROTO_ICMPV6 as u8, 0, 0, 0, 128, 0, 0, 0],
+        ]
+    );
+
+    assert!(matches!(
+        get_test_rule().icmp_types_any_family(&[], &mut Batch::new()),
+        Err(BuilderError::EmptySet)
+    ));
+}
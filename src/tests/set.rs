@@ -2,13 +2,23 @@ use std::net::{Ipv4Addr, Ipv6Addr};
 
 use crate::{
     data_type::DataType,
-    nlmsg::get_operation_from_nlmsghdr_type,
-    set::SetBuilder,
+    error::BuilderError,
+    expr::{Lookup, Register, Verdict, VerdictKind},
+    nlmsg::{
+        get_operation_from_nlmsghdr_type, AttributeDecoder, NfNetlinkAttribute,
+        NfNetlinkDeserializable, NfNetlinkObject, NfNetlinkWriter,
+    },
+    parser_impls::NfNetlinkData,
+    set::{
+        MapBuilder, Set, SetBuilder, SetDescription, SetElement, SetElementList, VerdictMapBuilder,
+    },
     sys::{
-        NFTA_DATA_VALUE, NFTA_LIST_ELEM, NFTA_SET_ELEM_KEY, NFTA_SET_ELEM_LIST_ELEMENTS,
-        NFTA_SET_ELEM_LIST_SET, NFTA_SET_ELEM_LIST_TABLE, NFTA_SET_KEY_LEN, NFTA_SET_KEY_TYPE,
-        NFTA_SET_NAME, NFTA_SET_TABLE, NFTA_SET_USERDATA, NFT_MSG_DELSET, NFT_MSG_NEWSET,
-        NFT_MSG_NEWSETELEM,
+        NFTA_DATA_VALUE, NFTA_LIST_ELEM, NFTA_SET_DESC, NFTA_SET_DESC_SIZE, NFTA_SET_ELEM_DATA,
+        NFTA_SET_ELEM_EXPIRATION, NFTA_SET_ELEM_KEY, NFTA_SET_ELEM_LIST_ELEMENTS,
+        NFTA_SET_ELEM_LIST_SET, NFTA_SET_ELEM_LIST_TABLE, NFTA_SET_ELEM_TIMEOUT, NFTA_SET_KEY_LEN,
+        NFTA_SET_KEY_TYPE, NFTA_SET_NAME, NFTA_SET_TABLE, NFTA_SET_USERDATA, NFT_DATA_VERDICT,
+        NFT_MSG_DELSET, NFT_MSG_NEWSET, NFT_MSG_NEWSETELEM, NFT_SET_ELEM_INTERVAL_END,
+        NFT_SET_INTERVAL, NFT_SET_MAP,
     },
     MsgType,
 };
@@ -43,6 +53,38 @@ fn new_empty_set() {
     );
 }
 
+#[test]
+fn set_description() {
+    let set =
+        get_test_set::<Ipv4Addr>().with_desc(SetDescription::default().with_max_elements(128u32));
+
+    assert_eq!(
+        set.to_bytes(),
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_SET_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_SET_NAME, SET_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_SET_KEY_TYPE, Ipv4Addr::TYPE.to_be_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_SET_KEY_LEN, Ipv4Addr::LEN.to_be_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_SET_DESC,
+                vec![NetlinkExpr::Final(
+                    NFTA_SET_DESC_SIZE,
+                    128u32.to_be_bytes().to_vec()
+                )]
+            ),
+            NetlinkExpr::Final(NFTA_SET_USERDATA, SET_USERDATA.as_bytes().to_vec()),
+        ])
+        .to_raw()
+    );
+
+    // the nested description is decoded into its own structure
+    let deserialized = Set::from_bytes(&set.to_bytes()).expect("Couldn't parse the attributes");
+    assert_eq!(
+        deserialized.get_desc().and_then(|d| d.get_max_elements()),
+        Some(&128)
+    );
+}
+
 #[test]
 fn delete_empty_set() {
     let mut set = get_test_set::<Ipv6Addr>();
@@ -117,3 +159,227 @@ fn new_set_with_data() {
         .to_raw()
     );
 }
+
+#[test]
+fn new_map_with_data() {
+    let ip = Ipv4Addr::new(10, 0, 0, 1);
+    let mark = 42u32;
+    let mut map_builder = MapBuilder::<Ipv4Addr, u32>::new(SET_NAME, &get_test_table())
+        .expect("Couldn't create a map");
+    map_builder.add(&ip, &mark);
+    let (map, mut elem_list) = map_builder.finish();
+
+    assert_eq!(map.get_flags(), Some(&NFT_SET_MAP));
+    assert_eq!(map.get_data_type(), Some(&u32::TYPE));
+    assert_eq!(map.get_data_len(), Some(&4));
+
+    let mut buf = Vec::new();
+    let (nlmsghdr, _nfgenmsg, raw_expr) = get_test_nlmsg(&mut buf, &mut elem_list);
+    assert_eq!(nlmsghdr.nlmsg_len, 80);
+
+    assert_eq!(
+        raw_expr,
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Final(NFTA_SET_ELEM_LIST_TABLE, TABLE_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_SET_ELEM_LIST_SET, SET_NAME.as_bytes().to_vec()),
+            NetlinkExpr::Nested(
+                NFTA_SET_ELEM_LIST_ELEMENTS,
+                vec![NetlinkExpr::Nested(
+                    NFTA_LIST_ELEM,
+                    vec![
+                        NetlinkExpr::Nested(
+                            NFTA_SET_ELEM_KEY,
+                            vec![NetlinkExpr::Final(NFTA_DATA_VALUE, ip.data())]
+                        ),
+                        NetlinkExpr::Nested(
+                            NFTA_SET_ELEM_DATA,
+                            vec![NetlinkExpr::Final(
+                                NFTA_DATA_VALUE,
+                                mark.to_ne_bytes().to_vec()
+                            )]
+                        ),
+                    ]
+                )]
+            ),
+        ])
+        .to_raw()
+    );
+
+    let lookup = Lookup::new_map(&map, Register::Reg2).unwrap();
+    assert_eq!(lookup.get_sreg(), Some(&Register::Reg1));
+    assert_eq!(lookup.get_dreg(), Some(&Register::Reg2));
+
+    let (set, _) = SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table())
+        .unwrap()
+        .finish();
+    assert!(matches!(
+        Lookup::new_map(&set, Register::Reg2),
+        Err(BuilderError::NotAMap)
+    ));
+}
+
+#[test]
+fn concatenated_keys() {
+    type Key = (Ipv4Addr, [u8; 2]);
+    assert_eq!(Key::TYPE, Ipv4Addr::TYPE << 6 | <[u8; 2]>::TYPE);
+    assert_eq!(Key::LEN, 8);
+    assert_eq!(
+        (Ipv4Addr::new(10, 0, 0, 1), [0u8, 22]).data(),
+        vec![10, 0, 0, 1, 0, 22, 0, 0]
+    );
+    assert_eq!(<(Ipv6Addr, Ipv4Addr, [u8; 1])>::LEN, 24);
+}
+
+#[test]
+fn new_verdict_map() {
+    let key = (Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2));
+    let mut map_builder =
+        VerdictMapBuilder::<(Ipv4Addr, Ipv4Addr)>::new(SET_NAME, &get_test_table())
+            .expect("Couldn't create a verdict map");
+    map_builder.add(&key, VerdictKind::Accept);
+    let (map, elem_list) = map_builder.finish();
+
+    assert_eq!(map.get_flags(), Some(&NFT_SET_MAP));
+    assert_eq!(map.get_key_len(), Some(&8));
+    assert_eq!(map.get_data_type(), Some(&NFT_DATA_VERDICT));
+    assert_eq!(map.get_data_len(), None);
+
+    let elements: Vec<_> = elem_list.get_elements().unwrap().iter().collect();
+    assert_eq!(elements.len(), 1);
+    assert_eq!(
+        elements[0].get_key().unwrap().get_value(),
+        Some(&key.data())
+    );
+    assert_eq!(
+        elements[0].get_data().unwrap().get_verdict(),
+        Some(&Verdict::from(VerdictKind::Accept))
+    );
+}
+
+#[test]
+fn set_element_comment() {
+    let ip = Ipv4Addr::new(10, 0, 0, 1);
+    let mut set_builder =
+        SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table()).expect("Couldn't create a set");
+    set_builder
+        .add_with_comment(&ip, "port scan")
+        .expect("Couldn't add an element with a comment");
+    set_builder.add(&Ipv4Addr::new(10, 0, 0, 2));
+    let (_set, elem_list) = set_builder.finish();
+
+    let mut buf = Vec::new();
+    let mut writer = NfNetlinkWriter::new(&mut buf);
+    elem_list.add_or_remove(&mut writer, MsgType::Add, 0);
+    let (elem_list, _) = SetElementList::deserialize(&buf).expect("Couldn't parse the elements");
+
+    let elements: Vec<_> = elem_list.get_elements().unwrap().iter().collect();
+    assert_eq!(
+        elements[0].get_userdata(),
+        Some(&b"\x00\x0aport scan\x00".to_vec())
+    );
+    assert_eq!(elements[0].get_comment().as_deref(), Some("port scan"));
+    assert_eq!(elements[1].get_comment(), None);
+
+    // the comment may lack its null terminator, or be truncated
+    let elem = SetElement::default().with_userdata(b"\x00\x03abc".to_vec());
+    assert_eq!(elem.get_comment().as_deref(), Some("abc"));
+    let elem = SetElement::default().with_userdata(b"\x00\x10abc".to_vec());
+    assert_eq!(elem.get_comment(), None);
+
+    assert!(matches!(
+        SetElement::default().with_comment("a\0b"),
+        Err(BuilderError::InvalidComment)
+    ));
+    assert!(matches!(
+        SetElement::default().with_comment(&"a".repeat(128)),
+        Err(BuilderError::InvalidComment)
+    ));
+}
+
+#[test]
+fn set_element_timeout() {
+    let elem = SetElement::default()
+        .with_key(NfNetlinkData::default().with_value(vec![10, 0, 0, 1]))
+        .with_timeout(60_000u64)
+        .with_expiration(42_000u64);
+    assert_eq!(
+        elem.to_bytes(),
+        NetlinkExpr::List(vec![
+            NetlinkExpr::Nested(
+                NFTA_SET_ELEM_KEY,
+                vec![NetlinkExpr::Final(NFTA_DATA_VALUE, vec![10, 0, 0, 1])]
+            ),
+            NetlinkExpr::Final(NFTA_SET_ELEM_TIMEOUT, 60_000u64.to_be_bytes().to_vec()),
+            NetlinkExpr::Final(NFTA_SET_ELEM_EXPIRATION, 42_000u64.to_be_bytes().to_vec()),
+        ])
+        .to_raw()
+    );
+    assert_eq!(SetElement::from_bytes(&elem.to_bytes()).unwrap(), elem);
+}
+
+#[test]
+fn interval_set_ranges() {
+    let mut set_builder = SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table())
+        .expect("Couldn't create a set")
+        .with_intervals();
+    set_builder
+        .add_range(&Ipv4Addr::new(10, 0, 0, 1), &Ipv4Addr::new(10, 0, 0, 9))
+        .expect("Couldn't add a range");
+    assert!(matches!(
+        set_builder.add_range(&Ipv4Addr::new(10, 0, 1, 0), &Ipv4Addr::new(10, 0, 0, 255)),
+        Err(BuilderError::InvertedRange)
+    ));
+    // the range reaching the largest address has no end element
+    set_builder
+        .add_range(&Ipv4Addr::new(255, 0, 0, 0), &Ipv4Addr::BROADCAST)
+        .expect("Couldn't add a range");
+    let (set, elem_list) = set_builder.finish();
+    assert_eq!(set.get_flags(), Some(&NFT_SET_INTERVAL));
+
+    let elements: Vec<_> = elem_list
+        .get_elements()
+        .unwrap()
+        .iter()
+        .map(|elem| {
+            (
+                elem.get_key().unwrap().get_value().unwrap().clone(),
+                elem.get_flags().copied(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        elements,
+        vec![
+            (vec![10, 0, 0, 1], None),
+            (vec![10, 0, 0, 10], Some(NFT_SET_ELEM_INTERVAL_END)),
+            (vec![255, 0, 0, 0], None),
+        ]
+    );
+}
+
+#[test]
+fn interval_set_element_comment() {
+    let mut set_builder = SetBuilder::<Ipv4Addr>::new(SET_NAME, &get_test_table())
+        .expect("Couldn't create a set")
+        .with_intervals();
+    set_builder
+        .add_with_comment(&Ipv4Addr::new(10, 0, 0, 1), "gateway")
+        .expect("Couldn't add an element with a comment");
+    let (_set, elem_list) = set_builder.finish();
+
+    // the key is added as a range holding only itself, whose start carries the comment
+    let elements: Vec<_> = elem_list.get_elements().unwrap().iter().collect();
+    assert_eq!(elements.len(), 2);
+    assert_eq!(
+        elements[0].get_key().unwrap().get_value(),
+        Some(&vec![10, 0, 0, 1])
+    );
+    assert_eq!(elements[0].get_flags(), None);
+    assert_eq!(elements[0].get_comment().as_deref(), Some("gateway"));
+    assert_eq!(
+        elements[1].get_key().unwrap().get_value(),
+        Some(&vec![10, 0, 0, 2])
+    );
+    assert_eq!(elements[1].get_flags(), Some(&NFT_SET_ELEM_INTERVAL_END));
+    assert_eq!(elements[1].get_comment(), None);
+}
@@ -1,7 +1,12 @@
 use crate::{
-    nlmsg::{get_operation_from_nlmsghdr_type, nft_nlmsg_maxsize, NfNetlinkDeserializable},
-    sys::{NFT_MSG_DELTABLE, NFT_MSG_NEWTABLE},
-    MsgType, Table,
+    error::{BuilderError, DecodeError},
+    nlmsg::{
+        get_operation_from_nlmsghdr_type, nft_nlmsg_maxsize, AttributeDecoder, NfNetlinkAttribute,
+        NfNetlinkDeserializable, NfNetlinkObject, WithUnknownAttributes,
+    },
+    set::SetBuilder,
+    sys::{NFT_MSG_DELTABLE, NFT_MSG_NEWTABLE, NLM_F_CREATE, NLM_F_EXCL},
+    Chain, MsgType, ProtocolFamily, Table,
 };
 
 use super::{
@@ -9,6 +14,20 @@ use super::{
     get_test_table_with_userdata_raw_expr, TABLE_USERDATA,
 };
 
+#[test]
+fn nfgenmsg_family() {
+    let mut table = Table::new(ProtocolFamily::Ipv6).with_name("mocktable6");
+    let mut buf = Vec::new();
+    let (_nlmsghdr, mut nfgenmsg, _raw_expr) = get_test_nlmsg(&mut buf, &mut table);
+    assert_eq!(nfgenmsg.family().unwrap(), ProtocolFamily::Ipv6);
+
+    nfgenmsg.nfgen_family = 42;
+    assert!(matches!(
+        nfgenmsg.family(),
+        Err(DecodeError::UnknownProtocolFamily(42))
+    ));
+}
+
 #[test]
 fn new_empty_table() {
     let mut table = get_test_table();
@@ -53,6 +72,27 @@ fn delete_empty_table() {
     assert_eq!(raw_expr, get_test_table_raw_expr().to_raw());
 }
 
+#[test]
+fn create_empty_table() {
+    let mut table = get_test_table();
+    let mut buf = Vec::with_capacity(nft_nlmsg_maxsize() as usize);
+    let (nlmsghdr, _nfgenmsg, raw_expr) =
+        get_test_nlmsg_with_msg_type(&mut buf, &mut table, MsgType::Create);
+    assert_eq!(
+        get_operation_from_nlmsghdr_type(nlmsghdr.nlmsg_type),
+        NFT_MSG_NEWTABLE as u8
+    );
+    assert_eq!(
+        nlmsghdr.nlmsg_flags & (NLM_F_CREATE | NLM_F_EXCL) as u16,
+        (NLM_F_CREATE | NLM_F_EXCL) as u16
+    );
+    assert_eq!(raw_expr, get_test_table_raw_expr().to_raw());
+
+    let mut buf = Vec::with_capacity(nft_nlmsg_maxsize() as usize);
+    let (nlmsghdr, _nfgenmsg, _raw_expr) = get_test_nlmsg(&mut buf, &mut table);
+    assert_eq!(nlmsghdr.nlmsg_flags & NLM_F_EXCL as u16, 0);
+}
+
 #[test]
 fn parse_table() {
     let mut table = get_test_table();
@@ -65,3 +105,102 @@ fn parse_table() {
     assert_eq!(table, deserialized_table);
     assert_eq!(remaining.len(), 0);
 }
+
+#[test]
+fn table_bytes_round_trip() {
+    let mut table = get_test_table();
+    table.set_userdata(TABLE_USERDATA.as_bytes().to_vec());
+
+    let bytes = table.to_bytes();
+    assert_eq!(bytes, get_test_table_with_userdata_raw_expr().to_raw());
+
+    // the family lives in the message header, not in the attributes
+    let deserialized_table = Table::from_bytes(&bytes)
+        .expect("Couldn't parse the attributes")
+        .with_family(ProtocolFamily::Inet);
+    assert_eq!(table, deserialized_table);
+}
+
+#[test]
+fn table_bytes_with_trailing_data() {
+    let mut bytes = get_test_table().to_bytes();
+    let consumed = bytes.len();
+    bytes.extend_from_slice(&[1, 2, 3]);
+
+    match Table::from_bytes(&bytes) {
+        Err(DecodeError::TrailingData { offset, len }) => {
+            assert_eq!(offset, consumed);
+            assert_eq!(len, 3);
+        }
+        res => panic!("Unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn parse_table_with_handle() {
+    let mut table = get_test_table().with_handle(42u64);
+    let mut buf = Vec::with_capacity(nft_nlmsg_maxsize() as usize);
+    get_test_nlmsg(&mut buf, &mut table);
+
+    let (deserialized_table, _) =
+        Table::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(deserialized_table.get_handle(), Some(&42));
+}
+
+#[test]
+fn parse_table_with_unknown_attribute() {
+    let mut table = get_test_table();
+    let mut buf = Vec::with_capacity(nft_nlmsg_maxsize() as usize);
+    get_test_nlmsg(&mut buf, &mut table);
+
+    // append an attribute of a type unknown to this crate, and update the message length
+    buf.extend_from_slice(&8u16.to_ne_bytes());
+    buf.extend_from_slice(&100u16.to_ne_bytes());
+    buf.extend_from_slice(&[1, 2, 3, 4]);
+    let len = buf.len() as u32;
+    buf[0..4].copy_from_slice(&len.to_ne_bytes());
+
+    // the attribute is skipped by default
+    let (deserialized_table, _) =
+        Table::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(table, deserialized_table);
+
+    let (deserialized, remaining) =
+        WithUnknownAttributes::<Table>::deserialize(&buf).expect("Couldn't deserialize the object");
+    assert_eq!(remaining.len(), 0);
+    assert_eq!(deserialized.object, table);
+    assert_eq!(
+        deserialized.unknown_attributes,
+        vec![(100, vec![1, 2, 3, 4])]
+    );
+    assert_eq!(
+        deserialized.get_unknown_attribute(100),
+        Some([1, 2, 3, 4].as_slice())
+    );
+    assert_eq!(deserialized.get_unknown_attribute(101), None);
+}
+
+#[test]
+fn table_and_chain_name_length() {
+    let name = "a".repeat(255);
+    let table = Table::named(ProtocolFamily::Inet, name.as_str()).expect("Valid table name");
+    assert_eq!(table.get_name(), Some(&name));
+    let chain = Chain::named(&table, name.as_str()).expect("Valid chain name");
+    assert_eq!(chain.get_name(), Some(&name));
+    assert!(SetBuilder::<u32>::new(name.as_str(), &table).is_ok());
+
+    // the kernel counts the null terminator
+    let name = "a".repeat(256);
+    assert!(matches!(
+        Table::named(ProtocolFamily::Inet, name.as_str()),
+        Err(BuilderError::NameTooLong)
+    ));
+    assert!(matches!(
+        Chain::named(&table, name.as_str()),
+        Err(BuilderError::NameTooLong)
+    ));
+    assert!(matches!(
+        SetBuilder::<u32>::new(name, &table),
+        Err(BuilderError::NameTooLong)
+    ));
+}